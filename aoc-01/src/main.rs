@@ -1,13 +1,11 @@
-use std::fs;
+use aoc_util::{load_input, parse_lines};
 
 const INCLUDE_FUEL_MASS: bool = true;
 
 fn main() {
-    let input = fs::read_to_string("aoc-01/input.txt").unwrap();
-    let lines = input.split("\n");
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     let mut total_fuel = 0.0;
-    for line in lines {
-        let mass = line.parse::<f64>().unwrap();
+    for mass in parse_lines::<f64>(&input).unwrap() {
         total_fuel += compute_fuel(mass);
     }
     println!("{}", total_fuel);
@@ -1,49 +1,42 @@
-use intcode::IntCode;
-use std::fs;
+use aoc_util::load_input;
+use intcode::{Constraint, IntCode, IntCodeSearch, SymbolicIntCode};
 
 const TARGET: isize = 19690720;
 
 fn main() {
-    let input = fs::read_to_string("aoc-02/input.txt").unwrap();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     part_01(&input);
     part_02(&input);
+    part_02_symbolic(&input);
 }
 
 fn part_01(input: &str) {
-    let mut data: Vec<isize> = input
-        .split(",")
-        .map(|x| x.parse::<isize>().unwrap())
-        .collect();
-    data[1] = 12;
-    data[2] = 2;
-    let mut processor = IntCode::from_vec(data);
-    println!("Part 1 answer: {:?}", processor.execute().unwrap());
+    let mut processor = IntCode::from_string(input);
+    processor.patch(&[(1, 12), (2, 2)]).unwrap();
+    processor.execute().unwrap();
+    println!("Part 1 answer: {:?}", processor.peek(0).unwrap());
 }
 
 fn part_02(input: &str) {
-    let raw: Vec<isize> = input
-        .split(",")
-        .map(|x| x.parse::<isize>().unwrap())
-        .collect();
-    let mut input1: isize = 0;
-    let mut input2: isize = 0;
-    loop {
-        let mut data = raw.clone();
-        data[1] = input1;
-        data[2] = input2;
-        println!("Part 2 trying input: {}, {}", input1, input2);
-        let mut processor = IntCode::from_vec(data);
-        let result = processor.execute();
-        if result.is_ok() && *result.unwrap().first == TARGET {
-            break;
-        }
-        if input1 >= 100 {
-            input1 = 0;
-            input2 += 1;
-        } else {
-            input1 += 1;
-        }
-    }
+    let search = IntCodeSearch::new(IntCode::from_string(input))
+        .vary(1, 0..=99)
+        .vary(2, 0..=99);
+    let result = search.find_first(|cpu, _| cpu.peek(0) == Ok(TARGET));
+    println!(
+        "Part 2 tried {} of {} inputs in {:?}",
+        result.stats.executed, result.stats.candidates, result.stats.elapsed
+    );
+    let answer = &result.matches[0].values;
+    println!("Part 2 answer: {}, {}", answer[0], answer[1]);
+}
 
-    println!("Part 2 answer: {}, {}", input1, input2);
+fn part_02_symbolic(input: &str) {
+    let mut processor = SymbolicIntCode::from_string(input);
+    let noun = processor.symbolic_cell(1, 0..=99);
+    let verb = processor.symbolic_cell(2, 0..=99);
+    let path = &processor.explore().unwrap()[0];
+    let output = path.cell(0).unwrap();
+    println!("Part 2 output: {}", output);
+    let answer = path.solve(&[Constraint::equals(output, TARGET)]).unwrap();
+    println!("Part 2 answer (solved): {}, {}", answer[noun], answer[verb]);
 }
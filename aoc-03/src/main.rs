@@ -1,14 +1,12 @@
-use aoc_util::Direction;
-use std::collections::HashMap;
+use aoc_util::{load_input, parse_lines, Direction, Point2, Segment, YAxis};
 use std::collections::HashSet;
-use std::fs;
+use std::str::FromStr;
 
 fn main() {
-    let input = fs::read_to_string("aoc-03/input.txt").unwrap();
-    let lines = input.split("\n");
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     let mut panel = WirePanel::new();
-    for line in lines {
-        panel.add_wire(Wire::from(line));
+    for wire in parse_lines::<Wire>(&input).unwrap() {
+        panel.add_wire(wire);
     }
     let nearest_collision = panel.get_nearest_collision();
     println!("Puzzle 3, part 1: {}", nearest_collision);
@@ -34,10 +32,8 @@ impl WirePanel {
 
     pub fn add_wire(&mut self, new_wire: Wire) {
         for wire in &self.wires {
-            let collisions: HashSet<_> = wire.locs.intersection(&new_wire.locs).collect();
-            for collision in collisions {
-                let &(x, y) = collision;
-                let dist = WirePanel::get_distance(x, y);
+            for collision in wire.collisions(&new_wire) {
+                let dist = collision.manhattan(&Point2::origin());
                 match self.nearest_collision {
                     Some(i) => {
                         if dist < i {
@@ -47,7 +43,7 @@ impl WirePanel {
                     None => self.nearest_collision = Some(dist),
                 }
 
-                let total_length = wire.length_at(x, y) + new_wire.length_at(x, y);
+                let total_length = wire.length_at(&collision) + new_wire.length_at(&collision);
                 match self.soonest_collision {
                     Some(i) => {
                         if total_length < i {
@@ -74,61 +70,61 @@ impl WirePanel {
             None => panic!(),
         }
     }
-
-    fn get_distance(x: i32, y: i32) -> i32 {
-        i32::abs(x) + i32::abs(y)
-    }
 }
 
 struct Wire {
-    pub locs: HashSet<(i32, i32)>,
-    loc_step: HashMap<(i32, i32), i32>,
+    /// Each straight run of the wire, with where it starts and the steps taken to get there.
+    runs: Vec<(Segment<i32>, Point2<i32>, i32)>,
+}
+
+impl FromStr for Wire {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Wire, String> {
+        let mut runs = Vec::new();
+        let mut start = Point2::origin();
+        let mut steps = 0;
+        for step in input.split(",") {
+            let (dir, len) = Wire::parse_step(step.trim())?;
+            let (dx, dy) = dir.offset(YAxis::GrowsUp);
+            let end = start + Point2::new(dx as i32, dy as i32) * len;
+            runs.push((Segment::new(start, end), start, steps));
+            start = end;
+            steps += len;
+        }
+        Ok(Wire { runs })
+    }
 }
 
 impl Wire {
-    pub fn from(input: &str) -> Wire {
-        let steps: Vec<(Direction, i32)> = input.split(",").map(|x| Wire::parse_step(x)).collect();
-        let mut locs = HashSet::new();
-        let mut loc_step = HashMap::new();
-        let mut x = 0;
-        let mut y = 0;
-        let mut s = 1;
-        for step in steps {
-            let mut i = step.1;
-            while i > 0 {
-                match step.0 {
-                    Direction::Right => x += 1,
-                    Direction::Left => x -= 1,
-                    Direction::Up => y += 1,
-                    Direction::Down => y -= 1,
+    /// The points where the wires meet, apart from where they both start.
+    pub fn collisions(&self, other: &Wire) -> HashSet<Point2<i32>> {
+        let mut points = HashSet::new();
+        for (a, _, _) in &self.runs {
+            for (b, _, _) in &other.runs {
+                if let Some(shared) = a.intersection(b) {
+                    points.extend(shared.points());
                 }
-                let loc = (x, y);
-                locs.insert(loc);
-                loc_step.insert(loc, s);
-                i -= 1;
-                s += 1;
             }
         }
-        Wire { locs, loc_step }
+        points.remove(&Point2::origin());
+        points
     }
 
-    pub fn length_at(&self, x: i32, y: i32) -> i32 {
-        match self.loc_step.get(&(x, y)) {
-            Some(i) => *i,
+    /// The steps along the wire to the first time it reaches `point`.
+    pub fn length_at(&self, point: &Point2<i32>) -> i32 {
+        match self.runs.iter().find(|(run, _, _)| run.contains(point)) {
+            Some((_, start, steps)) => steps + start.manhattan(point),
             None => panic!(),
         }
     }
 
-    fn parse_step(input: &str) -> (Direction, i32) {
-        let dir = match input.chars().next().unwrap() {
-            'R' => Direction::Right,
-            'L' => Direction::Left,
-            'U' => Direction::Up,
-            'D' => Direction::Down,
-            _ => panic!(),
-        };
-        let len = &input[1..].parse::<i32>().unwrap();
-
-        return (dir, *len);
+    /// Splits a step like `R75` into its direction and length.
+    fn parse_step(input: &str) -> Result<(Direction, i32), String> {
+        let invalid = || format!("invalid step {:?}", input);
+        let split = input.char_indices().nth(1).map_or(input.len(), |(i, _)| i);
+        let dir = input[..split].parse::<Direction>().map_err(|_| invalid())?;
+        let len = input[split..].parse::<i32>().map_err(|_| invalid())?;
+        Ok((dir, len))
     }
 }
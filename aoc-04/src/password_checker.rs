@@ -1,3 +1,5 @@
+use aoc_util::{run_lengths, DigitOrder, ToDigits};
+
 pub struct PasswordChecker {
   password: i32,
   end: i32,
@@ -28,48 +30,16 @@ impl PasswordChecker {
   }
 
   fn validate_adjacent_duplicate(&self) -> bool {
-    let mut prev: Option<i32> = None;
-    let mut count = vec![1];
-    for digit in self.digits() {
-      if let Some(i) = prev {
-        if i == digit {
-          let len = count.len();
-          count[len - 1] += 1;
-        } else {
-          count.push(1);
-        }
-      }
-      prev = Some(digit);
-    }
-    for c in count {
-      if c == 2 {
-        return true;
-      }
-    }
-    false
+    run_lengths(&self.digits())
+      .iter()
+      .any(|(_, count)| *count == 2)
   }
 
   fn validate_ascending(&self) -> bool {
-    let mut prev: Option<i32> = None;
-    for digit in self.digits() {
-      if let Some(i) = prev {
-        if i > digit {
-          return false;
-        }
-      }
-      prev = Some(digit);
-    }
-    true
+    self.digits().windows(2).all(|pair| pair[0] <= pair[1])
   }
 
   fn digits(&self) -> Vec<i32> {
-    let mut i = self.password;
-    let mut d = Vec::new();
-    while i >= 1 {
-      d.push(i % 10);
-      i /= 10;
-    }
-    d.reverse();
-    d
+    self.password.digits_in(10, DigitOrder::BigEndian)
   }
 }
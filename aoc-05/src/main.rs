@@ -1,14 +1,24 @@
+use aoc_util::load_input;
 use intcode::IntCode;
-use std::fs;
+use std::error::Error;
+use std::process;
 
 fn main() {
-    let input = fs::read_to_string("aoc-05/input.txt").unwrap();
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let input = load_input(env!("CARGO_MANIFEST_DIR"))?;
     let mut processor = IntCode::from_string(&input);
     processor.input(&vec![1]);
-    let result = processor.execute().unwrap();
+    let result = processor.execute()?;
     println!("Part 1: {:?}", result.output);
     let mut processor2 = IntCode::from_string(&input);
     processor2.input(&vec![5]);
-    let result2 = processor2.execute().unwrap();
+    let result2 = processor2.execute()?;
     println!("Part 2: {:?}", result2.output);
+    Ok(())
 }
@@ -1,11 +1,10 @@
-use std::collections::HashMap;
-use std::fs;
+use aoc_util::{load_input, parse_key_values, Tree};
 
 const ME: &str = "YOU";
 const SAN: &str = "SAN";
 
 fn main() {
-    let input = fs::read_to_string("aoc-06/input.txt").unwrap();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     let map = OrbitMap::from(&input);
 
     let chksum = map.checksum();
@@ -17,21 +16,16 @@ fn main() {
 }
 
 struct OrbitMap<'a> {
-    objects: HashMap<&'a str, Orbiter<'a>>,
+    objects: Tree<&'a str>,
 }
 
 impl<'a> OrbitMap<'a> {
     pub fn from(input: &'a str) -> OrbitMap<'a> {
         let mut map = OrbitMap {
-            objects: HashMap::new(),
+            objects: Tree::new(),
         };
 
-        let lines = input.split("\n");
-        for line in lines {
-            let objs: Vec<&str> = line.split(")").collect();
-            assert_eq!(objs.len(), 2);
-            let parent = objs[0];
-            let child = objs[1];
+        for (parent, child) in parse_key_values(input, ")").unwrap() {
             map.insert(parent, child);
         }
 
@@ -39,66 +33,19 @@ impl<'a> OrbitMap<'a> {
     }
 
     pub fn insert(&mut self, parent: &'a str, child: &'a str) {
-        let parent_orbiter = self.objects.entry(parent).or_default();
-        parent_orbiter.children.push(child);
-        let mut child_orbiter = self.objects.entry(child).or_default();
-        child_orbiter.parent = Some(parent);
+        self.objects.add_child(parent, child);
     }
 
     pub fn checksum(&self) -> i32 {
         let mut result = 0;
-        for (key, _) in self.objects.iter() {
-            result += self.get_chain(key).len() as i32 - 1;
+        for key in self.objects.nodes() {
+            result += self.objects.depth(key) as i32;
         }
         result
     }
 
     pub fn find_path(&self, start: &'a str, end: &'a str) -> Vec<&'a str> {
-        let start_chain = self.get_chain(start);
-        let end_chain = self.get_chain(end);
-        let (item, start_idx, end_idx) = self.first_common_object(&start_chain, &end_chain);
-        let mut chain: Vec<&'a str> = Vec::new();
-        chain.extend(start_chain.split_at(start_idx).0.iter().map(|x| *x));
-        chain.push(item);
-        chain.extend(end_chain.split_at(end_idx).0.iter().rev().map(|x| *x));
-        chain
-    }
-
-    fn first_common_object(
-        &self,
-        start: &Vec<&'a str>,
-        end: &Vec<&'a str>,
-    ) -> (&'a str, usize, usize) {
-        for (index, item) in start.iter().enumerate() {
-            if let Some(i) = end.iter().position(|x| x == item) {
-                return (item, index, i);
-            }
-        }
-        panic!("No common objects");
-    }
-
-    fn get_chain(&self, key: &'a str) -> Vec<&'a str> {
-        match self.objects.get(key).unwrap().parent {
-            None => vec![key],
-            Some(i) => {
-                let mut y = vec![key];
-                y.append(&mut self.get_chain(i));
-                y
-            }
-        }
-    }
-}
-
-struct Orbiter<'a> {
-    parent: Option<&'a str>,
-    children: Vec<&'a str>,
-}
-
-impl<'a> Default for Orbiter<'a> {
-    fn default() -> Self {
-        Orbiter {
-            parent: None,
-            children: Vec::new(),
-        }
+        let path = self.objects.path(&start, &end).expect("No common objects");
+        path.into_iter().cloned().collect()
     }
 }
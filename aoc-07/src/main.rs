@@ -1,18 +1,16 @@
-use aoc_util::Permutation;
+use aoc_util::{load_input, Permutations};
 use intcode::{IntCode, IntCodeResultKind};
-use std::fs;
 
 fn main() {
-    let input = fs::read_to_string("aoc-07/input.txt").unwrap();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     series(&input);
     feedback(&input);
 }
 
 fn series(input: &str) {
-    let permutations: Vec<Vec<isize>> = Permutation::calculate(&vec![0, 1, 2, 3, 4]);
     let mut largest: isize = 0;
 
-    for p in permutations {
+    for p in Permutations::new(vec![0, 1, 2, 3, 4]) {
         let mut c = spawn_cpu(input);
         let mut out: isize = 0;
 
@@ -32,10 +30,9 @@ fn series(input: &str) {
 }
 
 fn feedback(input: &str) {
-    let permutations: Vec<Vec<isize>> = Permutation::calculate(&vec![9, 8, 7, 6, 5]);
     let mut largest: isize = 0;
 
-    for p in permutations {
+    for p in Permutations::new(vec![9, 8, 7, 6, 5]) {
         let mut c = spawn_cpu(input);
 
         for (idx, p) in p.iter().enumerate() {
@@ -1,10 +1,10 @@
-use std::fs;
+use aoc_util::load_input;
 
 const WIDTH: u32 = 25;
 const HEIGHT: u32 = 6;
 
 fn main() {
-    let input = fs::read_to_string("aoc-08/input.txt").unwrap();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     let image = Image::from_string(WIDTH, HEIGHT, &input);
     validation(&image);
     decode(&image);
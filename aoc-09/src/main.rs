@@ -1,16 +1,26 @@
+use aoc_util::load_input;
 use intcode::IntCode;
-use std::fs;
+use std::error::Error;
+use std::process;
 
 fn main() {
-    let input = fs::read_to_string("aoc-09/input.txt").unwrap();
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let input = load_input(env!("CARGO_MANIFEST_DIR"))?;
     let mut processor = IntCode::from_string(&input);
     let mut processor2 = processor.clone();
 
     processor.input(&vec![1]);
-    let result = processor.execute();
-    println!("Test mode: {:?}", result);
+    let result = processor.execute()?;
+    println!("Test mode: {:?}", result.output);
 
     processor2.input(&vec![2]);
-    let result = processor2.execute();
-    println!("Sensor boost mode: {:?}", result);
+    let result = processor2.execute()?;
+    println!("Sensor boost mode: {:?}", result.output);
+    Ok(())
 }
@@ -3,12 +3,12 @@ pub mod char_enum;
 pub mod point_util;
 pub mod space_map;
 
+use aoc_util::load_input;
 use asteroid_field_point::AsteroidFieldPoint;
 use space_map::SpaceMap;
-use std::fs;
 
 fn main() {
-    let input = fs::read_to_string("aoc-10/input.txt").unwrap();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     let mut map = SpaceMap::<AsteroidFieldPoint>::from_string(&input);
     let max = map.max_visible();
     println!("Most visible (count, x, y): {:?}", max);
@@ -1,10 +1,11 @@
 use super::char_enum::CharEnum;
+use aoc_util::Grid;
 
 pub struct SpaceMap<T>
 where
   T: CharEnum,
 {
-  map_data: Vec<Vec<T>>,
+  map_data: Grid<T>,
 }
 
 impl<T> SpaceMap<T>
@@ -12,47 +13,26 @@ where
   T: CharEnum + PartialEq,
 {
   pub fn from_string(input: &str) -> Self {
-    let map_data = input
-      .lines()
-      .map(|line| line.chars().map(|c| T::from_char(&c).unwrap()).collect())
-      .collect();
+    let map_data = Grid::parse(input, |c| T::from_char(&c).ok()).unwrap();
     SpaceMap { map_data }
   }
 
   pub fn print(&self) {
-    let map_str = self.map_data.iter().fold(String::from("\n"), |mut acc, y| {
-      let line = y.iter().fold(String::from(" "), |mut iacc, x| {
-        iacc.push(x.to_char());
-        iacc
-      });
-      acc.push_str(&line);
-      acc.push('\n');
-      acc
-    });
-    println!("{}", map_str);
+    let map_str = self.map_data.render(|x| x.to_char());
+    let lines: Vec<String> = map_str.lines().map(|line| format!(" {}", line)).collect();
+    println!("\n{}\n", lines.join("\n"));
   }
 
   pub fn set_point(&mut self, point: &(usize, usize), value: T) {
-    self.map_data[point.1][point.0] = value;
+    self.map_data.set(point.0, point.1, value);
   }
 
   pub fn at_point(&self, point: &(usize, usize)) -> &T {
-    &self.map_data[point.1][point.0]
+    &self.map_data[*point]
   }
 
   pub fn points_one_of(&self, types: Vec<T>) -> Vec<(usize, usize)> {
-    let mut result = Vec::<(usize, usize)>::new();
-    for y in 0..self.map_data.len() {
-      for x in 0..self.map_data[y].len() {
-        let p = (x, y);
-        for t in &types {
-          if self.at_point(&p) == t {
-            result.push(p);
-            break;
-          }
-        }
-      }
-    }
-    result
+    let points = self.map_data.iter().filter(|(_, t)| types.contains(t));
+    points.map(|(p, _)| p).collect()
   }
 }
@@ -1,10 +1,8 @@
-use aoc_util::Direction;
+use aoc_util::{load_input, Direction, Point2, SparseGrid};
 use intcode::{IntCode, IntCodeResultKind};
-use std::collections::HashMap;
-use std::fs;
 
 fn main() {
-    let input = fs::read_to_string("aoc-11/input.txt").unwrap();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     let mut robot = HullPaintRobot::new(&input);
     let paint = robot.execute();
     println!("Tiles painted: {}", paint.len());
@@ -49,8 +47,8 @@ impl From<&HullPaintColor> for char {
 }
 
 pub struct HullPaintRobot {
-    painted: HashMap<(i8, i8), HullPaintColor>,
-    location: (i8, i8),
+    painted: SparseGrid<HullPaintColor>,
+    location: Point2<isize>,
     direction: Direction,
     processor: IntCode,
 }
@@ -58,27 +56,22 @@ pub struct HullPaintRobot {
 impl HullPaintRobot {
     pub fn new(program: &str) -> Self {
         HullPaintRobot {
-            painted: HashMap::new(),
-            location: (0, 0),
+            painted: SparseGrid::new(),
+            location: Point2::origin(),
             direction: Direction::Up,
             processor: IntCode::from_string(program),
         }
     }
 
-    pub fn execute(&mut self) -> &HashMap<(i8, i8), HullPaintColor> {
-        let mut out_idx = 0;
+    pub fn execute(&mut self) -> &SparseGrid<HullPaintColor> {
         loop {
             let input: isize = self.color_at_location().into();
             self.processor.input(&vec![input]);
             let result = self.processor.execute().unwrap();
-            let o1 = result.output[out_idx];
-            let o2 = result.output[out_idx + 1];
-            let k = result.kind;
-            self.paint(o1.into());
-            self.rotate(o2);
+            self.paint(result.output[0].into());
+            self.rotate(result.output[1]);
             self.advance();
-            out_idx = out_idx + 2;
-            if let IntCodeResultKind::Halt = k {
+            if let IntCodeResultKind::Halt = result.kind {
                 break;
             }
         }
@@ -86,32 +79,21 @@ impl HullPaintRobot {
     }
 
     pub fn print(&self) {
-        let (x1, x2, y1, y2) = self.bounds();
-        let s = (*y1..*y2 + 1).fold(String::from("\n"), |mut yacc, y| {
-            let row = (*x1..*x2 + 1).fold(String::from(" "), |mut xacc, x| {
-                xacc.push(
-                    self.painted
-                        .get(&(x, y))
-                        .unwrap_or(&HullPaintColor::Black)
-                        .into(),
-                );
-                xacc
-            });
-            yacc.push_str(&row);
-            yacc.push('\n');
-            yacc
-        });
-        println!("{}", s);
+        let hull = self
+            .painted
+            .render(|c| c.unwrap_or(&HullPaintColor::Black).into());
+        let rows: Vec<String> = hull.lines().map(|row| format!(" {}", row)).collect();
+        println!("\n{}\n", rows.join("\n"));
     }
 
     fn color_at_location(&self) -> &HullPaintColor {
         self.painted
-            .get(&self.location)
+            .get(self.location.x, self.location.y)
             .unwrap_or(&HullPaintColor::Black)
     }
 
     pub fn paint(&mut self, input: HullPaintColor) {
-        self.painted.insert(self.location, input);
+        self.painted.insert(self.location.x, self.location.y, input);
     }
 
     fn rotate(&mut self, input: isize) {
@@ -123,21 +105,6 @@ impl HullPaintRobot {
     }
 
     fn advance(&mut self) {
-        self.location = match self.direction {
-            Direction::Up => (self.location.0, self.location.1 - 1),
-            Direction::Down => (self.location.0, self.location.1 + 1),
-            Direction::Left => (self.location.0 - 1, self.location.1),
-            Direction::Right => (self.location.0 + 1, self.location.1),
-        }
-    }
-
-    fn bounds(&self) -> (&i8, &i8, &i8, &i8) {
-        let x_range = self.painted.keys().map(|(x, _y)| x);
-        let y_range = self.painted.keys().map(|(_x, y)| y);
-        let x1 = x_range.clone().min().unwrap();
-        let x2 = x_range.max().unwrap();
-        let y1 = y_range.clone().min().unwrap();
-        let y2 = y_range.max().unwrap();
-        (x1, x2, y1, y2)
+        self.location = self.location.step(&self.direction, 1);
     }
 }
@@ -1,9 +1,8 @@
-use aoc_util::LCM;
+use aoc_util::{lcm_all, load_input, Pattern};
 use std::cmp::Ordering;
-use std::fs;
 
 fn main() {
-    let input = fs::read_to_string("aoc-12/input.txt").unwrap();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
     let mut system = MoonSystem::from_string(&input);
 
     system.step_until(|x| x.step_count == 1000);
@@ -23,7 +22,8 @@ fn main() {
         "x_period: {}, y_period: {}, z_period: {}",
         x_period, y_period, z_period
     );
-    println!("lcm: {}", x_period.lcm(y_period).lcm(z_period));
+    let lcm = lcm_all(vec![x_period, y_period, z_period]).expect("lcm overflowed");
+    println!("lcm: {}", lcm);
 }
 
 type Coord3 = [i16; 3];
@@ -79,19 +79,10 @@ impl MoonSystem {
     }
 
     pub fn from_string(input: &str) -> Self {
-        let input = input
-            .replace("<x=", "")
-            .replace(" y=", "")
-            .replace(" z=", "")
-            .replace(">", "");
+        let pattern = Pattern::new("<x={}, y={}, z={}>");
         let mut system = MoonSystem::new();
-        for line in input.lines() {
-            let mut coords = line.split(",").map(|x| x.parse::<i16>().unwrap());
-            let pos = Position([
-                coords.next().unwrap(),
-                coords.next().unwrap(),
-                coords.next().unwrap(),
-            ]);
+        for coords in pattern.parse_lines::<i16>(input).unwrap() {
+            let pos = Position([coords[0], coords[1], coords[2]]);
             let moon = Moon::new(pos);
             system.add_moon(moon);
         }
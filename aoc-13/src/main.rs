@@ -1,9 +1,9 @@
+use aoc_util::{load_input, Grid};
 use crossterm::event::{poll, read, Event, KeyCode};
 use crossterm::ErrorKind as CrosstermErrorKind;
-use intcode::{IntCode, IntCodeResultKind};
+use intcode::{IntCode, IntCodeRecording, IntCodeResultKind};
 use std::cmp::Ordering;
 use std::fmt;
-use std::fs;
 use std::mem::discriminant;
 use std::thread::sleep;
 use std::time::Duration;
@@ -11,52 +11,86 @@ use std::time::Duration;
 const INSERT_QUARTERS: bool = true;
 const AUTO_PLAY: bool = true;
 const FRAME_DURATION: u64 = 150;
+/// With `RECORD`, the game is saved here, so a good run can be watched again with `REPLAY`.
+const RECORDING: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/session.txt");
+const RECORD: bool = false;
+const REPLAY: bool = false;
 
 fn main() {
-    let input = fs::read_to_string("aoc-13/input.txt").unwrap();
-    let mut data: Vec<isize> = input
-        .split(",")
-        .map(|x| x.parse::<isize>().unwrap())
-        .collect();
+    let input = load_input(env!("CARGO_MANIFEST_DIR")).unwrap();
+    let mut cpu = IntCode::from_string(&input);
     if INSERT_QUARTERS {
-        data[0] = 2;
+        cpu.poke(0, 2).unwrap();
     }
-    let mut cab = ArcadeCabinet::from_vec(data);
+    if REPLAY {
+        let recording = IntCodeRecording::load(RECORDING).unwrap();
+        // Check the whole game replays before showing it.
+        recording.replay(cpu.clone()).unwrap();
+        let mut cab = ArcadeCabinet::new(cpu);
+        cab.show_replay(&recording.inputs());
+        return;
+    }
+    if RECORD {
+        cpu.start_recording();
+    }
+    let mut cab = ArcadeCabinet::new(cpu);
     cab.run(&AUTO_PLAY);
+    if let Some(recording) = cab.cpu.stop_recording() {
+        if let Err(e) = recording.save(RECORDING) {
+            eprintln!("Could not save the recording to {}: {}", RECORDING, e);
+        }
+    }
 }
 
 struct ArcadeCabinet {
     cpu: IntCode,
-    output_index: usize,
-    screen: [[ArcadeCabinetTile; 40]; 26],
+    screen: Grid<ArcadeCabinetTile>,
     score: usize,
 }
 
 impl ArcadeCabinet {
-    pub fn from_vec(input: Vec<isize>) -> Self {
-        let mut cpu = IntCode::from_vec(input);
-        cpu.input(&vec![0]);
+    pub fn new(cpu: IntCode) -> Self {
         let cab = ArcadeCabinet {
             cpu,
-            output_index: 0,
-            screen: [[ArcadeCabinetTile::Empty; 40]; 26],
+            screen: Grid::new(40, 26, ArcadeCabinetTile::Empty),
             score: 0,
         };
         cab
     }
 
     pub fn run(&mut self, autoplay: &bool) {
-        while let IntCodeResultKind::Yield = self.cpu.execute().unwrap().kind {
-            self.update_state();
+        self.cpu.input(&vec![0]);
+        loop {
+            let result = self.cpu.execute().unwrap();
+            self.update_state(&result.output);
             self.print();
+            if let IntCodeResultKind::Halt = result.kind {
+                break;
+            }
             let input = match autoplay {
                 false => self.get_user_input().unwrap(),
                 true => self.get_auto_input(),
             };
             self.cpu.input(&vec![input])
         }
-        self.update_state();
-        self.print();
+        self.print_final_score();
+    }
+
+    /// Plays back a recorded game frame by frame, feeding in its recorded `inputs`.
+    pub fn show_replay(&mut self, inputs: &[isize]) {
+        for input in inputs {
+            self.cpu.input(&vec![*input]);
+            let result = self.cpu.execute().unwrap();
+            self.update_state(&result.output);
+            self.print();
+            if let IntCodeResultKind::Halt = result.kind {
+                break;
+            }
+        }
+        self.print_final_score();
+    }
+
+    fn print_final_score(&self) {
         println!(" ▬▬▬▬▬▬▬▬▬ Final Score ● {:0>5} ▬▬▬▬▬▬▬▬▬▬", self.score);
     }
 
@@ -66,37 +100,26 @@ impl ArcadeCabinet {
             " ██Left: {:0>3}███████████████Score: {:0>5}██",
             tiles_left, self.score
         );
-        let out = self.screen.iter().fold(String::from(""), |mut acc, row| {
-            let r = row.iter().fold(String::from(" "), |mut acc, tile| {
-                acc.push(char::from(*tile));
-                acc
-            });
-            acc.push_str(&r);
-            acc.push('\n');
-            acc
-        });
-        println!("{}", out);
+        let out = self.screen.render(|tile| char::from(*tile));
+        for row in out.lines() {
+            println!(" {}", row);
+        }
+        println!();
         sleep(Duration::from_millis(FRAME_DURATION));
     }
 
-    fn count_tiles(&self, tile: ArcadeCabinetTile) -> u32 {
-        self.screen.iter().fold(0, |acc, row| {
-            acc + row.iter().fold(0, |acc, t| match tile == *t {
-                true => acc + 1,
-                false => acc,
-            })
-        })
+    fn count_tiles(&self, tile: ArcadeCabinetTile) -> usize {
+        self.screen.iter().filter(|(_, t)| **t == tile).count()
     }
 
-    fn update_state(&mut self) {
-        let output = self.cpu.get_output();
-        while self.output_index < output.len() {
-            let slc = &output[self.output_index..self.output_index + 3];
+    fn update_state(&mut self, output: &[isize]) {
+        for slc in output.chunks(3) {
             if let [x, y, t] = slc {
                 if *x == -1 && *y == 0 {
                     self.score = *t as usize;
                 } else {
-                    self.screen[*y as usize][*x as usize] = ArcadeCabinetTile::from(*t);
+                    self.screen
+                        .set(*x as usize, *y as usize, ArcadeCabinetTile::from(*t));
                 }
             } else {
                 panic!(
@@ -104,7 +127,6 @@ impl ArcadeCabinet {
                     slc
                 )
             }
-            self.output_index = self.output_index + 3;
         }
     }
 
@@ -125,25 +147,23 @@ impl ArcadeCabinet {
     }
 
     fn get_auto_input(&self) -> isize {
-        let ball_x = self
-            .screen
-            .iter()
-            .find_map(|x| x.iter().position(|ix| *ix == ArcadeCabinetTile::Ball))
-            .unwrap();
-        let paddle_x = self
-            .screen
-            .iter()
-            .find_map(|x| {
-                x.iter()
-                    .position(|ix| *ix == ArcadeCabinetTile::PaddleHorizontal)
-            })
-            .unwrap();
+        let ball_x = self.find_tile(ArcadeCabinetTile::Ball);
+        let paddle_x = self.find_tile(ArcadeCabinetTile::PaddleHorizontal);
         match paddle_x.partial_cmp(&ball_x).unwrap() {
             Ordering::Less => 1,
             Ordering::Equal => 0,
             Ordering::Greater => -1,
         }
     }
+
+    /// The x position of the first `tile` on the screen.
+    fn find_tile(&self, tile: ArcadeCabinetTile) -> usize {
+        self.screen
+            .iter()
+            .find(|(_, t)| **t == tile)
+            .map(|((x, _), _)| x)
+            .unwrap()
+    }
 }
 
 #[derive(Copy, Clone)]
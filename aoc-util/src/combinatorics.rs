@@ -0,0 +1,713 @@
+//! Lazy iterators over arrangements of items, each yielding a new `Vec` of cloned items per
+//! step so only one arrangement is held in memory at a time.
+//!
+//! Iterators that visit their arrangements in a fixed order that can be indexed directly
+//! (`LexicographicPermutations`, `Combinations` and `Product`) can also be `split` into
+//! independent chunks, to share the work between threads.
+
+/// `n!`, or `None` if it doesn't fit in a `usize`.
+fn factorial(n: usize) -> Option<usize> {
+  (1..=n).try_fold(1usize, |acc, i| acc.checked_mul(i))
+}
+
+/// `n! / (n - k)!`, the number of ordered selections of `k` out of `n` items.
+fn falling_factorial(n: usize, k: usize) -> Option<usize> {
+  match k > n {
+    true => Some(0),
+    false => (n - k + 1..=n).try_fold(1usize, |acc, i| acc.checked_mul(i)),
+  }
+}
+
+/// `n choose k`, or `None` if it doesn't fit in a `usize`.
+fn binomial(n: usize, k: usize) -> Option<usize> {
+  if k > n {
+    return Some(0);
+  }
+  let k = k.min(n - k);
+  (0..k).try_fold(1usize, |acc, i| Some(acc.checked_mul(n - i)? / (i + 1)))
+}
+
+fn select<T: Clone>(items: &[T], indices: &[usize]) -> Vec<T> {
+  indices.iter().map(|i| items[*i].clone()).collect()
+}
+
+/// The `size_hint` of an iterator with `remaining` items left, where `None` means too many to
+/// count.
+fn size_hint(remaining: Option<usize>) -> (usize, Option<usize>) {
+  match remaining {
+    Some(n) => (n, Some(n)),
+    None => (usize::MAX, None),
+  }
+}
+
+/// The ranges of ranks covered by each of `parts` chunks of `total` items.
+fn chunks(total: usize, parts: usize) -> Vec<(usize, usize)> {
+  let parts = parts.max(1).min(total.max(1));
+  let (size, extra) = (total / parts, total % parts);
+  let mut start = 0;
+  (0..parts)
+    .map(|part| {
+      let len = size + (part < extra) as usize;
+      start += len;
+      (start - len, len)
+    })
+    .collect()
+}
+
+/// Every ordering of `items` in the order Heap's algorithm produces them, where each
+/// permutation differs from the one before by a single swap. The fastest way to visit every
+/// permutation when the order doesn't matter.
+#[derive(Debug, Clone)]
+pub struct Permutations<T> {
+  items: Vec<T>,
+  counters: Vec<usize>,
+  position: usize,
+  first: bool,
+  remaining: Option<usize>,
+}
+
+impl<T: Clone> Permutations<T> {
+  pub fn new(items: Vec<T>) -> Self {
+    Permutations {
+      counters: vec![0; items.len()],
+      remaining: factorial(items.len()),
+      items,
+      position: 1,
+      first: true,
+    }
+  }
+}
+
+impl<T: Clone> Iterator for Permutations<T> {
+  type Item = Vec<T>;
+
+  fn next(&mut self) -> Option<Vec<T>> {
+    if self.first {
+      self.first = false;
+    } else {
+      loop {
+        if self.position >= self.items.len() {
+          self.remaining = Some(0);
+          return None;
+        }
+        let i = self.position;
+        if self.counters[i] < i {
+          match i % 2 {
+            0 => self.items.swap(0, i),
+            _ => self.items.swap(self.counters[i], i),
+          }
+          self.counters[i] += 1;
+          self.position = 1;
+          break;
+        }
+        self.counters[i] = 0;
+        self.position += 1;
+      }
+    }
+    self.remaining = self.remaining.map(|n| n.saturating_sub(1));
+    Some(self.items.clone())
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    size_hint(self.remaining)
+  }
+}
+
+/// Every ordering of `items`, sorted by the positions the items started at, so the first
+/// permutation is `items` as given and the last is `items` reversed.
+#[derive(Debug, Clone)]
+pub struct LexicographicPermutations<T> {
+  items: Vec<T>,
+  indices: Vec<usize>,
+  first: bool,
+  remaining: Option<usize>,
+}
+
+impl<T: Clone> LexicographicPermutations<T> {
+  pub fn new(items: Vec<T>) -> Self {
+    LexicographicPermutations {
+      indices: (0..items.len()).collect(),
+      remaining: factorial(items.len()),
+      items,
+      first: true,
+    }
+  }
+
+  /// Divides the permutations into `parts` iterators over consecutive runs of roughly equal
+  /// length, which together visit every permutation once. Panics if there are too many
+  /// permutations to count.
+  pub fn split(self, parts: usize) -> Vec<Self> {
+    let total = factorial(self.items.len()).expect("too many permutations to split");
+    chunks(total, parts)
+      .into_iter()
+      .map(|(start, len)| LexicographicPermutations {
+        indices: Self::unrank(self.items.len(), start),
+        items: self.items.clone(),
+        first: true,
+        remaining: Some(len),
+      })
+      .collect()
+  }
+
+  /// The indices of the `rank`th permutation of `n` items.
+  fn unrank(n: usize, mut rank: usize) -> Vec<usize> {
+    let mut available: Vec<usize> = (0..n).collect();
+    let mut indices = Vec::with_capacity(n);
+    for position in 0..n {
+      let block = factorial(n - 1 - position).unwrap();
+      indices.push(available.remove(rank / block));
+      rank %= block;
+    }
+    indices
+  }
+}
+
+impl<T: Clone> Iterator for LexicographicPermutations<T> {
+  type Item = Vec<T>;
+
+  fn next(&mut self) -> Option<Vec<T>> {
+    if self.remaining == Some(0) {
+      return None;
+    }
+    if self.first {
+      self.first = false;
+    } else {
+      let indices = &mut self.indices;
+      let i = match (1..indices.len())
+        .rev()
+        .find(|i| indices[i - 1] < indices[*i])
+      {
+        Some(i) => i - 1,
+        None => {
+          self.remaining = Some(0);
+          return None;
+        }
+      };
+      let j = (i + 1..indices.len())
+        .rev()
+        .find(|j| indices[*j] > indices[i])
+        .unwrap();
+      indices.swap(i, j);
+      indices[i + 1..].reverse();
+    }
+    self.remaining = self.remaining.map(|n| n - 1);
+    Some(select(&self.items, &self.indices))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    size_hint(self.remaining)
+  }
+}
+
+/// Every ordered selection of `k` different items, sorted by the items' positions.
+#[derive(Debug, Clone)]
+pub struct KPermutations<T> {
+  items: Vec<T>,
+  indices: Vec<usize>,
+  first: bool,
+  remaining: Option<usize>,
+}
+
+impl<T: Clone> KPermutations<T> {
+  pub fn new(items: Vec<T>, k: usize) -> Self {
+    KPermutations {
+      indices: (0..k).collect(),
+      remaining: falling_factorial(items.len(), k),
+      items,
+      first: true,
+    }
+  }
+}
+
+impl<T: Clone> Iterator for KPermutations<T> {
+  type Item = Vec<T>;
+
+  fn next(&mut self) -> Option<Vec<T>> {
+    if self.remaining == Some(0) {
+      return None;
+    }
+    if self.first {
+      self.first = false;
+    } else {
+      let n = self.items.len();
+      let advanced = (0..self.indices.len()).rev().any(|position| {
+        let used = &self.indices[..position];
+        let next = (self.indices[position] + 1..n).find(|v| !used.contains(v));
+        let next = match next {
+          Some(next) => next,
+          None => return false,
+        };
+        self.indices[position] = next;
+        // Refill the rest with the smallest unused indices, the first arrangement after `next`.
+        let (head, tail) = self.indices.split_at_mut(position + 1);
+        let mut free = (0..n).filter(|v| !head.contains(v));
+        for index in tail.iter_mut() {
+          *index = free.next().unwrap();
+        }
+        true
+      });
+      if !advanced {
+        self.remaining = Some(0);
+        return None;
+      }
+    }
+    self.remaining = self.remaining.map(|n| n - 1);
+    Some(select(&self.items, &self.indices))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    size_hint(self.remaining)
+  }
+}
+
+/// Every selection of `k` different items in their original order, sorted by the items'
+/// positions.
+#[derive(Debug, Clone)]
+pub struct Combinations<T> {
+  items: Vec<T>,
+  indices: Vec<usize>,
+  first: bool,
+  remaining: Option<usize>,
+}
+
+impl<T: Clone> Combinations<T> {
+  pub fn new(items: Vec<T>, k: usize) -> Self {
+    Combinations {
+      indices: (0..k).collect(),
+      remaining: binomial(items.len(), k),
+      items,
+      first: true,
+    }
+  }
+
+  /// Divides the combinations into `parts` iterators over consecutive runs of roughly equal
+  /// length, which together visit every combination once. Panics if there are too many
+  /// combinations to count.
+  pub fn split(self, parts: usize) -> Vec<Self> {
+    let (n, k) = (self.items.len(), self.indices.len());
+    let total = binomial(n, k).expect("too many combinations to split");
+    chunks(total, parts)
+      .into_iter()
+      .map(|(start, len)| Combinations {
+        // With no combinations at all there is nothing to unrank, and the part stays empty.
+        indices: match len {
+          0 => self.indices.clone(),
+          _ => Self::unrank(n, k, start),
+        },
+        items: self.items.clone(),
+        first: true,
+        remaining: Some(len),
+      })
+      .collect()
+  }
+
+  /// The indices of the `rank`th combination of `k` out of `n` items.
+  fn unrank(n: usize, k: usize, mut rank: usize) -> Vec<usize> {
+    let mut indices = Vec::with_capacity(k);
+    let mut next = 0;
+    for position in 0..k {
+      loop {
+        // The number of combinations that start with `next` at this position.
+        let count = binomial(n - 1 - next, k - 1 - position).unwrap();
+        next += 1;
+        if rank < count {
+          indices.push(next - 1);
+          break;
+        }
+        rank -= count;
+      }
+    }
+    indices
+  }
+}
+
+impl<T: Clone> Iterator for Combinations<T> {
+  type Item = Vec<T>;
+
+  fn next(&mut self) -> Option<Vec<T>> {
+    if self.remaining == Some(0) {
+      return None;
+    }
+    if self.first {
+      self.first = false;
+    } else {
+      let (n, k) = (self.items.len(), self.indices.len());
+      let indices = &mut self.indices;
+      let i = match (0..k).rev().find(|i| indices[*i] < n - k + i) {
+        Some(i) => i,
+        None => {
+          self.remaining = Some(0);
+          return None;
+        }
+      };
+      indices[i] += 1;
+      for j in i + 1..k {
+        indices[j] = indices[j - 1] + 1;
+      }
+    }
+    self.remaining = self.remaining.map(|n| n - 1);
+    Some(select(&self.items, &self.indices))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    size_hint(self.remaining)
+  }
+}
+
+/// Every selection of `k` items where the same item may be picked more than once, in their
+/// original order.
+#[derive(Debug, Clone)]
+pub struct CombinationsWithReplacement<T> {
+  items: Vec<T>,
+  indices: Vec<usize>,
+  first: bool,
+  remaining: Option<usize>,
+}
+
+impl<T: Clone> CombinationsWithReplacement<T> {
+  pub fn new(items: Vec<T>, k: usize) -> Self {
+    let remaining = match (items.len(), k) {
+      (_, 0) => Some(1),
+      (0, _) => Some(0),
+      (n, k) => binomial(n + k - 1, k),
+    };
+    CombinationsWithReplacement {
+      indices: vec![0; k],
+      remaining,
+      items,
+      first: true,
+    }
+  }
+}
+
+impl<T: Clone> Iterator for CombinationsWithReplacement<T> {
+  type Item = Vec<T>;
+
+  fn next(&mut self) -> Option<Vec<T>> {
+    if self.remaining == Some(0) {
+      return None;
+    }
+    if self.first {
+      self.first = false;
+    } else {
+      let n = self.items.len();
+      let indices = &mut self.indices;
+      let i = match (0..indices.len()).rev().find(|i| indices[*i] + 1 < n) {
+        Some(i) => i,
+        None => {
+          self.remaining = Some(0);
+          return None;
+        }
+      };
+      let value = indices[i] + 1;
+      for index in indices[i..].iter_mut() {
+        *index = value;
+      }
+    }
+    self.remaining = self.remaining.map(|n| n - 1);
+    Some(select(&self.items, &self.indices))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    size_hint(self.remaining)
+  }
+}
+
+/// Every way of picking one item from each list, like nested loops with the last list
+/// innermost.
+#[derive(Debug, Clone)]
+pub struct Product<T> {
+  lists: Vec<Vec<T>>,
+  indices: Vec<usize>,
+  first: bool,
+  remaining: Option<usize>,
+}
+
+impl<T: Clone> Product<T> {
+  pub fn new(lists: Vec<Vec<T>>) -> Self {
+    Product {
+      indices: vec![0; lists.len()],
+      remaining: Self::total(&lists),
+      lists,
+      first: true,
+    }
+  }
+
+  /// Every sequence of `k` items drawn from `items`, like `k` nested loops over the same list.
+  pub fn repeat(items: Vec<T>, k: usize) -> Self {
+    Product::new(vec![items; k])
+  }
+
+  /// Divides the product into `parts` iterators over consecutive runs of roughly equal length,
+  /// which together visit every item once. Panics if there are too many items to count.
+  pub fn split(self, parts: usize) -> Vec<Self> {
+    let total = Self::total(&self.lists).expect("too many products to split");
+    chunks(total, parts)
+      .into_iter()
+      .map(|(start, len)| {
+        // Mixed radix, with the last list changing fastest.
+        let mut rank = start;
+        let mut indices = vec![0; self.lists.len()];
+        for (i, list) in self.lists.iter().enumerate().rev() {
+          indices[i] = rank % list.len().max(1);
+          rank /= list.len().max(1);
+        }
+        Product {
+          lists: self.lists.clone(),
+          indices,
+          first: true,
+          remaining: Some(len),
+        }
+      })
+      .collect()
+  }
+
+  fn total(lists: &[Vec<T>]) -> Option<usize> {
+    lists
+      .iter()
+      .try_fold(1usize, |acc, list| acc.checked_mul(list.len()))
+  }
+}
+
+impl<T: Clone> Iterator for Product<T> {
+  type Item = Vec<T>;
+
+  fn next(&mut self) -> Option<Vec<T>> {
+    if self.remaining == Some(0) {
+      return None;
+    }
+    if self.first {
+      self.first = false;
+    } else {
+      let lists = &self.lists;
+      let indices = &mut self.indices;
+      let i = match (0..lists.len())
+        .rev()
+        .find(|i| indices[*i] + 1 < lists[*i].len())
+      {
+        Some(i) => i,
+        None => {
+          self.remaining = Some(0);
+          return None;
+        }
+      };
+      indices[i] += 1;
+      for index in indices[i + 1..].iter_mut() {
+        *index = 0;
+      }
+    }
+    self.remaining = self.remaining.map(|n| n - 1);
+    let product = self
+      .lists
+      .iter()
+      .zip(&self.indices)
+      .map(|(list, i)| list[*i].clone())
+      .collect();
+    Some(product)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    size_hint(self.remaining)
+  }
+}
+
+/// Every subset of `items`, smallest first, with subsets of the same size in the order of
+/// `Combinations`.
+#[derive(Debug, Clone)]
+pub struct PowerSet<T> {
+  items: Vec<T>,
+  combinations: Combinations<T>,
+  size: usize,
+  remaining: Option<usize>,
+}
+
+impl<T: Clone> PowerSet<T> {
+  pub fn new(items: Vec<T>) -> Self {
+    let remaining = 1usize.checked_shl(items.len() as u32);
+    PowerSet {
+      combinations: Combinations::new(items.clone(), 0),
+      items,
+      size: 0,
+      remaining,
+    }
+  }
+}
+
+impl<T: Clone> Iterator for PowerSet<T> {
+  type Item = Vec<T>;
+
+  fn next(&mut self) -> Option<Vec<T>> {
+    loop {
+      if let Some(subset) = self.combinations.next() {
+        self.remaining = self.remaining.map(|n| n - 1);
+        return Some(subset);
+      }
+      if self.size == self.items.len() {
+        return None;
+      }
+      self.size += 1;
+      self.combinations = Combinations::new(self.items.clone(), self.size);
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    size_hint(self.remaining)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+  use std::thread;
+
+  #[test]
+  fn test_permutations() {
+    let heap: Vec<Vec<u8>> = Permutations::new(vec![1, 2, 3]).collect();
+    assert_eq!(6, heap.len());
+    assert_eq!(6, heap.iter().collect::<HashSet<_>>().len());
+    assert_eq!((24, Some(24)), Permutations::new(vec![0; 4]).size_hint());
+    assert_eq!(1, Permutations::new(Vec::<u8>::new()).count());
+
+    let lex: Vec<String> = LexicographicPermutations::new(vec!['a', 'b', 'c'])
+      .map(|p| p.into_iter().collect())
+      .collect();
+    assert_eq!(vec!["abc", "acb", "bac", "bca", "cab", "cba"], lex);
+
+    let mut iter = LexicographicPermutations::new(vec![0; 3]);
+    iter.next();
+    assert_eq!((5, Some(5)), iter.size_hint());
+  }
+
+  #[test]
+  fn test_k_permutations() {
+    let all: Vec<Vec<u8>> = KPermutations::new(vec![1, 2, 3], 2).collect();
+    assert_eq!(
+      vec![
+        vec![1, 2],
+        vec![1, 3],
+        vec![2, 1],
+        vec![2, 3],
+        vec![3, 1],
+        vec![3, 2]
+      ],
+      all
+    );
+    assert_eq!(0, KPermutations::new(vec![1], 2).count());
+    assert_eq!(60, KPermutations::new(vec![0; 5], 3).count());
+  }
+
+  #[test]
+  fn test_combinations() {
+    let all: Vec<Vec<u8>> = Combinations::new(vec![1, 2, 3, 4], 2).collect();
+    assert_eq!(
+      vec![
+        vec![1, 2],
+        vec![1, 3],
+        vec![1, 4],
+        vec![2, 3],
+        vec![2, 4],
+        vec![3, 4]
+      ],
+      all
+    );
+    assert_eq!(
+      vec![Vec::<u8>::new()],
+      Combinations::new(vec![1], 0).collect::<Vec<_>>()
+    );
+    assert_eq!(0, Combinations::new(vec![1], 2).count());
+
+    let all: Vec<Vec<u8>> = CombinationsWithReplacement::new(vec![1, 2, 3], 2).collect();
+    assert_eq!(
+      vec![
+        vec![1, 1],
+        vec![1, 2],
+        vec![1, 3],
+        vec![2, 2],
+        vec![2, 3],
+        vec![3, 3]
+      ],
+      all
+    );
+    assert_eq!(
+      (15, Some(15)),
+      CombinationsWithReplacement::new(vec![0; 5], 2).size_hint()
+    );
+  }
+
+  #[test]
+  fn test_product_and_power_set() {
+    let all: Vec<Vec<char>> =
+      Product::new(vec![vec!['a', 'b'], vec!['x'], vec!['1', '2']]).collect();
+    assert_eq!(
+      vec![
+        vec!['a', 'x', '1'],
+        vec!['a', 'x', '2'],
+        vec!['b', 'x', '1'],
+        vec!['b', 'x', '2']
+      ],
+      all
+    );
+    assert_eq!(27, Product::repeat(vec![0, 1, 2], 3).count());
+    assert_eq!(0, Product::new(vec![vec![1], vec![]]).count());
+
+    let subsets: Vec<Vec<u8>> = PowerSet::new(vec![1, 2, 3]).collect();
+    assert_eq!(
+      vec![
+        vec![],
+        vec![1],
+        vec![2],
+        vec![3],
+        vec![1, 2],
+        vec![1, 3],
+        vec![2, 3],
+        vec![1, 2, 3]
+      ],
+      subsets
+    );
+    assert_eq!((8, Some(8)), PowerSet::new(vec![0; 3]).size_hint());
+  }
+
+  #[test]
+  fn test_split() {
+    let items: Vec<u8> = (0..6).collect();
+    let whole: Vec<Vec<u8>> = LexicographicPermutations::new(items.clone()).collect();
+    let parts = LexicographicPermutations::new(items.clone()).split(7);
+    assert_eq!(7, parts.len());
+    let chunked: Vec<Vec<u8>> = parts.into_iter().flatten().collect();
+    assert_eq!(whole, chunked);
+
+    let whole: Vec<Vec<u8>> = Combinations::new(items.clone(), 3).collect();
+    let chunked: Vec<Vec<u8>> = Combinations::new(items.clone(), 3)
+      .split(3)
+      .into_iter()
+      .flatten()
+      .collect();
+    assert_eq!(whole, chunked);
+
+    let parts = Combinations::new(vec![1], 2).split(2);
+    assert!(parts.into_iter().flatten().next().is_none());
+
+    let whole: Vec<Vec<u8>> = Product::repeat(items.clone(), 2).collect();
+    let chunked: Vec<Vec<u8>> = Product::repeat(items, 2)
+      .split(5)
+      .into_iter()
+      .flatten()
+      .collect();
+    assert_eq!(whole, chunked);
+
+    let parts = Product::new(vec![vec![1], vec![]]).split(3);
+    assert!(parts.into_iter().flatten().next().is_none());
+  }
+
+  #[test]
+  fn test_split_across_threads() {
+    let handles: Vec<_> = LexicographicPermutations::new((1..=7).collect::<Vec<u32>>())
+      .split(4)
+      .into_iter()
+      .map(|part| thread::spawn(move || part.map(|p| p[0] * p[6]).max().unwrap()))
+      .collect();
+    let best = handles.into_iter().map(|h| h.join().unwrap()).max();
+    assert_eq!(Some(42), best);
+  }
+}
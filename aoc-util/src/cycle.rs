@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Where a sequence of states starts repeating: after `start` steps (μ) it reaches a state it
+/// returns to every `length` steps (λ) from then on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cycle {
+  pub start: usize,
+  pub length: usize,
+}
+
+impl Cycle {
+  /// The earliest step with the same state as step `n`, which is `n` itself before the cycle
+  /// starts.
+  pub fn reduce(&self, n: usize) -> usize {
+    match n < self.start {
+      true => n,
+      false => self.start + (n - self.start) % self.length,
+    }
+  }
+}
+
+/// Finds the cycle in the states reached by repeatedly applying `step` to `initial`, with
+/// Floyd's tortoise and hare. Only ever holds three states, but compares every state more than
+/// once. Never returns if the states don't repeat.
+pub fn floyd<T, F>(initial: T, mut step: F) -> Cycle
+where
+  T: Clone + PartialEq,
+  F: FnMut(&T) -> T,
+{
+  let mut tortoise = step(&initial);
+  let mut hare = step(&tortoise);
+  while tortoise != hare {
+    tortoise = step(&tortoise);
+    hare = step(&hare);
+    hare = step(&hare);
+  }
+  // The hare is now a whole number of cycles ahead, so moving both at the same speed from the
+  // start they first meet where the cycle begins.
+  let mut start = 0;
+  tortoise = initial;
+  while tortoise != hare {
+    tortoise = step(&tortoise);
+    hare = step(&hare);
+    start += 1;
+  }
+  let mut length = 1;
+  hare = step(&tortoise);
+  while tortoise != hare {
+    hare = step(&hare);
+    length += 1;
+  }
+  Cycle { start, length }
+}
+
+/// Finds the cycle like `floyd`, but with Brent's algorithm, which finds the length first and
+/// usually needs fewer steps. Never returns if the states don't repeat.
+pub fn brent<T, F>(initial: T, mut step: F) -> Cycle
+where
+  T: Clone + PartialEq,
+  F: FnMut(&T) -> T,
+{
+  let (mut power, mut length) = (1, 1);
+  let mut tortoise = initial.clone();
+  let mut hare = step(&initial);
+  while tortoise != hare {
+    if power == length {
+      tortoise = hare.clone();
+      power *= 2;
+      length = 0;
+    }
+    hare = step(&hare);
+    length += 1;
+  }
+  // Start the hare `length` steps ahead, so they meet where the cycle begins.
+  tortoise = initial.clone();
+  hare = initial;
+  for _ in 0..length {
+    hare = step(&hare);
+  }
+  let mut start = 0;
+  while tortoise != hare {
+    tortoise = step(&tortoise);
+    hare = step(&hare);
+    start += 1;
+  }
+  Cycle { start, length }
+}
+
+/// Finds the cycle by remembering every state until one repeats. Applies `step` the fewest
+/// times of the three detectors, at the cost of memory. Never returns if the states don't
+/// repeat.
+pub fn find_cycle<T, F>(initial: T, step: F) -> Cycle
+where
+  T: Clone + Eq + Hash,
+  F: FnMut(&T) -> T,
+{
+  let (_, cycle) = run(initial, step, None);
+  cycle.unwrap()
+}
+
+/// The state after applying `step` to `initial` `n` times. Stops stepping as soon as a state
+/// repeats and works out the answer from the cycle, so `n` can be far larger than the number of
+/// distinct states.
+pub fn simulate<T, F>(initial: T, step: F, n: usize) -> T
+where
+  T: Clone + Eq + Hash,
+  F: FnMut(&T) -> T,
+{
+  let (mut states, cycle) = run(initial, step, Some(n));
+  match cycle {
+    Some(cycle) => states.swap_remove(cycle.reduce(n)),
+    None => states.pop().unwrap(),
+  }
+}
+
+/// Steps from `initial` until a state repeats or `limit` steps have been taken, returning every
+/// state visited and the cycle if one was found.
+fn run<T, F>(initial: T, mut step: F, limit: Option<usize>) -> (Vec<T>, Option<Cycle>)
+where
+  T: Clone + Eq + Hash,
+  F: FnMut(&T) -> T,
+{
+  let mut seen = HashMap::new();
+  let mut states = vec![];
+  let mut state = initial;
+  loop {
+    if let Some(&start) = seen.get(&state) {
+      let length = states.len() - start;
+      return (states, Some(Cycle { start, length }));
+    }
+    seen.insert(state.clone(), states.len());
+    states.push(state.clone());
+    if Some(states.len() - 1) == limit {
+      return (states, None);
+    }
+    state = step(&state);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// 0, 1, 2, 3, 4, 5, 2, 3, 4, 5, ...
+  fn lasso(x: &u32) -> u32 {
+    match *x < 5 {
+      true => x + 1,
+      false => 2,
+    }
+  }
+
+  #[test]
+  fn test_detectors() {
+    let expected = Cycle {
+      start: 2,
+      length: 4,
+    };
+    assert_eq!(expected, floyd(0, lasso));
+    assert_eq!(expected, brent(0, lasso));
+    assert_eq!(expected, find_cycle(0, lasso));
+
+    let pure = Cycle {
+      start: 0,
+      length: 4,
+    };
+    assert_eq!(pure, floyd(2, lasso));
+    assert_eq!(pure, brent(2, lasso));
+    assert_eq!(pure, find_cycle(2, lasso));
+
+    let random = |x: &u64| (x * x + 1) % 255;
+    let cycle = find_cycle(3, random);
+    assert_eq!(cycle, floyd(3, random));
+    assert_eq!(cycle, brent(3, random));
+  }
+
+  #[test]
+  fn test_simulate() {
+    assert_eq!(0, simulate(0, lasso, 0));
+    assert_eq!(3, simulate(0, lasso, 3));
+    assert_eq!(5, simulate(0, lasso, 5));
+    assert_eq!(2, simulate(0, lasso, 6));
+    assert_eq!(3, simulate(0, lasso, 1_000_000_000_003));
+
+    // Only as many steps as needed are taken.
+    let mut steps = 0;
+    let counted = |x: &u32| {
+      steps += 1;
+      lasso(x)
+    };
+    assert_eq!(7, simulate(7, counted, 0));
+    assert_eq!(0, steps);
+
+    let cycle = Cycle {
+      start: 2,
+      length: 4,
+    };
+    assert_eq!(1, cycle.reduce(1));
+    assert_eq!(5, cycle.reduce(9));
+  }
+}
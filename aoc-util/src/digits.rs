@@ -1,8 +1,26 @@
 use core::ops::DivAssign;
 use num_traits::{FromPrimitive, PrimInt};
 
+/// Which end of a number its digits start from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigitOrder {
+  /// Least significant digit first, so 123 is `[3, 2, 1]`.
+  LittleEndian,
+  /// Most significant digit first, so 123 is `[1, 2, 3]`, the way it's written.
+  BigEndian,
+}
+
+/// Splits a number into its digits. Negative numbers give the digits of their absolute value,
+/// so -123 has the same digits as 123, and zero has the single digit 0.
 pub trait ToDigits<T> {
+  /// The base 10 digits, least significant first.
   fn digits(&self) -> Vec<T>;
+
+  fn digits_in(&self, radix: T, order: DigitOrder) -> Vec<T>;
+
+  /// Iterates over the digits without allocating, least significant first. Reverse it for the
+  /// most significant first.
+  fn digit_iter(&self, radix: T) -> Digits<T>;
 }
 
 impl<T> ToDigits<T> for T
@@ -10,18 +28,122 @@ where
   T: FromPrimitive + PrimInt + DivAssign,
 {
   fn digits(&self) -> Vec<T> {
-    let mut i = *self;
-    let mut d = Vec::new();
-    loop {
-      let digit = i % T::from_u8(10).unwrap();
-      d.push(digit);
-      i /= T::from_u8(10).unwrap();
-      if i == T::from_u8(0).unwrap() {
-        break;
-      }
+    self.digit_iter(T::from_u8(10).unwrap()).collect()
+  }
+
+  fn digits_in(&self, radix: T, order: DigitOrder) -> Vec<T> {
+    let digits = self.digit_iter(radix);
+    match order {
+      DigitOrder::LittleEndian => digits.collect(),
+      DigitOrder::BigEndian => digits.rev().collect(),
     }
-    d
   }
+
+  fn digit_iter(&self, radix: T) -> Digits<T> {
+    Digits::new(*self, radix)
+  }
+}
+
+/// The digits of a number, taken from the least significant end with `next` and the most
+/// significant end with `next_back`.
+#[derive(Debug, Clone)]
+pub struct Digits<T> {
+  value: T,
+  radix: T,
+  /// The place value of the most significant digit left.
+  high: T,
+  len: usize,
+}
+
+impl<T: PrimInt> Digits<T> {
+  fn new(value: T, radix: T) -> Self {
+    assert!(radix > T::one(), "radix must be at least 2");
+    // Negative values are never negated, as the most negative value has no positive
+    // counterpart. Their place values are negative instead, and each digit is negated on its
+    // own.
+    let mut high = match value < T::zero() {
+      true => T::zero() - T::one(),
+      false => T::one(),
+    };
+    let mut len = 1;
+    while value / radix / high >= T::one() {
+      high = high * radix;
+      len += 1;
+    }
+    Digits {
+      value,
+      radix,
+      high,
+      len,
+    }
+  }
+
+  fn magnitude(digit: T) -> T {
+    match digit < T::zero() {
+      true => T::zero() - digit,
+      false => digit,
+    }
+  }
+}
+
+impl<T: PrimInt> Iterator for Digits<T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<T> {
+    if self.len == 0 {
+      return None;
+    }
+    let digit = self.value % self.radix;
+    self.value = self.value / self.radix;
+    self.high = self.high / self.radix;
+    self.len -= 1;
+    Some(Self::magnitude(digit))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<T: PrimInt> DoubleEndedIterator for Digits<T> {
+  fn next_back(&mut self) -> Option<T> {
+    if self.len == 0 {
+      return None;
+    }
+    let digit = self.value / self.high;
+    self.value = self.value % self.high;
+    self.high = self.high / self.radix;
+    self.len -= 1;
+    Some(Self::magnitude(digit))
+  }
+}
+
+impl<T: PrimInt> ExactSizeIterator for Digits<T> {}
+
+/// Puts a number back together from its digits. Returns `None` if a digit is negative or not
+/// below `radix`, or the number doesn't fit in `T`. No digits at all make 0.
+pub fn from_digits<T: PrimInt>(digits: &[T], radix: T, order: DigitOrder) -> Option<T> {
+  let push = |acc: T, digit: &T| match *digit >= T::zero() && *digit < radix {
+    true => acc.checked_mul(&radix)?.checked_add(digit),
+    false => None,
+  };
+  match order {
+    DigitOrder::LittleEndian => digits.iter().rev().try_fold(T::zero(), push),
+    DigitOrder::BigEndian => digits.iter().try_fold(T::zero(), push),
+  }
+}
+
+/// Groups runs of equal digits, giving each digit with the number of times it repeats, so
+/// `[1, 1, 2, 1]` is `[(1, 2), (2, 1), (1, 1)]`.
+pub fn run_lengths<T: Copy + PartialEq>(digits: &[T]) -> Vec<(T, usize)> {
+  let mut runs: Vec<(T, usize)> = vec![];
+  for digit in digits {
+    match runs.last_mut() {
+      Some((last, count)) if last == digit => *count += 1,
+      _ => runs.push((*digit, 1)),
+    }
+  }
+  runs
 }
 
 #[cfg(test)]
@@ -40,6 +162,75 @@ mod tests {
     assert_vec(893476.digits(), vec![6, 7, 4, 3, 9, 8]);
   }
 
+  #[test]
+  fn test_radix_and_order() {
+    assert_eq!(vec![1, 2, 3], 123.digits_in(10, DigitOrder::BigEndian));
+    assert_eq!(vec![1, 0, 1, 1], 11u8.digits_in(2, DigitOrder::BigEndian));
+    assert_eq!(vec![15, 15], 255u8.digits_in(16, DigitOrder::LittleEndian));
+    assert_eq!(vec![1, 0, 0], 100.digits_in(10, DigitOrder::BigEndian));
+    assert_eq!(vec![0], 0u64.digits_in(7, DigitOrder::BigEndian));
+    assert_eq!(vec![2, 5, 5], u8::MAX.digits_in(10, DigitOrder::BigEndian));
+  }
+
+  #[test]
+  fn test_negative() {
+    assert_eq!((-123).digits(), 123.digits());
+    assert_eq!(vec![1, 2, 8], i8::MIN.digits_in(10, DigitOrder::BigEndian));
+    assert_eq!(
+      vec![1, 0, 0, 0, 0, 0, 0, 0],
+      i8::MIN.digits_in(2, DigitOrder::BigEndian)
+    );
+    assert_eq!(
+      Some(123),
+      from_digits(&(-123).digits(), 10, DigitOrder::LittleEndian)
+    );
+  }
+
+  #[test]
+  fn test_iter() {
+    let mut digits = 12345.digit_iter(10);
+    assert_eq!(5, digits.len());
+    assert_eq!(Some(5), digits.next());
+    assert_eq!(Some(1), digits.next_back());
+    assert_eq!(Some(2), digits.next_back());
+    assert_eq!((2, Some(2)), digits.size_hint());
+    assert_eq!(vec![4, 3], digits.collect::<Vec<_>>());
+    assert_eq!(45, 9_876_543_210u64.digit_iter(10).sum::<u64>());
+  }
+
+  #[test]
+  fn test_from_digits() {
+    assert_eq!(
+      Some(123),
+      from_digits(&[1, 2, 3], 10, DigitOrder::BigEndian)
+    );
+    assert_eq!(
+      Some(321),
+      from_digits(&[1, 2, 3], 10, DigitOrder::LittleEndian)
+    );
+    assert_eq!(
+      Some(0xff),
+      from_digits(&[15, 15], 16, DigitOrder::BigEndian)
+    );
+    assert_eq!(Some(0), from_digits::<i32>(&[], 10, DigitOrder::BigEndian));
+    assert_eq!(None, from_digits(&[1, 10], 10, DigitOrder::BigEndian));
+    assert_eq!(None, from_digits(&[-1], 10, DigitOrder::BigEndian));
+    assert_eq!(None, from_digits(&[2, 5, 6], 10u8, DigitOrder::BigEndian));
+    for n in [0u32, 7, 4096, u32::MAX].iter() {
+      let digits = n.digits_in(3, DigitOrder::BigEndian);
+      assert_eq!(Some(*n), from_digits(&digits, 3, DigitOrder::BigEndian));
+    }
+  }
+
+  #[test]
+  fn test_run_lengths() {
+    assert_eq!(
+      vec![(1, 2), (2, 3), (1, 1)],
+      run_lengths(&[1, 1, 2, 2, 2, 1])
+    );
+    assert_eq!(Vec::<(u8, usize)>::new(), run_lengths(&[]));
+  }
+
   fn assert_vec<T>(output: Vec<T>, required: Vec<T>)
   where
     T: PrimInt + FromPrimitive + Debug,
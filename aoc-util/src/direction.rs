@@ -1,26 +1,183 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
   Up,
   Down,
   Left,
   Right,
+  UpRight,
+  DownRight,
+  DownLeft,
+  UpLeft,
+}
+
+/// Which way the y axis points, for turning a `Direction` into an offset. Screens and grids
+/// usually grow down, while maths and many puzzle descriptions grow up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YAxis {
+  GrowsUp,
+  GrowsDown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDirectionError {
+  pub text: String,
 }
 
 impl Direction {
+  /// The four orthogonal directions, clockwise from up.
+  pub const CARDINAL: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+  ];
+
+  /// All eight directions, clockwise from up.
+  pub const ALL: [Direction; 8] = [
+    Direction::Up,
+    Direction::UpRight,
+    Direction::Right,
+    Direction::DownRight,
+    Direction::Down,
+    Direction::DownLeft,
+    Direction::Left,
+    Direction::UpLeft,
+  ];
+
+  /// A quarter turn clockwise.
   pub fn clockwise(&self) -> Self {
-    match self {
-      Direction::Up => Direction::Right,
-      Direction::Right => Direction::Down,
-      Direction::Down => Direction::Left,
-      Direction::Left => Direction::Up,
-    }
+    self.turn(2)
   }
 
+  /// A quarter turn counter-clockwise.
   pub fn counter_clockwise(&self) -> Self {
-    match self {
-      Direction::Up => Direction::Left,
-      Direction::Right => Direction::Up,
-      Direction::Down => Direction::Right,
-      Direction::Left => Direction::Down,
+    self.turn(-2)
+  }
+
+  pub fn opposite(&self) -> Self {
+    self.turn(4)
+  }
+
+  /// Turns `eighths` eighths of a full turn, clockwise if positive. `turn(2)` is a right angle.
+  pub fn turn(&self, eighths: isize) -> Self {
+    let index = Direction::ALL.iter().position(|d| d == self).unwrap() as isize;
+    Direction::ALL[(index + eighths).rem_euclid(8) as usize]
+  }
+
+  pub fn is_diagonal(&self) -> bool {
+    !Direction::CARDINAL.contains(self)
+  }
+
+  /// The `(x, y)` offset of one step in this direction, with y pointing the way of `y_axis`.
+  pub fn offset(&self, y_axis: YAxis) -> (isize, isize) {
+    let (x, up) = match self {
+      Direction::Up => (0, 1),
+      Direction::Down => (0, -1),
+      Direction::Left => (-1, 0),
+      Direction::Right => (1, 0),
+      Direction::UpRight => (1, 1),
+      Direction::DownRight => (1, -1),
+      Direction::DownLeft => (-1, -1),
+      Direction::UpLeft => (-1, 1),
+    };
+    match y_axis {
+      YAxis::GrowsUp => (x, up),
+      YAxis::GrowsDown => (x, -up),
+    }
+  }
+}
+
+/// Parses a direction from a letter (`U`, `R`, `N`, `E`, `NE`, ...), an arrow (`^`, `>`, `↑`,
+/// `↗`, ...) or a word (`up`, `right`, `north`, `north-east`, `up-right`, ...), ignoring case.
+impl FromStr for Direction {
+  type Err = ParseDirectionError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let text = s.trim().to_lowercase().replace(&['_', ' '][..], "-");
+    let direction = match text.as_str() {
+      "u" | "n" | "^" | "↑" | "up" | "north" => Direction::Up,
+      "d" | "s" | "v" | "↓" | "down" | "south" => Direction::Down,
+      "l" | "w" | "<" | "←" | "left" | "west" => Direction::Left,
+      "r" | "e" | ">" | "→" | "right" | "east" => Direction::Right,
+      "ur" | "ne" | "↗" | "up-right" | "north-east" | "northeast" => Direction::UpRight,
+      "dr" | "se" | "↘" | "down-right" | "south-east" | "southeast" => Direction::DownRight,
+      "dl" | "sw" | "↙" | "down-left" | "south-west" | "southwest" => Direction::DownLeft,
+      "ul" | "nw" | "↖" | "up-left" | "north-west" | "northwest" => Direction::UpLeft,
+      _ => {
+        return Err(ParseDirectionError {
+          text: s.to_string(),
+        })
+      }
+    };
+    Ok(direction)
+  }
+}
+
+impl fmt::Display for Direction {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Direction::Up => "up",
+      Direction::Down => "down",
+      Direction::Left => "left",
+      Direction::Right => "right",
+      Direction::UpRight => "up-right",
+      Direction::DownRight => "down-right",
+      Direction::DownLeft => "down-left",
+      Direction::UpLeft => "up-left",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+impl fmt::Display for ParseDirectionError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "unknown direction {:?}", self.text)
+  }
+}
+
+impl Error for ParseDirectionError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_turn() {
+    assert_eq!(Direction::Right, Direction::Up.clockwise());
+    assert_eq!(Direction::UpLeft, Direction::UpRight.counter_clockwise());
+    assert_eq!(Direction::DownLeft, Direction::UpRight.opposite());
+    assert_eq!(Direction::UpRight, Direction::Up.turn(1));
+    assert_eq!(Direction::Left, Direction::Up.turn(-2));
+    assert_eq!(Direction::Down, Direction::Up.turn(12));
+    assert!(Direction::DownRight.is_diagonal());
+    assert!(!Direction::Down.is_diagonal());
+  }
+
+  #[test]
+  fn test_offset() {
+    assert_eq!((0, 1), Direction::Up.offset(YAxis::GrowsUp));
+    assert_eq!((0, -1), Direction::Up.offset(YAxis::GrowsDown));
+    assert_eq!((1, 1), Direction::DownRight.offset(YAxis::GrowsDown));
+    assert_eq!((-1, 0), Direction::Left.offset(YAxis::GrowsUp));
+  }
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(Ok(Direction::Right), "R".parse());
+    assert_eq!(Ok(Direction::Down), "v".parse());
+    assert_eq!(Ok(Direction::UpLeft), "North West".parse());
+    assert_eq!(Ok(Direction::DownRight), "↘".parse());
+    assert_eq!(Ok(Direction::Left), "WEST".parse());
+    assert_eq!(
+      "unknown direction \"x\"",
+      "x".parse::<Direction>().unwrap_err().to_string()
+    );
+    for direction in Direction::ALL.iter() {
+      assert_eq!(Ok(*direction), direction.to_string().parse());
     }
   }
 }
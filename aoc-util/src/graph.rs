@@ -0,0 +1,592 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+/// A graph stored as adjacency lists, with nodes of any hashable type and `u64` edge weights.
+///
+/// Nodes are kept in the order they were first added, and neighbors in the order their edges
+/// were added, so every traversal is deterministic.
+#[derive(Debug, Clone)]
+pub struct Graph<N> {
+  directed: bool,
+  nodes: Vec<N>,
+  indices: HashMap<N, usize>,
+  edges: Vec<Vec<(usize, u64)>>,
+}
+
+/// A route through a graph, including both ends, and the total weight of its edges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path<N> {
+  pub nodes: Vec<N>,
+  pub cost: u64,
+}
+
+impl<N> Graph<N>
+where
+  N: Clone + Eq + Hash,
+{
+  /// A graph whose edges only go from `a` to `b`.
+  pub fn directed() -> Self {
+    Graph {
+      directed: true,
+      nodes: Vec::new(),
+      indices: HashMap::new(),
+      edges: Vec::new(),
+    }
+  }
+
+  /// A graph whose edges go both ways.
+  pub fn undirected() -> Self {
+    Graph {
+      directed: false,
+      ..Graph::directed()
+    }
+  }
+
+  pub fn is_directed(&self) -> bool {
+    self.directed
+  }
+
+  /// Adds `node` if it isn't already in the graph.
+  pub fn add_node(&mut self, node: N) {
+    self.index_of(node);
+  }
+
+  /// Adds an edge of weight 1, adding either node if needed.
+  pub fn add_edge(&mut self, a: N, b: N) {
+    self.add_weighted_edge(a, b, 1);
+  }
+
+  pub fn add_weighted_edge(&mut self, a: N, b: N, weight: u64) {
+    let a = self.index_of(a);
+    let b = self.index_of(b);
+    self.edges[a].push((b, weight));
+    if !self.directed && a != b {
+      self.edges[b].push((a, weight));
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.nodes.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.nodes.is_empty()
+  }
+
+  pub fn contains(&self, node: &N) -> bool {
+    self.indices.contains_key(node)
+  }
+
+  pub fn nodes(&self) -> impl Iterator<Item = &N> {
+    self.nodes.iter()
+  }
+
+  /// The nodes an edge leads to from `node`, with the edge's weight.
+  pub fn neighbors(&self, node: &N) -> impl Iterator<Item = (&N, u64)> {
+    let edges = match self.indices.get(node) {
+      Some(i) => &self.edges[*i][..],
+      None => &[],
+    };
+    edges.iter().map(move |(j, w)| (&self.nodes[*j], *w))
+  }
+
+  /// Every node reachable from `start`, nearest first.
+  pub fn bfs(&self, start: &N) -> Vec<&N> {
+    let start = match self.indices.get(start) {
+      Some(i) => *i,
+      None => return Vec::new(),
+    };
+    let mut seen = vec![false; self.len()];
+    let mut queue = VecDeque::new();
+    let mut order = Vec::new();
+    seen[start] = true;
+    queue.push_back(start);
+    while let Some(i) = queue.pop_front() {
+      order.push(&self.nodes[i]);
+      for (j, _) in &self.edges[i] {
+        if !seen[*j] {
+          seen[*j] = true;
+          queue.push_back(*j);
+        }
+      }
+    }
+    order
+  }
+
+  /// Every node reachable from `start`, in the order a depth first search visits them. Use
+  /// `dfs_path` for the route to a particular node.
+  pub fn dfs(&self, start: &N) -> Vec<&N> {
+    let start = match self.indices.get(start) {
+      Some(i) => *i,
+      None => return Vec::new(),
+    };
+    let mut seen = vec![false; self.len()];
+    let mut stack = vec![start];
+    let mut order = Vec::new();
+    while let Some(i) = stack.pop() {
+      if seen[i] {
+        continue;
+      }
+      seen[i] = true;
+      order.push(&self.nodes[i]);
+      // Pushed in reverse so the first neighbor is visited first.
+      for (j, _) in self.edges[i].iter().rev() {
+        if !seen[*j] {
+          stack.push(*j);
+        }
+      }
+    }
+    order
+  }
+
+  /// The path a depth first search takes from `start` to `goal`. It is found without exploring
+  /// the whole graph, but needn't be the shortest.
+  pub fn dfs_path(&self, start: &N, goal: &N) -> Option<Path<N>> {
+    let (start, goal) = (*self.indices.get(start)?, *self.indices.get(goal)?);
+    let mut previous = vec![None; self.len()];
+    let mut costs = vec![0; self.len()];
+    let mut seen = vec![false; self.len()];
+    // Each node is stacked with the node it was reached from and the weight of that edge.
+    let mut stack = vec![(start, None, 0)];
+    while let Some((i, from, weight)) = stack.pop() {
+      if seen[i] {
+        continue;
+      }
+      seen[i] = true;
+      previous[i] = from;
+      costs[i] = from.map_or(0, |f| costs[f] + weight);
+      if i == goal {
+        let nodes = self.reconstruct(&previous, goal);
+        return Some(Path {
+          nodes,
+          cost: costs[goal],
+        });
+      }
+      for (j, weight) in self.edges[i].iter().rev() {
+        if !seen[*j] {
+          stack.push((*j, Some(i), *weight));
+        }
+      }
+    }
+    None
+  }
+
+  /// The path from `start` to `goal` with the fewest edges, ignoring weights.
+  pub fn shortest_path(&self, start: &N, goal: &N) -> Option<Path<N>> {
+    let (start, goal) = (*self.indices.get(start)?, *self.indices.get(goal)?);
+    let mut previous = vec![None; self.len()];
+    let mut seen = vec![false; self.len()];
+    let mut queue = VecDeque::new();
+    seen[start] = true;
+    queue.push_back(start);
+    while let Some(i) = queue.pop_front() {
+      if i == goal {
+        let nodes = self.reconstruct(&previous, goal);
+        let cost = nodes.len() as u64 - 1;
+        return Some(Path { nodes, cost });
+      }
+      for (j, _) in &self.edges[i] {
+        if !seen[*j] {
+          seen[*j] = true;
+          previous[*j] = Some(i);
+          queue.push_back(*j);
+        }
+      }
+    }
+    None
+  }
+
+  /// The cheapest path from `start` to `goal`.
+  pub fn dijkstra(&self, start: &N, goal: &N) -> Option<Path<N>> {
+    self.astar(start, goal, |_| 0)
+  }
+
+  /// The cheapest path from `start` to `goal`, searching the nodes `heuristic` estimates to be
+  /// closest to the goal first. The heuristic must never overestimate the remaining cost, or the
+  /// path found may not be the cheapest.
+  pub fn astar<H>(&self, start: &N, goal: &N, mut heuristic: H) -> Option<Path<N>>
+  where
+    H: FnMut(&N) -> u64,
+  {
+    let (start, goal) = (*self.indices.get(start)?, *self.indices.get(goal)?);
+    let mut costs = vec![u64::MAX; self.len()];
+    let mut previous = vec![None; self.len()];
+    let mut heap = BinaryHeap::new();
+    costs[start] = 0;
+    heap.push(Reverse((heuristic(&self.nodes[start]), start)));
+    while let Some(Reverse((_, i))) = heap.pop() {
+      if i == goal {
+        let nodes = self.reconstruct(&previous, goal);
+        return Some(Path {
+          nodes,
+          cost: costs[goal],
+        });
+      }
+      for (j, weight) in &self.edges[i] {
+        let cost = costs[i] + weight;
+        if cost < costs[*j] {
+          costs[*j] = cost;
+          previous[*j] = Some(i);
+          heap.push(Reverse((cost + heuristic(&self.nodes[*j]), *j)));
+        }
+      }
+    }
+    None
+  }
+
+  /// Groups the nodes into sets that are connected to each other, ignoring the direction of
+  /// edges. Each group is in the order its nodes were added.
+  pub fn connected_components(&self) -> Vec<Vec<&N>> {
+    let mut undirected = vec![Vec::new(); self.len()];
+    for (i, edges) in self.edges.iter().enumerate() {
+      for (j, _) in edges {
+        undirected[i].push(*j);
+        undirected[*j].push(i);
+      }
+    }
+    let mut component = vec![None; self.len()];
+    let mut components: Vec<Vec<usize>> = Vec::new();
+    for start in 0..self.len() {
+      if component[start].is_some() {
+        continue;
+      }
+      let id = components.len();
+      let mut members = Vec::new();
+      let mut stack = vec![start];
+      component[start] = Some(id);
+      while let Some(i) = stack.pop() {
+        members.push(i);
+        for j in &undirected[i] {
+          if component[*j].is_none() {
+            component[*j] = Some(id);
+            stack.push(*j);
+          }
+        }
+      }
+      members.sort_unstable();
+      components.push(members);
+    }
+    components
+      .into_iter()
+      .map(|members| members.into_iter().map(|i| &self.nodes[i]).collect())
+      .collect()
+  }
+
+  /// Orders the nodes so every edge goes from an earlier node to a later one, or returns `None`
+  /// if there is a cycle. Ties are broken by the order nodes were added.
+  pub fn topological_sort(&self) -> Option<Vec<&N>> {
+    let mut incoming = vec![0; self.len()];
+    for edges in &self.edges {
+      for (j, _) in edges {
+        incoming[*j] += 1;
+      }
+    }
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..self.len())
+      .filter(|i| incoming[*i] == 0)
+      .map(Reverse)
+      .collect();
+    let mut order = Vec::new();
+    while let Some(Reverse(i)) = ready.pop() {
+      order.push(&self.nodes[i]);
+      for (j, _) in &self.edges[i] {
+        incoming[*j] -= 1;
+        if incoming[*j] == 0 {
+          ready.push(Reverse(*j));
+        }
+      }
+    }
+    match order.len() == self.len() {
+      true => Some(order),
+      false => None,
+    }
+  }
+
+  fn index_of(&mut self, node: N) -> usize {
+    if let Some(i) = self.indices.get(&node) {
+      return *i;
+    }
+    let i = self.nodes.len();
+    self.nodes.push(node.clone());
+    self.indices.insert(node, i);
+    self.edges.push(Vec::new());
+    i
+  }
+
+  fn reconstruct(&self, previous: &[Option<usize>], goal: usize) -> Vec<N> {
+    let mut nodes = vec![self.nodes[goal].clone()];
+    let mut i = goal;
+    while let Some(p) = previous[i] {
+      nodes.push(self.nodes[p].clone());
+      i = p;
+    }
+    nodes.reverse();
+    nodes
+  }
+}
+
+/// A rooted tree, or forest, built from parent and child pairs.
+#[derive(Debug, Clone)]
+pub struct Tree<N> {
+  parents: HashMap<N, N>,
+  children: HashMap<N, Vec<N>>,
+}
+
+impl<N> Tree<N>
+where
+  N: Clone + Eq + Hash,
+{
+  pub fn new() -> Self {
+    Tree {
+      parents: HashMap::new(),
+      children: HashMap::new(),
+    }
+  }
+
+  /// Adds `child` below `parent`, replacing any parent `child` already had. Panics if `parent`
+  /// is `child` or one of its descendants, as the edge would make a cycle.
+  pub fn add_child(&mut self, parent: N, child: N) {
+    assert!(
+      !self.ancestors(&parent).contains(&&child),
+      "adding the edge would make a cycle in the tree"
+    );
+    if let Some(old) = self.parents.insert(child.clone(), parent.clone()) {
+      if let Some(siblings) = self.children.get_mut(&old) {
+        siblings.retain(|c| *c != child);
+      }
+    }
+    self.children.entry(child.clone()).or_default();
+    self.children.entry(parent).or_default().push(child);
+  }
+
+  pub fn len(&self) -> usize {
+    self.children.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.children.is_empty()
+  }
+
+  pub fn nodes(&self) -> impl Iterator<Item = &N> {
+    self.children.keys()
+  }
+
+  pub fn parent(&self, node: &N) -> Option<&N> {
+    self.parents.get(node)
+  }
+
+  pub fn children(&self, node: &N) -> &[N] {
+    self.children.get(node).map_or(&[], |c| &c[..])
+  }
+
+  /// `node` and every node above it, ending with its root.
+  pub fn ancestors<'a>(&'a self, mut node: &'a N) -> Vec<&'a N> {
+    let mut chain = vec![node];
+    while let Some(parent) = self.parents.get(node) {
+      chain.push(parent);
+      node = parent;
+    }
+    chain
+  }
+
+  /// The number of edges between `node` and its root.
+  pub fn depth(&self, node: &N) -> usize {
+    self.ancestors(node).len() - 1
+  }
+
+  /// The deepest node that is an ancestor of both `a` and `b`, which may be `a` or `b` itself.
+  /// Returns `None` if they are in different trees.
+  pub fn lowest_common_ancestor<'a>(&'a self, a: &'a N, b: &'a N) -> Option<&'a N> {
+    let a_chain = self.ancestors(a);
+    let b_chain = self.ancestors(b);
+    // Walk down from the root while the chains agree.
+    a_chain
+      .iter()
+      .rev()
+      .zip(b_chain.iter().rev())
+      .take_while(|(x, y)| x == y)
+      .last()
+      .map(|(x, _)| *x)
+  }
+
+  /// The nodes from `a` up to their lowest common ancestor and back down to `b`.
+  pub fn path<'a>(&'a self, a: &'a N, b: &'a N) -> Option<Vec<&'a N>> {
+    let ancestor = self.lowest_common_ancestor(a, b)?;
+    let up = self.ancestors(a);
+    let down = self.ancestors(b);
+    let up_len = up.iter().position(|n| *n == ancestor)?;
+    let down_len = down.iter().position(|n| *n == ancestor)?;
+    let mut path: Vec<&N> = up[..=up_len].to_vec();
+    path.extend(down[..down_len].iter().rev());
+    Some(path)
+  }
+}
+
+impl<N> Default for Tree<N>
+where
+  N: Clone + Eq + Hash,
+{
+  fn default() -> Self {
+    Tree::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn grid_graph() -> Graph<(i32, i32)> {
+    // A 3x3 grid with the middle removed.
+    let mut graph = Graph::undirected();
+    for x in 0..3 {
+      for y in 0..3 {
+        for (nx, ny) in &[(x + 1, y), (x, y + 1)] {
+          if *nx < 3 && *ny < 3 && (x, y) != (1, 1) && (*nx, *ny) != (1, 1) {
+            graph.add_edge((x, y), (*nx, *ny));
+          }
+        }
+      }
+    }
+    graph
+  }
+
+  #[test]
+  fn test_traversal() {
+    let mut graph = Graph::directed();
+    graph.add_edge('a', 'b');
+    graph.add_edge('a', 'c');
+    graph.add_edge('b', 'd');
+    graph.add_edge('c', 'd');
+    graph.add_node('e');
+    assert_eq!(vec![&'a', &'b', &'c', &'d'], graph.bfs(&'a'));
+    assert_eq!(vec![&'a', &'b', &'d', &'c'], graph.dfs(&'a'));
+    assert_eq!(
+      Some(vec!['a', 'b', 'd']),
+      graph.dfs_path(&'a', &'d').map(|p| p.nodes)
+    );
+    assert_eq!(Some(2), graph.dfs_path(&'a', &'d').map(|p| p.cost));
+    assert_eq!(None, graph.dfs_path(&'d', &'a'));
+    assert_eq!(None, graph.dfs_path(&'a', &'e'));
+    assert_eq!(vec![&'d'], graph.bfs(&'d'));
+    assert_eq!(Vec::<&char>::new(), graph.bfs(&'z'));
+    assert_eq!(
+      vec![(&'b', 1), (&'c', 1)],
+      graph.neighbors(&'a').collect::<Vec<_>>()
+    );
+    assert_eq!(5, graph.len());
+  }
+
+  #[test]
+  fn test_shortest_paths() {
+    let graph = grid_graph();
+    let path = graph.shortest_path(&(0, 0), &(2, 2)).unwrap();
+    assert_eq!(4, path.cost);
+    assert_eq!(5, path.nodes.len());
+    assert_eq!(None, graph.shortest_path(&(0, 0), &(1, 1)));
+
+    let mut weighted = Graph::directed();
+    weighted.add_weighted_edge("a", "b", 1);
+    weighted.add_weighted_edge("b", "c", 1);
+    weighted.add_weighted_edge("a", "c", 5);
+    weighted.add_weighted_edge("c", "d", 1);
+    let path = weighted.dijkstra(&"a", &"d").unwrap();
+    assert_eq!(
+      Path {
+        nodes: vec!["a", "b", "c", "d"],
+        cost: 3
+      },
+      path
+    );
+    assert_eq!(2, weighted.shortest_path(&"a", &"d").unwrap().cost);
+    assert_eq!(None, weighted.dijkstra(&"d", &"a"));
+  }
+
+  #[test]
+  fn test_astar() {
+    let graph = grid_graph();
+    let goal = (2, 0);
+    let manhattan = |(x, y): &(i32, i32)| ((goal.0 - x).abs() + (goal.1 - y).abs()) as u64;
+    let path = graph.astar(&(0, 2), &goal, manhattan).unwrap();
+    assert_eq!(4, path.cost);
+    assert_eq!(Some(&(0, 2)), path.nodes.first());
+    assert_eq!(Some(&goal), path.nodes.last());
+  }
+
+  #[test]
+  fn test_components_and_sort() {
+    let mut graph = Graph::directed();
+    graph.add_edge("shirt", "tie");
+    graph.add_edge("tie", "jacket");
+    graph.add_edge("trousers", "shoes");
+    graph.add_edge("socks", "shoes");
+    graph.add_edge("trousers", "belt");
+    graph.add_edge("shirt", "belt");
+    graph.add_node("watch");
+    assert_eq!(
+      Some(vec![
+        &"shirt",
+        &"tie",
+        &"jacket",
+        &"trousers",
+        &"socks",
+        &"shoes",
+        &"belt",
+        &"watch"
+      ]),
+      graph.topological_sort()
+    );
+    assert_eq!(
+      vec![
+        vec![
+          &"shirt",
+          &"tie",
+          &"jacket",
+          &"trousers",
+          &"shoes",
+          &"socks",
+          &"belt"
+        ],
+        vec![&"watch"]
+      ],
+      graph.connected_components()
+    );
+    graph.add_edge("belt", "shirt");
+    assert_eq!(None, graph.topological_sort());
+  }
+
+  #[test]
+  fn test_tree() {
+    let mut tree = Tree::new();
+    for (parent, child) in &[("COM", "B"), ("B", "C"), ("C", "D"), ("B", "E"), ("E", "F")] {
+      tree.add_child(*parent, *child);
+    }
+    assert_eq!(6, tree.len());
+    assert_eq!(3, tree.depth(&"D"));
+    assert_eq!(vec![&"D", &"C", &"B", &"COM"], tree.ancestors(&"D"));
+    assert_eq!(Some(&"B"), tree.lowest_common_ancestor(&"D", &"F"));
+    assert_eq!(Some(&"C"), tree.lowest_common_ancestor(&"D", &"C"));
+    assert_eq!(
+      Some(vec![&"D", &"C", &"B", &"E", &"F"]),
+      tree.path(&"D", &"F")
+    );
+    assert_eq!(&["C", "E"], tree.children(&"B"));
+
+    tree.add_child("X", "Y");
+    assert_eq!(None, tree.lowest_common_ancestor(&"D", &"Y"));
+  }
+
+  #[test]
+  #[should_panic(expected = "would make a cycle")]
+  fn test_tree_cycle() {
+    let mut tree = Tree::new();
+    tree.add_child("A", "B");
+    tree.add_child("B", "C");
+    tree.add_child("C", "A");
+  }
+
+  #[test]
+  #[should_panic(expected = "would make a cycle")]
+  fn test_tree_self_loop() {
+    Tree::new().add_child("A", "A");
+  }
+}
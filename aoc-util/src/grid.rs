@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+/// Offsets to the orthogonal neighbors of a cell, with y growing downwards.
+const OFFSETS_4: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+/// Offsets to the orthogonal and diagonal neighbors of a cell, clockwise from the top left.
+const OFFSETS_8: [(isize, isize); 8] = [
+  (-1, -1),
+  (0, -1),
+  (1, -1),
+  (1, 0),
+  (1, 1),
+  (0, 1),
+  (-1, 1),
+  (-1, 0),
+];
+
+/// Which cells count as neighbors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbors {
+  /// Up, right, down and left.
+  Four,
+  /// The four orthogonal neighbors and the four diagonals.
+  Eight,
+}
+
+impl Neighbors {
+  fn offsets(self) -> &'static [(isize, isize)] {
+    match self {
+      Neighbors::Four => &OFFSETS_4,
+      Neighbors::Eight => &OFFSETS_8,
+    }
+  }
+}
+
+/// A fixed size, dense 2D grid addressed by `(x, y)`, with `(0, 0)` in the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+  width: usize,
+  height: usize,
+  cells: Vec<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGridError {
+  pub kind: ParseGridErrorKind,
+  /// The 1-based line of the problem.
+  pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseGridErrorKind {
+  UnknownChar { character: char, column: usize },
+  RowLength { expected: usize, found: usize },
+}
+
+impl<T> Grid<T> {
+  pub fn new(width: usize, height: usize, fill: T) -> Self
+  where
+    T: Clone,
+  {
+    Grid {
+      width,
+      height,
+      cells: vec![fill; width * height],
+    }
+  }
+
+  /// Parses one row per line of `text`, turning each character into a cell with `cell`. Every
+  /// line must be the same length.
+  pub fn parse<F>(text: &str, mut cell: F) -> Result<Self, ParseGridError>
+  where
+    F: FnMut(char) -> Option<T>,
+  {
+    let mut width = None;
+    let mut height = 0;
+    let mut cells = Vec::new();
+    for (y, line) in text.lines().enumerate() {
+      let start = cells.len();
+      for (x, c) in line.chars().enumerate() {
+        match cell(c) {
+          Some(value) => cells.push(value),
+          None => {
+            let kind = ParseGridErrorKind::UnknownChar {
+              character: c,
+              column: x + 1,
+            };
+            return Err(ParseGridError { kind, line: y + 1 });
+          }
+        }
+      }
+      let found = cells.len() - start;
+      match width {
+        Some(expected) if expected != found => {
+          let kind = ParseGridErrorKind::RowLength { expected, found };
+          return Err(ParseGridError { kind, line: y + 1 });
+        }
+        _ => width = Some(found),
+      }
+      height += 1;
+    }
+    Ok(Grid {
+      width: width.unwrap_or(0),
+      height,
+      cells,
+    })
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn in_bounds(&self, x: isize, y: isize) -> bool {
+    x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+  }
+
+  pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+    match x < self.width && y < self.height {
+      true => Some(&self.cells[y * self.width + x]),
+      false => None,
+    }
+  }
+
+  pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+    match x < self.width && y < self.height {
+      true => Some(&mut self.cells[y * self.width + x]),
+      false => None,
+    }
+  }
+
+  /// Replaces the cell at `(x, y)`, panicking if it is out of bounds.
+  pub fn set(&mut self, x: usize, y: usize, value: T) {
+    self[(x, y)] = value;
+  }
+
+  /// The positions next to `(x, y)` that are inside the grid.
+  pub fn neighbors(
+    &self,
+    x: usize,
+    y: usize,
+    neighbors: Neighbors,
+  ) -> impl Iterator<Item = (usize, usize)> + '_ {
+    neighbors
+      .offsets()
+      .iter()
+      .map(move |(dx, dy)| (x as isize + dx, y as isize + dy))
+      .filter(move |(x, y)| self.in_bounds(*x, *y))
+      .map(|(x, y)| (x as usize, y as usize))
+  }
+
+  /// The cells of row `y`, left to right. Panics if `y` is outside the grid.
+  pub fn row(&self, y: usize) -> &[T] {
+    assert!(
+      y < self.height,
+      "row {} is outside a grid of height {}",
+      y,
+      self.height
+    );
+    &self.cells[y * self.width..(y + 1) * self.width]
+  }
+
+  pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+    // `chunks` panics on 0, and an empty grid has no rows anyway.
+    self.cells.chunks(self.width.max(1))
+  }
+
+  /// The cells of column `x`, top to bottom. Panics if `x` is outside the grid.
+  pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+    assert!(
+      x < self.width,
+      "column {} is outside a grid of width {}",
+      x,
+      self.width
+    );
+    self.cells.iter().skip(x).step_by(self.width)
+  }
+
+  /// Every cell with its position, row by row.
+  pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+    let width = self.width;
+    self
+      .cells
+      .iter()
+      .enumerate()
+      .map(move |(i, cell)| ((i % width, i / width), cell))
+  }
+
+  /// Draws the grid with one line per row.
+  pub fn render<F>(&self, mut cell: F) -> String
+  where
+    F: FnMut(&T) -> char,
+  {
+    let mut out = String::with_capacity((self.width + 1) * self.height);
+    for row in self.rows() {
+      out.extend(row.iter().map(&mut cell));
+      out.push('\n');
+    }
+    out
+  }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+  type Output = T;
+
+  fn index(&self, (x, y): (usize, usize)) -> &T {
+    self
+      .get(x, y)
+      .unwrap_or_else(|| panic!("({}, {}) is outside the grid", x, y))
+  }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+  fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+    self
+      .get_mut(x, y)
+      .unwrap_or_else(|| panic!("({}, {}) is outside the grid", x, y))
+  }
+}
+
+/// A 2D grid that grows to fit whatever is stored in it, for when the extent isn't known up front.
+/// Coordinates may be negative, and cells that were never set are empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseGrid<T> {
+  cells: HashMap<(isize, isize), T>,
+}
+
+impl<T> SparseGrid<T> {
+  pub fn new() -> Self {
+    SparseGrid {
+      cells: HashMap::new(),
+    }
+  }
+
+  /// The number of cells that have been set.
+  pub fn len(&self) -> usize {
+    self.cells.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.cells.is_empty()
+  }
+
+  pub fn get(&self, x: isize, y: isize) -> Option<&T> {
+    self.cells.get(&(x, y))
+  }
+
+  pub fn get_mut(&mut self, x: isize, y: isize) -> Option<&mut T> {
+    self.cells.get_mut(&(x, y))
+  }
+
+  /// Sets the cell at `(x, y)`, returning what was there before.
+  pub fn insert(&mut self, x: isize, y: isize, value: T) -> Option<T> {
+    self.cells.insert((x, y), value)
+  }
+
+  pub fn remove(&mut self, x: isize, y: isize) -> Option<T> {
+    self.cells.remove(&(x, y))
+  }
+
+  /// The smallest and largest `(x, y)` of the cells that are set, or `None` if there are none.
+  pub fn bounds(&self) -> Option<((isize, isize), (isize, isize))> {
+    let mut keys = self.cells.keys();
+    let first = *keys.next()?;
+    Some(keys.fold((first, first), |((x1, y1), (x2, y2)), (x, y)| {
+      ((x1.min(*x), y1.min(*y)), (x2.max(*x), y2.max(*y)))
+    }))
+  }
+
+  /// The positions next to `(x, y)`. A sparse grid has no edges, so there are always 4 or 8.
+  pub fn neighbors(
+    &self,
+    x: isize,
+    y: isize,
+    neighbors: Neighbors,
+  ) -> impl Iterator<Item = (isize, isize)> {
+    neighbors
+      .offsets()
+      .iter()
+      .map(move |(dx, dy)| (x + dx, y + dy))
+  }
+
+  /// The cells of row `y` between the left and right edges of `bounds`.
+  pub fn row(&self, y: isize) -> impl Iterator<Item = Option<&T>> {
+    let (x1, x2) = self.bounds().map_or((0, -1), |((x1, _), (x2, _))| (x1, x2));
+    (x1..=x2).map(move |x| self.get(x, y))
+  }
+
+  /// The cells of column `x` between the top and bottom edges of `bounds`.
+  pub fn column(&self, x: isize) -> impl Iterator<Item = Option<&T>> {
+    let (y1, y2) = self.bounds().map_or((0, -1), |((_, y1), (_, y2))| (y1, y2));
+    (y1..=y2).map(move |y| self.get(x, y))
+  }
+
+  /// Every cell that is set with its position, in no particular order.
+  pub fn iter(&self) -> impl Iterator<Item = ((isize, isize), &T)> {
+    self.cells.iter().map(|(pos, cell)| (*pos, cell))
+  }
+
+  /// Draws the area inside `bounds` with one line per row, passing `None` for empty cells.
+  pub fn render<F>(&self, mut cell: F) -> String
+  where
+    F: FnMut(Option<&T>) -> char,
+  {
+    let mut out = String::new();
+    if let Some(((_, y1), (_, y2))) = self.bounds() {
+      for y in y1..=y2 {
+        out.extend(self.row(y).map(&mut cell));
+        out.push('\n');
+      }
+    }
+    out
+  }
+}
+
+impl<T> Default for SparseGrid<T> {
+  fn default() -> Self {
+    SparseGrid::new()
+  }
+}
+
+impl fmt::Display for ParseGridError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.kind {
+      ParseGridErrorKind::UnknownChar { character, column } => write!(
+        f,
+        "unknown character {:?} at line {}, column {}",
+        character, self.line, column
+      ),
+      ParseGridErrorKind::RowLength { expected, found } => write!(
+        f,
+        "line {} has {} cells, expected {}",
+        self.line, found, expected
+      ),
+    }
+  }
+}
+
+impl Error for ParseGridError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(text: &str) -> Result<Grid<bool>, ParseGridError> {
+    Grid::parse(text, |c| match c {
+      '#' => Some(true),
+      '.' => Some(false),
+      _ => None,
+    })
+  }
+
+  #[test]
+  #[should_panic(expected = "column 3 is outside a grid of width 3")]
+  fn test_column_out_of_range() {
+    parse("#..\n.#.\n").unwrap().column(3).count();
+  }
+
+  #[test]
+  #[should_panic(expected = "row 2 is outside a grid of height 2")]
+  fn test_row_out_of_range() {
+    parse("#..\n.#.\n").unwrap().row(2);
+  }
+
+  #[test]
+  fn test_parse_and_render() {
+    let grid = parse("#..\n.#.\n").unwrap();
+    assert_eq!((3, 2), (grid.width(), grid.height()));
+    assert!(grid[(1, 1)]);
+    assert_eq!(None, grid.get(3, 0));
+    assert_eq!(&[false, true, false], grid.row(1));
+    assert_eq!(vec![&false, &true], grid.column(1).collect::<Vec<_>>());
+    assert_eq!(2, grid.rows().count());
+    assert_eq!(
+      vec![(0, 0), (1, 1)],
+      grid
+        .iter()
+        .filter(|(_, c)| **c)
+        .map(|(p, _)| p)
+        .collect::<Vec<_>>()
+    );
+    assert_eq!(
+      "#..\n.#.\n",
+      grid.render(|c| match c {
+        true => '#',
+        false => '.',
+      })
+    );
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    assert_eq!(
+      Err(ParseGridError {
+        kind: ParseGridErrorKind::UnknownChar {
+          character: 'x',
+          column: 2
+        },
+        line: 2
+      }),
+      parse("..\n.x")
+    );
+    assert_eq!(
+      "line 2 has 1 cells, expected 2",
+      parse("..\n.").unwrap_err().to_string()
+    );
+    assert_eq!(0, parse("").unwrap().rows().count());
+  }
+
+  #[test]
+  fn test_neighbors() {
+    let mut grid = Grid::new(3, 3, 0);
+    grid.set(2, 2, 5);
+    assert_eq!(5, grid[(2, 2)]);
+    assert_eq!(
+      vec![(1, 0), (0, 1)],
+      grid.neighbors(0, 0, Neighbors::Four).collect::<Vec<_>>()
+    );
+    assert_eq!(8, grid.neighbors(1, 1, Neighbors::Eight).count());
+    assert_eq!(3, grid.neighbors(2, 2, Neighbors::Eight).count());
+  }
+
+  #[test]
+  fn test_sparse() {
+    let mut grid = SparseGrid::new();
+    assert_eq!(None, grid.bounds());
+    assert_eq!("", grid.render(|_: Option<&char>| ' '));
+    grid.insert(-2, 1, 'a');
+    grid.insert(1, -1, 'b');
+    assert_eq!(None, grid.insert(0, 0, 'c'));
+    assert_eq!(Some('c'), grid.insert(0, 0, 'd'));
+    assert_eq!(3, grid.len());
+    assert_eq!(Some(((-2, -1), (1, 1))), grid.bounds());
+    assert_eq!(
+      vec![None, None, Some(&'d'), None],
+      grid.row(0).collect::<Vec<_>>()
+    );
+    assert_eq!(3, grid.column(0).count());
+    assert_eq!("...b\n..d.\na...\n", grid.render(|c| *c.unwrap_or(&'.')));
+    assert_eq!(vec![(-1, 0), (0, -1), (0, 1), (1, 0)], {
+      let mut n: Vec<_> = grid.neighbors(0, 0, Neighbors::Four).collect();
+      n.sort();
+      n
+    });
+  }
+}
@@ -0,0 +1,334 @@
+use super::{Grid, ParseGridError};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The environment variable that overrides where `load_input` reads from.
+pub const INPUT_VAR: &str = "AOC_INPUT";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseInputError {
+  pub kind: ParseInputErrorKind,
+  /// The 1-based line of the problem.
+  pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseInputErrorKind {
+  /// `text` didn't parse as the requested type, for `reason`.
+  Invalid {
+    text: String,
+    reason: String,
+  },
+  MissingSeparator {
+    separator: String,
+  },
+  /// The line didn't match the literal text of a `Pattern`.
+  Mismatch {
+    expected: String,
+    found: String,
+  },
+}
+
+/// Reads a day's puzzle input. Call it with `env!("CARGO_MANIFEST_DIR")` to read `input.txt`
+/// next to the crate's manifest, wherever the binary is run from. The first command line
+/// argument, or else the `AOC_INPUT` environment variable, overrides the path.
+pub fn load_input(manifest_dir: &str) -> io::Result<String> {
+  let path = input_path(manifest_dir, env::args().nth(1), env::var(INPUT_VAR).ok());
+  fs::read_to_string(&path)
+    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+fn input_path(manifest_dir: &str, arg: Option<String>, var: Option<String>) -> PathBuf {
+  match arg.or(var).filter(|path| !path.is_empty()) {
+    Some(path) => PathBuf::from(path),
+    None => Path::new(manifest_dir).join("input.txt"),
+  }
+}
+
+/// The numbered lines of `input`, ignoring trailing blank lines.
+fn numbered_lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+  input
+    .trim_end()
+    .lines()
+    .enumerate()
+    .map(|(i, line)| (i + 1, line))
+}
+
+fn parse_value<T>(text: &str, line: usize) -> Result<T, ParseInputError>
+where
+  T: FromStr,
+  T::Err: fmt::Display,
+{
+  let text = text.trim();
+  text.parse().map_err(|e: T::Err| ParseInputError {
+    kind: ParseInputErrorKind::Invalid {
+      text: text.to_string(),
+      reason: e.to_string(),
+    },
+    line,
+  })
+}
+
+/// Parses values separated by commas, like `1,-2,3`, which may continue over several lines.
+pub fn parse_comma_separated<T>(input: &str) -> Result<Vec<T>, ParseInputError>
+where
+  T: FromStr,
+  T::Err: fmt::Display,
+{
+  let mut values = vec![];
+  for (line, text) in numbered_lines(input) {
+    for field in text.split(',') {
+      values.push(parse_value(field, line)?);
+    }
+  }
+  Ok(values)
+}
+
+/// Parses one value per line.
+pub fn parse_lines<T>(input: &str) -> Result<Vec<T>, ParseInputError>
+where
+  T: FromStr,
+  T::Err: fmt::Display,
+{
+  numbered_lines(input)
+    .map(|(line, text)| parse_value(text, line))
+    .collect()
+}
+
+/// Parses a rectangle of characters into a grid of them.
+pub fn parse_char_grid(input: &str) -> Result<Grid<char>, ParseGridError> {
+  Grid::parse(input.trim_end_matches(&['\r', '\n'][..]), Some)
+}
+
+/// Splits each line into a key and value around the first `separator`, like `COM)B` around
+/// `)`. Both sides are trimmed of whitespace.
+pub fn parse_key_values<'a>(
+  input: &'a str,
+  separator: &str,
+) -> Result<Vec<(&'a str, &'a str)>, ParseInputError> {
+  numbered_lines(input)
+    .map(|(line, text)| {
+      let index = text.find(separator).ok_or_else(|| ParseInputError {
+        kind: ParseInputErrorKind::MissingSeparator {
+          separator: separator.to_string(),
+        },
+        line,
+      })?;
+      let value = &text[index + separator.len()..];
+      Ok((text[..index].trim(), value.trim()))
+    })
+    .collect()
+}
+
+/// A line template with `{}` where each field goes, like `<x={}, y={}, z={}>`, for pulling the
+/// fields out of lines without splitting them by hand. A field runs up to the first occurrence
+/// of the literal text after it, and a field at the end of the template takes the rest of the
+/// line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+  /// The literal text around the fields, one more than there are fields.
+  literals: Vec<String>,
+}
+
+impl Pattern {
+  pub fn new(template: &str) -> Self {
+    Pattern {
+      literals: template.split("{}").map(String::from).collect(),
+    }
+  }
+
+  /// The raw text of each field in `text`, which is treated as the first line.
+  pub fn extract<'a>(&self, text: &'a str) -> Result<Vec<&'a str>, ParseInputError> {
+    self.fields(text, 1)
+  }
+
+  /// Parses each field of `text`, which is treated as the first line, as a `T`.
+  pub fn parse<T>(&self, text: &str) -> Result<Vec<T>, ParseInputError>
+  where
+    T: FromStr,
+    T::Err: fmt::Display,
+  {
+    self.parse_line(text, 1)
+  }
+
+  /// Parses the fields of every line, giving one `Vec` of values per line.
+  pub fn parse_lines<T>(&self, input: &str) -> Result<Vec<Vec<T>>, ParseInputError>
+  where
+    T: FromStr,
+    T::Err: fmt::Display,
+  {
+    numbered_lines(input)
+      .map(|(line, text)| self.parse_line(text, line))
+      .collect()
+  }
+
+  fn parse_line<T>(&self, text: &str, line: usize) -> Result<Vec<T>, ParseInputError>
+  where
+    T: FromStr,
+    T::Err: fmt::Display,
+  {
+    self
+      .fields(text, line)?
+      .into_iter()
+      .map(|field| parse_value(field, line))
+      .collect()
+  }
+
+  fn fields<'a>(&self, text: &'a str, line: usize) -> Result<Vec<&'a str>, ParseInputError> {
+    let mismatch = |expected: &str, found: &str| ParseInputError {
+      kind: ParseInputErrorKind::Mismatch {
+        expected: expected.to_string(),
+        found: found.to_string(),
+      },
+      line,
+    };
+    let (first, rest) = self.literals.split_first().unwrap();
+    if !text.starts_with(first.as_str()) {
+      return Err(mismatch(first, text));
+    }
+    let mut remaining = &text[first.len()..];
+    let mut fields = vec![];
+    for (i, literal) in rest.iter().enumerate() {
+      let end = match i == rest.len() - 1 {
+        // The last literal has to end the line, so there's nothing left unmatched.
+        true => match remaining.ends_with(literal.as_str()) {
+          true => remaining.len() - literal.len(),
+          false => return Err(mismatch(literal, remaining)),
+        },
+        false => match remaining.find(literal.as_str()) {
+          Some(end) => end,
+          None => return Err(mismatch(literal, remaining)),
+        },
+      };
+      fields.push(&remaining[..end]);
+      remaining = &remaining[end + literal.len()..];
+    }
+    if rest.is_empty() && !remaining.is_empty() {
+      return Err(mismatch("end of line", remaining));
+    }
+    Ok(fields)
+  }
+}
+
+impl fmt::Display for ParseInputError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.kind {
+      ParseInputErrorKind::Invalid { text, reason } => write!(
+        f,
+        "invalid value {:?} at line {}: {}",
+        text, self.line, reason
+      ),
+      ParseInputErrorKind::MissingSeparator { separator } => {
+        write!(f, "line {} is missing separator {:?}", self.line, separator)
+      }
+      ParseInputErrorKind::Mismatch { expected, found } => write!(
+        f,
+        "expected {:?} at line {}, found {:?}",
+        expected, self.line, found
+      ),
+    }
+  }
+}
+
+impl Error for ParseInputError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_input_path() {
+    let dir = "/aoc/aoc-01";
+    assert_eq!(
+      PathBuf::from("/aoc/aoc-01/input.txt"),
+      input_path(dir, None, None)
+    );
+    let var = Some("var.txt".to_string());
+    assert_eq!(PathBuf::from("var.txt"), input_path(dir, None, var.clone()));
+    let arg = Some("arg.txt".to_string());
+    assert_eq!(PathBuf::from("arg.txt"), input_path(dir, arg, var));
+    let empty = Some(String::new());
+    assert_eq!(
+      PathBuf::from("/aoc/aoc-01/input.txt"),
+      input_path(dir, None, empty)
+    );
+  }
+
+  #[test]
+  fn test_parse_numbers() {
+    assert_eq!(
+      Ok(vec![1, -2, 3]),
+      parse_comma_separated::<i64>("1, -2,3\n")
+    );
+    assert_eq!(
+      Ok(vec![1, 2, 3, 4]),
+      parse_comma_separated::<u8>("1,2\n3,4\n\n")
+    );
+    assert_eq!(Ok(vec![12.0, 14.5]), parse_lines::<f64>("12\r\n14.5\r\n"));
+    assert_eq!(Ok(Vec::<i32>::new()), parse_lines::<i32>(""));
+    assert_eq!(
+      "invalid value \"x\" at line 2: invalid digit found in string",
+      parse_lines::<i32>("1\nx\n3").unwrap_err().to_string()
+    );
+    assert_eq!(
+      "invalid value \"\" at line 1: cannot parse integer from empty string",
+      parse_comma_separated::<i32>("1,,2")
+        .unwrap_err()
+        .to_string()
+    );
+  }
+
+  #[test]
+  fn test_parse_char_grid_and_key_values() {
+    let grid = parse_char_grid(".#\n#.\n\n").unwrap();
+    assert_eq!((2, 2), (grid.width(), grid.height()));
+    assert_eq!('#', grid[(1, 0)]);
+
+    assert_eq!(
+      Ok(vec![("COM", "B"), ("B", "C")]),
+      parse_key_values("COM)B\nB)C\n", ")")
+    );
+    assert_eq!(Ok(vec![("a", "b = c")]), parse_key_values("a = b = c", "="));
+    assert_eq!(
+      "line 2 is missing separator \")\"",
+      parse_key_values("A)B\nC", ")").unwrap_err().to_string()
+    );
+  }
+
+  #[test]
+  fn test_pattern() {
+    let moon = Pattern::new("<x={}, y={}, z={}>");
+    assert_eq!(Ok(vec![-1, 0, 2]), moon.parse::<i16>("<x=-1, y=0, z=2>"));
+    assert_eq!(
+      Ok(vec![vec![1, 2, 3], vec![4, 5, 6]]),
+      moon.parse_lines::<i16>("<x=1, y=2, z=3>\n<x=4, y=5, z=6>\n")
+    );
+    assert_eq!(
+      "expected \", z=\" at line 2, found \"5>\"",
+      moon
+        .parse_lines::<i16>("<x=1, y=2, z=3>\n<x=4, y=5>")
+        .unwrap_err()
+        .to_string()
+    );
+    assert_eq!(
+      "invalid value \"a\" at line 1: invalid digit found in string",
+      moon
+        .parse::<i16>("<x=a, y=0, z=2>")
+        .unwrap_err()
+        .to_string()
+    );
+
+    let step = Pattern::new("{}{}");
+    assert_eq!(Ok(vec!["", "R75"]), step.extract("R75"));
+    let rule = Pattern::new("{} => {}");
+    assert_eq!(Ok(vec!["7 A, 1 B", "1 C"]), rule.extract("7 A, 1 B => 1 C"));
+    let literal = Pattern::new("halt");
+    assert_eq!(Ok(vec![]), literal.extract("halt"));
+    assert!(literal.extract("halted").is_err());
+  }
+}
@@ -0,0 +1,429 @@
+use super::Point2;
+use num_traits::PrimInt;
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+
+/// The integers from `start` to `end`, inclusive. Never empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval<T> {
+  start: T,
+  end: T,
+}
+
+/// The values of an `Interval`, in order.
+#[derive(Debug, Clone)]
+pub struct IntervalIter<T> {
+  front: T,
+  back: T,
+  done: bool,
+}
+
+/// A set of integers stored as the sorted, disjoint intervals it covers, so huge ranges cost no
+/// more than small ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RangeSet<T> {
+  /// Sorted, with a gap of at least one value between neighbors.
+  intervals: Vec<Interval<T>>,
+}
+
+/// A horizontal or vertical line segment, with both ends included, stored as the range of
+/// coordinates it covers on each axis. One of the ranges is always a single value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Segment<T> {
+  x: Interval<T>,
+  y: Interval<T>,
+}
+
+impl<T: PrimInt> Interval<T> {
+  /// Panics if `start` is after `end`.
+  pub fn new(start: T, end: T) -> Self {
+    assert!(start <= end, "interval start is after its end");
+    Interval { start, end }
+  }
+
+  /// The interval from `a` to `b`, in either order.
+  pub fn between(a: T, b: T) -> Self {
+    Interval {
+      start: a.min(b),
+      end: a.max(b),
+    }
+  }
+
+  pub fn single(value: T) -> Self {
+    Interval {
+      start: value,
+      end: value,
+    }
+  }
+
+  pub fn start(&self) -> T {
+    self.start
+  }
+
+  pub fn end(&self) -> T {
+    self.end
+  }
+
+  /// The number of values in the interval, or `None` if that doesn't fit in `T`.
+  pub fn size(&self) -> Option<T> {
+    self.end.checked_sub(&self.start)?.checked_add(&T::one())
+  }
+
+  pub fn contains(&self, value: T) -> bool {
+    self.start <= value && value <= self.end
+  }
+
+  pub fn contains_interval(&self, other: &Self) -> bool {
+    self.start <= other.start && other.end <= self.end
+  }
+
+  pub fn overlaps(&self, other: &Self) -> bool {
+    self.start <= other.end && other.start <= self.end
+  }
+
+  /// Whether the intervals overlap or sit next to each other, so their union is one interval.
+  fn touches(&self, other: &Self) -> bool {
+    let before = |a: &Self, b: &Self| match a.end.checked_add(&T::one()) {
+      Some(next) => next < b.start,
+      None => false,
+    };
+    !before(self, other) && !before(other, self)
+  }
+
+  pub fn intersection(&self, other: &Self) -> Option<Self> {
+    match self.overlaps(other) {
+      true => Some(Interval {
+        start: self.start.max(other.start),
+        end: self.end.min(other.end),
+      }),
+      false => None,
+    }
+  }
+
+  /// The parts of `self` outside `other`: none, one, or two if `other` splits it in the middle.
+  pub fn difference(&self, other: &Self) -> Vec<Self> {
+    if !self.overlaps(other) {
+      return vec![*self];
+    }
+    let mut parts = vec![];
+    if self.start < other.start {
+      parts.push(Interval::new(self.start, other.start - T::one()));
+    }
+    if other.end < self.end {
+      parts.push(Interval::new(other.end + T::one(), self.end));
+    }
+    parts
+  }
+
+  pub fn iter(&self) -> IntervalIter<T> {
+    IntervalIter {
+      front: self.start,
+      back: self.end,
+      done: false,
+    }
+  }
+}
+
+impl<T: PrimInt> Iterator for IntervalIter<T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<T> {
+    if self.done {
+      return None;
+    }
+    let value = self.front;
+    match self.front == self.back {
+      true => self.done = true,
+      false => self.front = self.front + T::one(),
+    }
+    Some(value)
+  }
+}
+
+impl<T: PrimInt> DoubleEndedIterator for IntervalIter<T> {
+  fn next_back(&mut self) -> Option<T> {
+    if self.done {
+      return None;
+    }
+    let value = self.back;
+    match self.front == self.back {
+      true => self.done = true,
+      false => self.back = self.back - T::one(),
+    }
+    Some(value)
+  }
+}
+
+impl<T: PrimInt> RangeSet<T> {
+  pub fn new() -> Self {
+    RangeSet { intervals: vec![] }
+  }
+
+  /// The disjoint intervals making up the set, in order.
+  pub fn intervals(&self) -> &[Interval<T>] {
+    &self.intervals
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.intervals.is_empty()
+  }
+
+  /// The number of values in the set, or `None` if that doesn't fit in `T`.
+  pub fn size(&self) -> Option<T> {
+    self.intervals.iter().try_fold(T::zero(), |acc, interval| {
+      acc.checked_add(&interval.size()?)
+    })
+  }
+
+  pub fn contains(&self, value: T) -> bool {
+    self
+      .intervals
+      .binary_search_by(|interval| {
+        if interval.end < value {
+          Ordering::Less
+        } else if interval.start > value {
+          Ordering::Greater
+        } else {
+          Ordering::Equal
+        }
+      })
+      .is_ok()
+  }
+
+  /// Whether every value of `interval` is in the set.
+  pub fn contains_interval(&self, interval: &Interval<T>) -> bool {
+    self.intervals.iter().any(|i| i.contains_interval(interval))
+  }
+
+  pub fn insert(&mut self, interval: Interval<T>) {
+    self.intervals.push(interval);
+    self.normalize();
+  }
+
+  pub fn remove(&mut self, interval: &Interval<T>) {
+    self.intervals = self
+      .intervals
+      .iter()
+      .flat_map(|i| i.difference(interval))
+      .collect();
+  }
+
+  pub fn union(&self, other: &Self) -> Self {
+    self
+      .intervals
+      .iter()
+      .chain(&other.intervals)
+      .cloned()
+      .collect()
+  }
+
+  pub fn intersection(&self, other: &Self) -> Self {
+    let (a, b) = (&self.intervals, &other.intervals);
+    let (mut i, mut j) = (0, 0);
+    let mut intervals = vec![];
+    while i < a.len() && j < b.len() {
+      if let Some(overlap) = a[i].intersection(&b[j]) {
+        intervals.push(overlap);
+      }
+      match a[i].end < b[j].end {
+        true => i += 1,
+        false => j += 1,
+      }
+    }
+    RangeSet { intervals }
+  }
+
+  /// The values in `self` but not `other`.
+  pub fn difference(&self, other: &Self) -> Self {
+    let mut result = self.clone();
+    for interval in &other.intervals {
+      result.remove(interval);
+    }
+    result
+  }
+
+  /// Every value in the set, in order.
+  pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+    self.intervals.iter().flat_map(|interval| interval.iter())
+  }
+
+  /// Sorts the intervals and merges any that overlap or touch.
+  fn normalize(&mut self) {
+    self.intervals.sort();
+    let mut merged: Vec<Interval<T>> = Vec::with_capacity(self.intervals.len());
+    for interval in self.intervals.drain(..) {
+      match merged.last_mut() {
+        Some(last) if last.touches(&interval) => last.end = last.end.max(interval.end),
+        _ => merged.push(interval),
+      }
+    }
+    self.intervals = merged;
+  }
+}
+
+impl<T: PrimInt> Default for RangeSet<T> {
+  fn default() -> Self {
+    RangeSet::new()
+  }
+}
+
+impl<T: PrimInt> FromIterator<Interval<T>> for RangeSet<T> {
+  fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+    let mut set = RangeSet {
+      intervals: iter.into_iter().collect(),
+    };
+    set.normalize();
+    set
+  }
+}
+
+impl<T: PrimInt> Segment<T> {
+  /// The segment from `a` to `b`. Panics if they aren't in a horizontal or vertical line.
+  pub fn new(a: Point2<T>, b: Point2<T>) -> Self {
+    assert!(a.x == b.x || a.y == b.y, "segment is not axis-aligned");
+    Segment {
+      x: Interval::between(a.x, b.x),
+      y: Interval::between(a.y, b.y),
+    }
+  }
+
+  /// The range of x coordinates the segment covers.
+  pub fn x(&self) -> Interval<T> {
+    self.x
+  }
+
+  /// The range of y coordinates the segment covers.
+  pub fn y(&self) -> Interval<T> {
+    self.y
+  }
+
+  pub fn is_horizontal(&self) -> bool {
+    self.y.start == self.y.end
+  }
+
+  pub fn is_vertical(&self) -> bool {
+    self.x.start == self.x.end
+  }
+
+  pub fn contains(&self, point: &Point2<T>) -> bool {
+    self.x.contains(point.x) && self.y.contains(point.y)
+  }
+
+  /// Where the segments meet: a single point if they cross or touch, a shorter segment if they
+  /// overlap along the same line, or `None`.
+  pub fn intersection(&self, other: &Self) -> Option<Self> {
+    Some(Segment {
+      x: self.x.intersection(&other.x)?,
+      y: self.y.intersection(&other.y)?,
+    })
+  }
+
+  /// Every point on the segment, from the top left.
+  pub fn points(&self) -> impl Iterator<Item = Point2<T>> {
+    let y = self.y;
+    self
+      .x
+      .iter()
+      .flat_map(move |x| y.iter().map(move |y| Point2::new(x, y)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_interval() {
+    let a = Interval::new(1, 10);
+    assert_eq!((1, 10), (a.start(), a.end()));
+    assert_eq!(Interval::new(3, 5), Interval::between(5, 3));
+    assert_eq!(Some(10), a.size());
+    assert_eq!(None, Interval::new(i8::MIN, i8::MAX).size());
+    assert!(a.contains(10) && !a.contains(11));
+    assert!(a.contains_interval(&Interval::new(2, 9)));
+    assert_eq!(
+      Some(Interval::new(8, 10)),
+      a.intersection(&Interval::new(8, 20))
+    );
+    assert_eq!(None, a.intersection(&Interval::new(11, 20)));
+    assert_eq!(
+      vec![Interval::new(1, 2), Interval::new(6, 10)],
+      a.difference(&Interval::new(3, 5))
+    );
+    assert_eq!(
+      Vec::<Interval<i32>>::new(),
+      a.difference(&Interval::new(0, 11))
+    );
+    assert_eq!(vec![a], a.difference(&Interval::new(11, 12)));
+    assert_eq!(
+      vec![1, 2, 3],
+      Interval::new(1, 3).iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+      vec![3, 2, 1],
+      Interval::new(1, 3).iter().rev().collect::<Vec<_>>()
+    );
+    assert_eq!(256, Interval::new(0u8, u8::MAX).iter().count());
+  }
+
+  #[test]
+  fn test_range_set() {
+    let mut set: RangeSet<i32> = vec![
+      Interval::new(5, 8),
+      Interval::new(1, 2),
+      Interval::new(3, 3),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(&[Interval::new(1, 3), Interval::new(5, 8)], set.intervals());
+    assert_eq!(Some(7), set.size());
+    assert!(set.contains(3) && !set.contains(4) && set.contains(8));
+    assert!(set.contains_interval(&Interval::new(5, 7)));
+    assert!(!set.contains_interval(&Interval::new(3, 5)));
+
+    set.insert(Interval::new(4, 4));
+    assert_eq!(&[Interval::new(1, 8)], set.intervals());
+    set.remove(&Interval::new(3, 6));
+    assert_eq!(vec![1, 2, 7, 8], set.iter().collect::<Vec<_>>());
+
+    let other: RangeSet<i32> = vec![Interval::new(2, 7)].into_iter().collect();
+    assert_eq!(&[Interval::new(1, 8)], set.union(&other).intervals());
+    assert_eq!(
+      &[Interval::new(2, 2), Interval::new(7, 7)],
+      set.intersection(&other).intervals()
+    );
+    assert_eq!(
+      &[Interval::new(1, 1), Interval::new(8, 8)],
+      set.difference(&other).intervals()
+    );
+    assert!(set.difference(&set).is_empty());
+
+    let edges: RangeSet<u8> = vec![Interval::new(0, 100), Interval::new(101, u8::MAX)]
+      .into_iter()
+      .collect();
+    assert_eq!(&[Interval::new(0, u8::MAX)], edges.intervals());
+  }
+
+  #[test]
+  fn test_segment() {
+    let horizontal = Segment::new(Point2::new(0, 5), Point2::new(8, 5));
+    let vertical = Segment::new(Point2::new(3, 9), Point2::new(3, 2));
+    assert!(horizontal.is_horizontal() && vertical.is_vertical());
+    assert_eq!(Interval::new(2, 9), vertical.y());
+    assert_eq!(Interval::single(3), vertical.x());
+    let crossing = horizontal.intersection(&vertical).unwrap();
+    assert_eq!(
+      vec![Point2::new(3, 5)],
+      crossing.points().collect::<Vec<_>>()
+    );
+
+    let overlapping = Segment::new(Point2::new(6, 5), Point2::new(12, 5));
+    let shared = horizontal.intersection(&overlapping).unwrap();
+    assert_eq!(Segment::new(Point2::new(6, 5), Point2::new(8, 5)), shared);
+    assert_eq!(3, shared.points().count());
+
+    let parallel = Segment::new(Point2::new(0, 6), Point2::new(8, 6));
+    assert_eq!(None, horizontal.intersection(&parallel));
+    assert!(vertical.contains(&Point2::new(3, 2)));
+  }
+}
@@ -12,7 +12,11 @@ where
 {
   fn lcm(&self, b: T) -> T {
     let a = *self;
-    (a * b) / a.gcd(b)
+    if a == T::zero() || b == T::zero() {
+      return T::zero();
+    }
+    // Dividing first keeps the intermediate value no larger than the result.
+    (a / a.gcd(b)) * b
   }
 }
 
@@ -25,5 +29,7 @@ mod tests {
     assert_eq!(12, 4.lcm(6));
     assert_eq!(20, 4.lcm(5));
     assert_eq!(42, 21.lcm(6).lcm(7));
+    assert_eq!(0, 0.lcm(0));
+    assert_eq!(120, 60u8.lcm(40));
   }
 }
@@ -1,13 +1,38 @@
 extern crate num_traits;
 
+mod combinatorics;
+mod cycle;
 mod digits;
 mod direction;
 mod gcd;
+mod graph;
+mod grid;
+mod input;
+mod interval;
 mod lcm;
+mod number_theory;
 mod permutation;
+mod point;
 
-pub use digits::ToDigits;
-pub use direction::Direction;
+pub use combinatorics::{
+  Combinations, CombinationsWithReplacement, KPermutations, LexicographicPermutations,
+  Permutations, PowerSet, Product,
+};
+pub use cycle::{brent, find_cycle, floyd, simulate, Cycle};
+pub use digits::{from_digits, run_lengths, DigitOrder, Digits, ToDigits};
+pub use direction::{Direction, ParseDirectionError, YAxis};
 pub use gcd::GCD;
+pub use graph::{Graph, Path, Tree};
+pub use grid::{Grid, Neighbors, ParseGridError, ParseGridErrorKind, SparseGrid};
+pub use input::{
+  load_input, parse_char_grid, parse_comma_separated, parse_key_values, parse_lines,
+  ParseInputError, ParseInputErrorKind, Pattern, INPUT_VAR,
+};
+pub use interval::{Interval, IntervalIter, RangeSet, Segment};
 pub use lcm::LCM;
+pub use number_theory::{
+  checked_lcm, chinese_remainder, extended_gcd, factorize, is_prime, isqrt, lcm_all, mod_inverse,
+  mod_pow, primes,
+};
 pub use permutation::Permutation;
+pub use point::{Point2, Point3};
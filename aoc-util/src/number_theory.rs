@@ -0,0 +1,308 @@
+use super::GCD;
+use num_traits::{FromPrimitive, PrimInt, Signed};
+use std::fmt::Display;
+
+/// `x mod m` in `0..m`, for a positive `m`.
+fn modulo<T: PrimInt>(x: T, m: T) -> T {
+  let r = x % m;
+  match r < T::zero() {
+    true => r + m,
+    false => r,
+  }
+}
+
+/// `(a * b) mod m` for `a` and `b` in `0..m`, without overflowing when the product doesn't fit.
+fn mul_mod<T: PrimInt>(a: T, b: T, m: T) -> T {
+  if let Some(product) = a.checked_mul(&b) {
+    return product % m;
+  }
+  // Double and add, keeping every intermediate value below `m`.
+  let add_mod = |x: T, y: T| match x >= m - y {
+    true => x - (m - y),
+    false => x + y,
+  };
+  let (mut a, mut b, mut result) = (a, b, T::zero());
+  while b > T::zero() {
+    if b & T::one() == T::one() {
+      result = add_mod(result, a);
+    }
+    a = add_mod(a, a);
+    b = b >> 1;
+  }
+  result
+}
+
+/// Returns `(g, x, y)` where `g` is the greatest common divisor of `a` and `b` and
+/// `a * x + b * y == g`.
+pub fn extended_gcd<T: PrimInt + Signed>(a: T, b: T) -> (T, T, T) {
+  let (mut r0, mut r1) = (a, b);
+  let (mut x0, mut x1) = (T::one(), T::zero());
+  let (mut y0, mut y1) = (T::zero(), T::one());
+  while r1 != T::zero() {
+    let q = r0 / r1;
+    let (r, x, y) = (r0 - q * r1, x0 - q * x1, y0 - q * y1);
+    r0 = r1;
+    r1 = r;
+    x0 = x1;
+    x1 = x;
+    y0 = y1;
+    y1 = y;
+  }
+  match r0 < T::zero() {
+    true => (-r0, -x0, -y0),
+    false => (r0, x0, y0),
+  }
+}
+
+/// The `x` in `0..m` with `a * x` congruent to 1 mod `m`, or `None` if `a` and `m` share a
+/// factor. Panics if `m` isn't positive.
+pub fn mod_inverse<T: PrimInt + Signed>(a: T, m: T) -> Option<T> {
+  assert!(m > T::zero(), "modulus must be positive");
+  let (g, x, _) = extended_gcd(modulo(a, m), m);
+  match g == T::one() {
+    true => Some(modulo(x, m)),
+    false => None,
+  }
+}
+
+/// `base` to the power of `exponent`, mod `m`, by repeated squaring. Intermediate products
+/// never overflow, even when `m` is close to the largest value of `T`. Panics if `m` isn't
+/// positive or `exponent` is negative.
+pub fn mod_pow<T: PrimInt>(base: T, exponent: T, m: T) -> T {
+  assert!(m > T::zero(), "modulus must be positive");
+  assert!(exponent >= T::zero(), "negative exponent");
+  let mut base = modulo(base, m);
+  let mut exponent = exponent;
+  let mut result = T::one() % m;
+  while exponent > T::zero() {
+    if exponent & T::one() == T::one() {
+      result = mul_mod(result, base, m);
+    }
+    base = mul_mod(base, base, m);
+    exponent = exponent >> 1;
+  }
+  result
+}
+
+/// Solves a system of congruences `x ≡ residue (mod modulus)` with the Chinese Remainder
+/// Theorem, returning the smallest non-negative `x` and the modulus of the combined congruence,
+/// the lowest common multiple of the moduli. The moduli needn't be coprime.
+///
+/// Returns `None` if a modulus isn't positive, the congruences contradict each other or the
+/// combined modulus overflows.
+pub fn chinese_remainder<T: PrimInt + Signed>(congruences: &[(T, T)]) -> Option<(T, T)> {
+  congruences
+    .iter()
+    .try_fold((T::zero(), T::one()), |(x, m), &(residue, modulus)| {
+      if modulus <= T::zero() {
+        return None;
+      }
+      let residue = modulo(residue, modulus);
+      let (g, p, _) = extended_gcd(m, modulus);
+      let difference = residue - x;
+      if difference % g != T::zero() {
+        return None;
+      }
+      // Find `t` with `x + m * t ≡ residue (mod modulus)`.
+      let step = modulus / g;
+      let t = mul_mod(modulo(difference / g, step), modulo(p, step), step);
+      let lcm = (m / g).checked_mul(&modulus)?;
+      Some((x + m * t, lcm))
+    })
+}
+
+/// The lowest common multiple of `a` and `b`, or `None` if it doesn't fit in `T`.
+pub fn checked_lcm<T>(a: T, b: T) -> Option<T>
+where
+  T: PrimInt + FromPrimitive + Display,
+{
+  if a == T::zero() || b == T::zero() {
+    return Some(T::zero());
+  }
+  (a / a.gcd(b)).checked_mul(&b)
+}
+
+/// The lowest common multiple of every value, or `None` if it doesn't fit in `T`. An empty
+/// list gives 1.
+pub fn lcm_all<T, I>(values: I) -> Option<T>
+where
+  T: PrimInt + FromPrimitive + Display,
+  I: IntoIterator<Item = T>,
+{
+  values
+    .into_iter()
+    .try_fold(T::one(), |acc, value| checked_lcm(acc, value))
+}
+
+/// Every prime up to and including `limit`, by the sieve of Eratosthenes.
+pub fn primes<T: PrimInt>(limit: T) -> Vec<T> {
+  let limit = match limit.to_usize() {
+    Some(limit) if limit >= 2 => limit,
+    _ => return vec![],
+  };
+  let mut composite = vec![false; limit + 1];
+  let mut i = 2;
+  while i * i <= limit {
+    if !composite[i] {
+      for multiple in (i * i..=limit).step_by(i) {
+        composite[multiple] = true;
+      }
+    }
+    i += 1;
+  }
+  (2..=limit)
+    .filter(|i| !composite[*i])
+    .map(|i| T::from(i).unwrap())
+    .collect()
+}
+
+pub fn is_prime<T: PrimInt>(n: T) -> bool {
+  let two = T::one() + T::one();
+  if n < two {
+    return false;
+  }
+  let mut d = two;
+  while d <= n / d {
+    if n % d == T::zero() {
+      return false;
+    }
+    d = d + T::one();
+  }
+  true
+}
+
+/// The prime factors of `n` with their exponents, smallest first, by trial division. Numbers
+/// below 2 have no factors.
+pub fn factorize<T: PrimInt>(n: T) -> Vec<(T, u32)> {
+  let mut factors = vec![];
+  let mut n = n;
+  let mut d = T::one() + T::one();
+  while d <= n / d {
+    let mut exponent = 0;
+    while n % d == T::zero() {
+      n = n / d;
+      exponent += 1;
+    }
+    if exponent > 0 {
+      factors.push((d, exponent));
+    }
+    d = d + T::one();
+  }
+  if n > T::one() {
+    factors.push((n, 1));
+  }
+  factors
+}
+
+/// The largest integer whose square is at most `n`. Panics if `n` is negative.
+pub fn isqrt<T: PrimInt>(n: T) -> T {
+  assert!(n >= T::zero(), "square root of negative number");
+  // Digit by digit, working down from the largest power of four `T` can hold.
+  let bits = T::zero().count_zeros() as usize;
+  let mut bit = T::one() << (bits - 2);
+  while bit > n {
+    bit = bit >> 2;
+  }
+  let (mut rest, mut root) = (n, T::zero());
+  while bit != T::zero() {
+    if rest >= root + bit {
+      rest = rest - (root + bit);
+      root = (root >> 1) + bit;
+    } else {
+      root = root >> 1;
+    }
+    bit = bit >> 2;
+  }
+  root
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_extended_gcd() {
+    assert_eq!((2, -9, 47), extended_gcd(240, 46));
+    assert_eq!((6, -1, 0), extended_gcd(-6, 0));
+    let (g, x, y) = extended_gcd(-35i64, 15);
+    assert_eq!(5, g);
+    assert_eq!(g, -35 * x + 15 * y);
+    assert_eq!(Some(4), mod_inverse(3, 11));
+    assert_eq!(Some(12), mod_inverse(-1, 13));
+    assert_eq!(None, mod_inverse(4, 10));
+  }
+
+  #[test]
+  #[should_panic(expected = "modulus must be positive")]
+  fn test_mod_pow_zero_modulus() {
+    mod_pow(2, 3, 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "modulus must be positive")]
+  fn test_mod_inverse_negative_modulus() {
+    mod_inverse(3, -11);
+  }
+
+  #[test]
+  fn test_mod_pow() {
+    assert_eq!(445, mod_pow(4, 13, 497));
+    assert_eq!(0, mod_pow(5, 0, 1));
+    assert_eq!(1, mod_pow(2u8, 0, 7));
+    // The Advent of Code 2019 day 22 shuffle needs products of 47-bit numbers.
+    let m: i64 = 119_315_717_514_047;
+    assert_eq!(1, mod_pow(123_456_789, m - 1, m));
+    assert_eq!(u64::MAX - 1, mod_pow(u64::MAX - 1, 3, u64::MAX));
+  }
+
+  #[test]
+  fn test_chinese_remainder() {
+    assert_eq!(
+      Some((23, 105)),
+      chinese_remainder(&[(2, 3), (3, 5), (2, 7)])
+    );
+    assert_eq!(
+      Some((1068781, 3162341)),
+      chinese_remainder(&[(0, 7), (-1, 13), (-4, 59), (-6, 31), (-7, 19)])
+    );
+    assert_eq!(Some((10, 12)), chinese_remainder(&[(2, 4), (4, 6)]));
+    assert_eq!(None, chinese_remainder(&[(1, 4), (2, 6)]));
+    assert_eq!(Some((0, 1)), chinese_remainder::<i32>(&[]));
+    assert_eq!(None, chinese_remainder(&[(0, 1i8 << 6), (0, 3)]));
+    assert_eq!(None, chinese_remainder(&[(1, 3), (0, 0)]));
+    assert_eq!(None, chinese_remainder(&[(1, -5)]));
+  }
+
+  #[test]
+  fn test_lcm_all() {
+    assert_eq!(Some(420), lcm_all(vec![4, 6, 5, 7]));
+    assert_eq!(Some(1), lcm_all(Vec::<u8>::new()));
+    assert_eq!(Some(0), lcm_all(vec![3, 0]));
+    assert_eq!(None, lcm_all(vec![16u8, 17]));
+    assert_eq!(Some(u64::MAX), checked_lcm(u64::MAX, u64::MAX));
+  }
+
+  #[test]
+  fn test_primes() {
+    assert_eq!(vec![2, 3, 5, 7, 11, 13, 17, 19], primes(20));
+    assert_eq!(Vec::<u8>::new(), primes(1u8));
+    assert_eq!(25, primes(100u8).len());
+    assert!(is_prime(2_147_483_647i32));
+    assert!(!is_prime(1));
+    assert!(!is_prime(91u8));
+    assert_eq!(vec![(2, 2), (3, 1), (7, 2)], factorize(588));
+    assert_eq!(vec![(4_294_967_291u64, 1)], factorize(4_294_967_291u64));
+    assert_eq!(Vec::<(i32, u32)>::new(), factorize(1));
+  }
+
+  #[test]
+  fn test_isqrt() {
+    assert_eq!(0, isqrt(0));
+    assert_eq!(3, isqrt(15));
+    assert_eq!(4, isqrt(16));
+    assert_eq!(11, isqrt(127i8));
+    assert_eq!(15, isqrt(255u8));
+    assert_eq!(u32::MAX as u64, isqrt(u64::MAX));
+    assert_eq!(3_037_000_499, isqrt(i64::MAX));
+  }
+}
@@ -1,3 +1,5 @@
+/// Every ordering of a list, collected up front. Holds all `n!` permutations in memory at
+/// once, so prefer the lazy `Permutations` iterator for anything beyond a handful of items.
 pub struct Permutation<T>
 where
   T: Copy,
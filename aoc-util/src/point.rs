@@ -0,0 +1,339 @@
+use super::direction::{Direction, YAxis};
+use num_traits::{Signed, Zero};
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A position or offset on a 2D plane, with y growing downwards like the rows of a `Grid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Point2<T> {
+  pub x: T,
+  pub y: T,
+}
+
+/// A position or offset in 3D space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Point3<T> {
+  pub x: T,
+  pub y: T,
+  pub z: T,
+}
+
+/// `|a - b|` for types that may be unsigned.
+fn abs_diff<T>(a: T, b: T) -> T
+where
+  T: PartialOrd + Sub<Output = T>,
+{
+  match a > b {
+    true => a - b,
+    false => b - a,
+  }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+  match a > b {
+    true => a,
+    false => b,
+  }
+}
+
+impl<T> Point2<T> {
+  pub fn new(x: T, y: T) -> Self {
+    Point2 { x, y }
+  }
+}
+
+impl<T> Point2<T>
+where
+  T: Copy + PartialOrd + Add<Output = T> + Sub<Output = T>,
+{
+  /// The number of orthogonal steps between the points.
+  pub fn manhattan(&self, other: &Self) -> T {
+    abs_diff(self.x, other.x) + abs_diff(self.y, other.y)
+  }
+
+  /// The number of steps between the points when diagonal steps are allowed.
+  pub fn chebyshev(&self, other: &Self) -> T {
+    max(abs_diff(self.x, other.x), abs_diff(self.y, other.y))
+  }
+}
+
+impl<T> Point2<T>
+where
+  T: Copy + Signed,
+{
+  /// The offset of one step in `direction`.
+  pub fn unit(direction: &Direction) -> Self {
+    let unit = |v: isize| match v {
+      1 => T::one(),
+      -1 => -T::one(),
+      _ => T::zero(),
+    };
+    let (x, y) = direction.offset(YAxis::GrowsDown);
+    Point2::new(unit(x), unit(y))
+  }
+
+  /// Rotates the point around the origin, treating `direction` as a turn from facing up: `Right`
+  /// turns a quarter clockwise, `Down` half way and `Left` a quarter counter-clockwise.
+  ///
+  /// Panics if `direction` is diagonal, as a 45 degree turn doesn't land on whole coordinates.
+  pub fn rotate(&self, direction: &Direction) -> Self {
+    match direction {
+      Direction::Up => *self,
+      Direction::Right => Point2::new(-self.y, self.x),
+      Direction::Down => Point2::new(-self.x, -self.y),
+      Direction::Left => Point2::new(self.y, -self.x),
+      _ => panic!("cannot rotate a point {}", direction),
+    }
+  }
+
+  /// The point `distance` steps away in `direction`.
+  pub fn step(&self, direction: &Direction, distance: T) -> Self {
+    *self + Point2::unit(direction) * distance
+  }
+}
+
+impl<T> Point3<T> {
+  pub fn new(x: T, y: T, z: T) -> Self {
+    Point3 { x, y, z }
+  }
+}
+
+impl<T> Point3<T>
+where
+  T: Copy + PartialOrd + Add<Output = T> + Sub<Output = T>,
+{
+  /// The number of orthogonal steps between the points.
+  pub fn manhattan(&self, other: &Self) -> T {
+    abs_diff(self.x, other.x) + abs_diff(self.y, other.y) + abs_diff(self.z, other.z)
+  }
+
+  /// The number of steps between the points when diagonal steps are allowed.
+  pub fn chebyshev(&self, other: &Self) -> T {
+    let xy = max(abs_diff(self.x, other.x), abs_diff(self.y, other.y));
+    max(xy, abs_diff(self.z, other.z))
+  }
+}
+
+impl<T: Zero + Copy> Point2<T> {
+  pub fn origin() -> Self {
+    Point2::new(T::zero(), T::zero())
+  }
+}
+
+impl<T: Zero + Copy> Point3<T> {
+  pub fn origin() -> Self {
+    Point3::new(T::zero(), T::zero(), T::zero())
+  }
+}
+
+impl<T: Add<Output = T>> Add for Point2<T> {
+  type Output = Self;
+
+  fn add(self, other: Self) -> Self {
+    Point2::new(self.x + other.x, self.y + other.y)
+  }
+}
+
+impl<T: Sub<Output = T>> Sub for Point2<T> {
+  type Output = Self;
+
+  fn sub(self, other: Self) -> Self {
+    Point2::new(self.x - other.x, self.y - other.y)
+  }
+}
+
+impl<T: Neg<Output = T>> Neg for Point2<T> {
+  type Output = Self;
+
+  fn neg(self) -> Self {
+    Point2::new(-self.x, -self.y)
+  }
+}
+
+/// Scales both coordinates.
+impl<T: Copy + Mul<Output = T>> Mul<T> for Point2<T> {
+  type Output = Self;
+
+  fn mul(self, k: T) -> Self {
+    Point2::new(self.x * k, self.y * k)
+  }
+}
+
+impl<T: AddAssign> AddAssign for Point2<T> {
+  fn add_assign(&mut self, other: Self) {
+    self.x += other.x;
+    self.y += other.y;
+  }
+}
+
+impl<T: SubAssign> SubAssign for Point2<T> {
+  fn sub_assign(&mut self, other: Self) {
+    self.x -= other.x;
+    self.y -= other.y;
+  }
+}
+
+impl<T: Add<Output = T>> Add for Point3<T> {
+  type Output = Self;
+
+  fn add(self, other: Self) -> Self {
+    Point3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+  }
+}
+
+impl<T: Sub<Output = T>> Sub for Point3<T> {
+  type Output = Self;
+
+  fn sub(self, other: Self) -> Self {
+    Point3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+  }
+}
+
+impl<T: Neg<Output = T>> Neg for Point3<T> {
+  type Output = Self;
+
+  fn neg(self) -> Self {
+    Point3::new(-self.x, -self.y, -self.z)
+  }
+}
+
+/// Scales all three coordinates.
+impl<T: Copy + Mul<Output = T>> Mul<T> for Point3<T> {
+  type Output = Self;
+
+  fn mul(self, k: T) -> Self {
+    Point3::new(self.x * k, self.y * k, self.z * k)
+  }
+}
+
+impl<T: AddAssign> AddAssign for Point3<T> {
+  fn add_assign(&mut self, other: Self) {
+    self.x += other.x;
+    self.y += other.y;
+    self.z += other.z;
+  }
+}
+
+impl<T: SubAssign> SubAssign for Point3<T> {
+  fn sub_assign(&mut self, other: Self) {
+    self.x -= other.x;
+    self.y -= other.y;
+    self.z -= other.z;
+  }
+}
+
+impl<T> From<(T, T)> for Point2<T> {
+  fn from((x, y): (T, T)) -> Self {
+    Point2::new(x, y)
+  }
+}
+
+impl<T> From<Point2<T>> for (T, T) {
+  fn from(p: Point2<T>) -> Self {
+    (p.x, p.y)
+  }
+}
+
+impl<T> From<(T, T, T)> for Point3<T> {
+  fn from((x, y, z): (T, T, T)) -> Self {
+    Point3::new(x, y, z)
+  }
+}
+
+impl<T: Copy> From<[T; 3]> for Point3<T> {
+  fn from(a: [T; 3]) -> Self {
+    Point3::new(a[0], a[1], a[2])
+  }
+}
+
+impl<T> From<Point3<T>> for (T, T, T) {
+  fn from(p: Point3<T>) -> Self {
+    (p.x, p.y, p.z)
+  }
+}
+
+impl<T> From<Point3<T>> for [T; 3] {
+  fn from(p: Point3<T>) -> Self {
+    [p.x, p.y, p.z]
+  }
+}
+
+impl<T: fmt::Display> fmt::Display for Point2<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "({}, {})", self.x, self.y)
+  }
+}
+
+impl<T: fmt::Display> fmt::Display for Point3<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "({}, {}, {})", self.x, self.y, self.z)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  #[test]
+  fn test_arithmetic() {
+    let a = Point2::new(1, -2);
+    let b = Point2::from((3, 4));
+    assert_eq!(Point2::new(4, 2), a + b);
+    assert_eq!(Point2::new(-2, -6), a - b);
+    assert_eq!(Point2::new(3, -6), a * 3);
+    assert_eq!(Point2::new(-1, 2), -a);
+    let mut c = a;
+    c += b;
+    c -= Point2::new(1, 1);
+    assert_eq!((3, 1), c.into());
+
+    let p = Point3::from([1i16, 2, 3]);
+    assert_eq!(Point3::new(2, 4, 6), p + p);
+    assert_eq!(Point3::origin(), p - p);
+    assert_eq!("(1, 2, 3)", p.to_string());
+  }
+
+  #[test]
+  fn test_distance() {
+    let a = Point2::new(1usize, 5);
+    let b = Point2::new(4usize, 1);
+    assert_eq!(7, a.manhattan(&b));
+    assert_eq!(4, a.chebyshev(&b));
+    let c = Point3::new(-1, 2, 0);
+    assert_eq!(6, c.manhattan(&Point3::new(1, 0, -2)));
+    assert_eq!(3, c.chebyshev(&Point3::new(0, 0, 3)));
+  }
+
+  #[test]
+  fn test_directions() {
+    let origin: Point2<i32> = Point2::origin();
+    assert_eq!(Point2::new(0, -1), Point2::unit(&Direction::Up));
+    assert_eq!(Point2::new(1, 0), Point2::unit(&Direction::Right));
+    assert_eq!(Point2::new(0, 1), Point2::unit(&Direction::Down));
+    assert_eq!(Point2::new(-1, 0), Point2::unit(&Direction::Left));
+    assert_eq!(Point2::new(-1, -1), Point2::unit(&Direction::UpLeft));
+    assert_eq!(Point2::new(-3, 0), origin.step(&Direction::Left, 3));
+    assert_eq!(
+      Point2::new(-2, 1),
+      Point2::new(1, 2).rotate(&Direction::Right)
+    );
+    assert_eq!(
+      Point2::new(2, -1),
+      Point2::new(1, 2).rotate(&Direction::Left)
+    );
+    assert_eq!(
+      Point2::new(-1, -2),
+      Point2::new(1, 2).rotate(&Direction::Down)
+    );
+  }
+
+  #[test]
+  fn test_hash() {
+    let points: HashSet<Point2<i8>> = vec![(0, 0), (1, 0), (0, 0)]
+      .into_iter()
+      .map(Point2::from)
+      .collect();
+    assert_eq!(2, points.len());
+  }
+}
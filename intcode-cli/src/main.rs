@@ -0,0 +1,370 @@
+use intcode::{IntCode, IntCodeAscii, IntCodeResultKind};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "\
+Usage: intcode <program> [options]
+
+Runs an Intcode program with all of the given input and prints the result. Input
+values are separated by commas or whitespace; in ASCII mode each line of input
+is sent as text.
+
+Options:
+  -i, --input <values>      Add input from the argument
+      --input-file <path>   Add input from a file
+      --stdin               Add input from stdin
+  -s, --set <addr>=<value>  Set a memory cell before running
+  -a, --ascii               Send input as text and print output as text
+  -l, --limit <count>       Fail after executing this many instructions
+  -p, --print <what>        outputs (default), cell0 or state
+  -f, --format <format>     text (default) or json
+  -h, --help                Print this message
+";
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let options = match Options::parse(env::args().skip(1))? {
+        Some(options) => options,
+        None => {
+            print!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    let program = fs::read_to_string(&options.program)
+        .map_err(|e| format!("could not read {}: {}", options.program, e))?;
+    let mut cpu = IntCode::from_vec(parse_values(&program)?);
+    cpu.patch(&options.patches)?;
+    cpu.set_instruction_limit(options.limit);
+
+    let mut input = String::new();
+    for source in &options.inputs {
+        input.push_str(&source.read()?);
+        input.push('\n');
+    }
+
+    let report = if options.ascii {
+        let mut ascii = IntCodeAscii::new(cpu);
+        for line in input.lines() {
+            ascii.input_line(line);
+        }
+        let output = ascii.execute()?;
+        Report {
+            cpu: ascii.cpu().clone(),
+            kind: output.kind,
+            text: Some(output.text),
+            values: output.values,
+        }
+    } else {
+        cpu.input(&parse_values(&input)?);
+        let result = cpu.execute()?;
+        Report {
+            cpu,
+            kind: result.kind,
+            text: None,
+            values: result.output,
+        }
+    };
+    print!("{}", report.format(options.print, options.format)?);
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum InputSource {
+    Argument(String),
+    File(String),
+    Stdin,
+}
+
+impl InputSource {
+    fn read(&self) -> Result<String, Box<dyn Error>> {
+        match self {
+            InputSource::Argument(text) => Ok(text.clone()),
+            InputSource::File(path) => {
+                Ok(fs::read_to_string(path)
+                    .map_err(|e| format!("could not read {}: {}", path, e))?)
+            }
+            InputSource::Stdin => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                Ok(text)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Print {
+    Outputs,
+    Cell0,
+    State,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    program: String,
+    inputs: Vec<InputSource>,
+    patches: Vec<(isize, isize)>,
+    ascii: bool,
+    limit: Option<usize>,
+    print: Print,
+    format: Format,
+}
+
+impl Options {
+    /// Parses the command line, not including the binary name. Returns `None` if help was asked
+    /// for.
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+        let mut program = None;
+        let mut options = Options {
+            program: String::new(),
+            inputs: Vec::new(),
+            patches: Vec::new(),
+            ascii: false,
+            limit: None,
+            print: Print::Outputs,
+            format: Format::Text,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-i" | "--input" => options.inputs.push(InputSource::Argument(value()?)),
+                "--input-file" => options.inputs.push(InputSource::File(value()?)),
+                "--stdin" => options.inputs.push(InputSource::Stdin),
+                "-s" | "--set" => options.patches.push(parse_patch(&value()?)?),
+                "-a" | "--ascii" => options.ascii = true,
+                "-l" | "--limit" => {
+                    let limit = value()?;
+                    let limit = limit
+                        .parse()
+                        .map_err(|_| format!("invalid instruction limit '{}'", limit))?;
+                    options.limit = Some(limit);
+                }
+                "-p" | "--print" => {
+                    options.print = match value()?.as_str() {
+                        "outputs" => Print::Outputs,
+                        "cell0" => Print::Cell0,
+                        "state" => Print::State,
+                        other => return Err(format!("cannot print '{}'", other)),
+                    }
+                }
+                "-f" | "--format" => {
+                    options.format = match value()?.as_str() {
+                        "text" => Format::Text,
+                        "json" => Format::Json,
+                        other => return Err(format!("unknown format '{}'", other)),
+                    }
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option '{}'\n\n{}", arg, USAGE))
+                }
+                _ if program.is_none() => program = Some(arg),
+                _ => return Err(format!("unexpected argument '{}'", arg)),
+            }
+        }
+        match program {
+            Some(program) => {
+                options.program = program;
+                Ok(Some(options))
+            }
+            None => Err(format!("no program given\n\n{}", USAGE)),
+        }
+    }
+}
+
+/// Parses a memory patch of the form `addr=value`.
+fn parse_patch(patch: &str) -> Result<(isize, isize), String> {
+    let error = || format!("invalid patch '{}', expected <addr>=<value>", patch);
+    let mut parts = patch.splitn(2, '=');
+    let addr = parts.next().and_then(|a| a.trim().parse().ok());
+    let value = parts.next().and_then(|v| v.trim().parse().ok());
+    match (addr, value) {
+        (Some(addr), Some(value)) => Ok((addr, value)),
+        _ => Err(error()),
+    }
+}
+
+/// Parses integers separated by commas or whitespace.
+fn parse_values(text: &str) -> Result<Vec<isize>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().map_err(|_| format!("invalid value '{}'", v)))
+        .collect()
+}
+
+/// The machine once it has stopped, and what it output. In ASCII mode, output that is text goes
+/// in `text` and only the rest is left in `values`.
+struct Report {
+    cpu: IntCode,
+    kind: IntCodeResultKind,
+    text: Option<String>,
+    values: Vec<isize>,
+}
+
+impl Report {
+    fn format(&self, print: Print, format: Format) -> Result<String, Box<dyn Error>> {
+        let status = match self.kind {
+            IntCodeResultKind::Halt => "halted",
+            IntCodeResultKind::Yield => "waiting for input",
+        };
+        let output = match (print, format) {
+            (Print::Outputs, Format::Text) => {
+                let mut out = self.text.clone().unwrap_or_default();
+                for value in &self.values {
+                    out.push_str(&format!("{}\n", value));
+                }
+                out
+            }
+            (Print::Outputs, Format::Json) => {
+                let text = match &self.text {
+                    Some(text) => format!(",\"text\":{}", json_string(text)),
+                    None => String::new(),
+                };
+                format!(
+                    "{{\"status\":\"{}\"{},\"outputs\":{}}}\n",
+                    status,
+                    text,
+                    json_array(&self.values)
+                )
+            }
+            (Print::Cell0, Format::Text) => format!("{}\n", self.cpu.peek(0)?),
+            (Print::Cell0, Format::Json) => format!(
+                "{{\"status\":\"{}\",\"cell0\":{}}}\n",
+                status,
+                self.cpu.peek(0)?
+            ),
+            (Print::State, Format::Text) => format!(
+                "status: {}\ninstruction pointer: {}\nrelative base: {}\ninstructions: {}\n\
+                 outputs: {}\nmemory: {}\n",
+                status,
+                self.cpu.get_instruction_pointer(),
+                self.cpu.get_relative_base(),
+                self.cpu.get_instruction_count(),
+                join(self.cpu.get_output()),
+                join(self.cpu.get_memory())
+            ),
+            (Print::State, Format::Json) => format!(
+                "{{\"status\":\"{}\",\"instruction_pointer\":{},\"relative_base\":{},\
+                 \"instructions\":{},\"outputs\":{},\"memory\":{}}}\n",
+                status,
+                self.cpu.get_instruction_pointer(),
+                self.cpu.get_relative_base(),
+                self.cpu.get_instruction_count(),
+                json_array(self.cpu.get_output()),
+                json_array(self.cpu.get_memory())
+            ),
+        };
+        Ok(output)
+    }
+}
+
+fn join(values: &[isize]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    values.join(",")
+}
+
+fn json_array(values: &[isize]) -> String {
+    format!("[{}]", join(values))
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = Options::parse(args(
+            "prog.txt -i 1,2 --set 1=12 -s 2=2 --stdin -l 100 -p state -f json",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!("prog.txt", options.program);
+        assert_eq!(
+            vec![InputSource::Argument("1,2".to_string()), InputSource::Stdin],
+            options.inputs
+        );
+        assert_eq!(vec![(1, 12), (2, 2)], options.patches);
+        assert_eq!(Some(100), options.limit);
+        assert_eq!(Print::State, options.print);
+        assert_eq!(Format::Json, options.format);
+        assert_eq!(None, Options::parse(args("prog.txt --help")).unwrap());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Options::parse(args("")).is_err());
+        assert!(Options::parse(args("prog.txt --input")).is_err());
+        assert!(Options::parse(args("prog.txt --set 12")).is_err());
+        assert!(Options::parse(args("prog.txt -p everything")).is_err());
+        assert!(Options::parse(args("prog.txt other.txt")).is_err());
+        assert_eq!(
+            Err("invalid value 'x'".to_string()),
+            parse_values("1, 2\nx")
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let mut cpu = IntCode::from_vec(parse_values("3,0,4,0,99\n").unwrap());
+        cpu.input(&vec![7]);
+        let result = cpu.execute().unwrap();
+        let report = Report {
+            cpu,
+            kind: result.kind,
+            text: None,
+            values: result.output,
+        };
+        assert_eq!("7\n", report.format(Print::Outputs, Format::Text).unwrap());
+        assert_eq!("7\n", report.format(Print::Cell0, Format::Text).unwrap());
+        assert_eq!(
+            "{\"status\":\"halted\",\"outputs\":[7]}\n",
+            report.format(Print::Outputs, Format::Json).unwrap()
+        );
+        assert_eq!(
+            "{\"status\":\"halted\",\"instruction_pointer\":4,\"relative_base\":0,\
+             \"instructions\":3,\"outputs\":[7],\"memory\":[7,0,4,0,99]}\n",
+            report.format(Print::State, Format::Json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!("\"a\\\"b\\n\\u0001\"", json_string("a\"b\n\u{1}"));
+    }
+}
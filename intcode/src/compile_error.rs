@@ -0,0 +1,93 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+  pub kind: CompileErrorKind,
+  pub line: usize,
+  pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileErrorKind {
+  UnexpectedCharacter {
+    character: char,
+  },
+  InvalidNumber {
+    text: String,
+  },
+  UnexpectedToken {
+    found: String,
+    expected: String,
+  },
+  UnexpectedEnd {
+    expected: String,
+  },
+  UndefinedVariable {
+    name: String,
+  },
+  UndefinedFunction {
+    name: String,
+  },
+  DuplicateVariable {
+    name: String,
+  },
+  DuplicateFunction {
+    name: String,
+  },
+  WrongArgumentCount {
+    name: String,
+    expected: usize,
+    found: usize,
+  },
+  MissingMain,
+}
+
+impl CompileError {
+  pub(crate) fn new(kind: CompileErrorKind, (line, column): (usize, usize)) -> Self {
+    CompileError { kind, line, column }
+  }
+}
+
+impl fmt::Display for CompileErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CompileErrorKind::UnexpectedCharacter { character } => {
+        write!(f, "unexpected character '{}'", character)
+      }
+      CompileErrorKind::InvalidNumber { text } => write!(f, "invalid number {}", text),
+      CompileErrorKind::UnexpectedToken { found, expected } => {
+        write!(f, "expected {}, found {}", expected, found)
+      }
+      CompileErrorKind::UnexpectedEnd { expected } => {
+        write!(f, "expected {}, found end of input", expected)
+      }
+      CompileErrorKind::UndefinedVariable { name } => write!(f, "undefined variable {}", name),
+      CompileErrorKind::UndefinedFunction { name } => write!(f, "undefined function {}", name),
+      CompileErrorKind::DuplicateVariable { name } => {
+        write!(f, "variable {} is already defined in this scope", name)
+      }
+      CompileErrorKind::DuplicateFunction { name } => {
+        write!(f, "function {} is already defined", name)
+      }
+      CompileErrorKind::WrongArgumentCount {
+        name,
+        expected,
+        found,
+      } => write!(
+        f,
+        "function {} takes {} arguments but {} were given",
+        name, expected, found
+      ),
+      CompileErrorKind::MissingMain => write!(f, "no main function without parameters"),
+    }
+  }
+}
+
+impl fmt::Display for CompileError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+  }
+}
+
+impl Error for CompileError {}
@@ -0,0 +1,210 @@
+use super::compile_error::{CompileError, CompileErrorKind};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+  Number(isize),
+  Ident(String),
+  Fn,
+  Let,
+  If,
+  Else,
+  While,
+  Return,
+  LeftParen,
+  RightParen,
+  LeftBrace,
+  RightBrace,
+  Comma,
+  Semicolon,
+  Assign,
+  Plus,
+  Minus,
+  Star,
+  Not,
+  Less,
+  LessEqual,
+  Greater,
+  GreaterEqual,
+  Equal,
+  NotEqual,
+  And,
+  Or,
+}
+
+/// A token and the line and column it starts at.
+pub type Spanned = (Token, (usize, usize));
+
+/// Splits source code into tokens, skipping whitespace and `//` comments.
+pub fn tokenize(source: &str) -> Result<Vec<Spanned>, CompileError> {
+  let chars: Vec<char> = source.chars().collect();
+  let mut tokens = Vec::new();
+  let (mut i, mut line, mut column) = (0, 1, 1);
+  while i < chars.len() {
+    let c = chars[i];
+    let pos = (line, column);
+    let next = chars.get(i + 1).cloned();
+    if c == '\n' {
+      i += 1;
+      line += 1;
+      column = 1;
+      continue;
+    }
+    if c.is_whitespace() {
+      i += 1;
+      column += 1;
+      continue;
+    }
+    if c == '/' && next == Some('/') {
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+      continue;
+    }
+
+    let start = i;
+    let token = if c.is_ascii_digit() {
+      while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+        i += 1;
+      }
+      let text: String = chars[start..i].iter().collect();
+      match text.parse::<isize>() {
+        Ok(n) => Token::Number(n),
+        Err(_) => {
+          return Err(CompileError::new(
+            CompileErrorKind::InvalidNumber { text },
+            pos,
+          ))
+        }
+      }
+    } else if c.is_ascii_alphabetic() || c == '_' {
+      while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+        i += 1;
+      }
+      let text: String = chars[start..i].iter().collect();
+      match text.as_str() {
+        "fn" => Token::Fn,
+        "let" => Token::Let,
+        "if" => Token::If,
+        "else" => Token::Else,
+        "while" => Token::While,
+        "return" => Token::Return,
+        _ => Token::Ident(text),
+      }
+    } else {
+      let (token, len) = match (c, next) {
+        ('<', Some('=')) => (Token::LessEqual, 2),
+        ('>', Some('=')) => (Token::GreaterEqual, 2),
+        ('=', Some('=')) => (Token::Equal, 2),
+        ('!', Some('=')) => (Token::NotEqual, 2),
+        ('&', Some('&')) => (Token::And, 2),
+        ('|', Some('|')) => (Token::Or, 2),
+        ('(', _) => (Token::LeftParen, 1),
+        (')', _) => (Token::RightParen, 1),
+        ('{', _) => (Token::LeftBrace, 1),
+        ('}', _) => (Token::RightBrace, 1),
+        (',', _) => (Token::Comma, 1),
+        (';', _) => (Token::Semicolon, 1),
+        ('=', _) => (Token::Assign, 1),
+        ('+', _) => (Token::Plus, 1),
+        ('-', _) => (Token::Minus, 1),
+        ('*', _) => (Token::Star, 1),
+        ('!', _) => (Token::Not, 1),
+        ('<', _) => (Token::Less, 1),
+        ('>', _) => (Token::Greater, 1),
+        _ => {
+          let kind = CompileErrorKind::UnexpectedCharacter { character: c };
+          return Err(CompileError::new(kind, pos));
+        }
+      };
+      i += len;
+      token
+    };
+    column += i - start;
+    tokens.push((token, pos));
+  }
+  Ok(tokens)
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let text = match self {
+      Token::Number(n) => return write!(f, "{}", n),
+      Token::Ident(name) => return write!(f, "{}", name),
+      Token::Fn => "fn",
+      Token::Let => "let",
+      Token::If => "if",
+      Token::Else => "else",
+      Token::While => "while",
+      Token::Return => "return",
+      Token::LeftParen => "(",
+      Token::RightParen => ")",
+      Token::LeftBrace => "{",
+      Token::RightBrace => "}",
+      Token::Comma => ",",
+      Token::Semicolon => ";",
+      Token::Assign => "=",
+      Token::Plus => "+",
+      Token::Minus => "-",
+      Token::Star => "*",
+      Token::Not => "!",
+      Token::Less => "<",
+      Token::LessEqual => "<=",
+      Token::Greater => ">",
+      Token::GreaterEqual => ">=",
+      Token::Equal => "==",
+      Token::NotEqual => "!=",
+      Token::And => "&&",
+      Token::Or => "||",
+    };
+    write!(f, "'{}'", text)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_tokenize() {
+    let tokens = tokenize("let x = 10; // ten\nif (x <= y_1) {}").unwrap();
+    assert_eq!(
+      vec![
+        (Token::Let, (1, 1)),
+        (Token::Ident("x".to_string()), (1, 5)),
+        (Token::Assign, (1, 7)),
+        (Token::Number(10), (1, 9)),
+        (Token::Semicolon, (1, 11)),
+        (Token::If, (2, 1)),
+        (Token::LeftParen, (2, 4)),
+        (Token::Ident("x".to_string()), (2, 5)),
+        (Token::LessEqual, (2, 7)),
+        (Token::Ident("y_1".to_string()), (2, 10)),
+        (Token::RightParen, (2, 13)),
+        (Token::LeftBrace, (2, 15)),
+        (Token::RightBrace, (2, 16)),
+      ],
+      tokens
+    );
+  }
+
+  #[test]
+  fn test_errors() {
+    assert_eq!(
+      Err(CompileError::new(
+        CompileErrorKind::UnexpectedCharacter { character: '/' },
+        (2, 3)
+      )),
+      tokenize("1\n2 / 3")
+    );
+    assert_eq!(
+      Err(CompileError::new(
+        CompileErrorKind::InvalidNumber {
+          text: "12ab".to_string()
+        },
+        (1, 1)
+      )),
+      tokenize("12ab")
+    );
+  }
+}
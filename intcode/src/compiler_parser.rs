@@ -0,0 +1,431 @@
+use super::compile_error::{CompileError, CompileErrorKind};
+use super::compiler_lexer::{Spanned, Token};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+  pub name: String,
+  pub params: Vec<String>,
+  pub body: Vec<Stmt>,
+  pub pos: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+  Let {
+    name: String,
+    value: Expr,
+    pos: (usize, usize),
+  },
+  Assign {
+    name: String,
+    value: Expr,
+    pos: (usize, usize),
+  },
+  If {
+    cond: Expr,
+    then: Vec<Stmt>,
+    otherwise: Vec<Stmt>,
+  },
+  While {
+    cond: Expr,
+    body: Vec<Stmt>,
+  },
+  Return {
+    value: Option<Expr>,
+  },
+  Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+  Number(isize),
+  Var {
+    name: String,
+    pos: (usize, usize),
+  },
+  Call {
+    name: String,
+    args: Vec<Expr>,
+    pos: (usize, usize),
+  },
+  Unary {
+    op: UnaryOp,
+    expr: Box<Expr>,
+  },
+  Binary {
+    op: BinaryOp,
+    left: Box<Expr>,
+    right: Box<Expr>,
+  },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+  Negate,
+  Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+  Add,
+  Sub,
+  Mul,
+  Less,
+  LessEqual,
+  Greater,
+  GreaterEqual,
+  Equal,
+  NotEqual,
+  And,
+  Or,
+}
+
+/// Binary operators from the loosest binding to the tightest.
+const PRECEDENCE: [&[(Token, BinaryOp)]; 5] = [
+  &[(Token::Or, BinaryOp::Or)],
+  &[(Token::And, BinaryOp::And)],
+  &[
+    (Token::Equal, BinaryOp::Equal),
+    (Token::NotEqual, BinaryOp::NotEqual),
+  ],
+  &[
+    (Token::Less, BinaryOp::Less),
+    (Token::LessEqual, BinaryOp::LessEqual),
+    (Token::Greater, BinaryOp::Greater),
+    (Token::GreaterEqual, BinaryOp::GreaterEqual),
+  ],
+  &[(Token::Plus, BinaryOp::Add), (Token::Minus, BinaryOp::Sub)],
+];
+
+/// A recursive descent parser over the output of `tokenize`.
+pub struct Parser {
+  tokens: Vec<Spanned>,
+  index: usize,
+}
+
+impl Parser {
+  pub fn new(tokens: Vec<Spanned>) -> Self {
+    Parser { tokens, index: 0 }
+  }
+
+  /// Parses a whole program: a list of functions.
+  pub fn parse(mut self) -> Result<Vec<Function>, CompileError> {
+    let mut functions = Vec::new();
+    while self.index < self.tokens.len() {
+      functions.push(self.function()?);
+    }
+    Ok(functions)
+  }
+
+  fn function(&mut self) -> Result<Function, CompileError> {
+    let pos = self.expect(Token::Fn, "'fn'")?;
+    let name = self.ident()?;
+    self.expect(Token::LeftParen, "'('")?;
+    let mut params = Vec::new();
+    if !self.accept(&Token::RightParen) {
+      loop {
+        params.push(self.ident()?);
+        if self.accept(&Token::RightParen) {
+          break;
+        }
+        self.expect(Token::Comma, "',' or ')'")?;
+      }
+    }
+    let body = self.block()?;
+    Ok(Function {
+      name,
+      params,
+      body,
+      pos,
+    })
+  }
+
+  fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+    self.expect(Token::LeftBrace, "'{'")?;
+    let mut stmts = Vec::new();
+    while !self.accept(&Token::RightBrace) {
+      stmts.push(self.stmt()?);
+    }
+    Ok(stmts)
+  }
+
+  fn stmt(&mut self) -> Result<Stmt, CompileError> {
+    let stmt = match self.peek() {
+      Some(Token::Let) => {
+        self.index += 1;
+        let pos = self.pos();
+        let name = self.ident()?;
+        self.expect(Token::Assign, "'='")?;
+        let value = self.expr()?;
+        Stmt::Let { name, value, pos }
+      }
+      Some(Token::If) => {
+        self.index += 1;
+        let cond = self.expr()?;
+        let then = self.block()?;
+        let otherwise = match self.accept(&Token::Else) {
+          true if self.peek() == Some(&Token::If) => vec![self.stmt()?],
+          true => self.block()?,
+          false => Vec::new(),
+        };
+        return Ok(Stmt::If {
+          cond,
+          then,
+          otherwise,
+        });
+      }
+      Some(Token::While) => {
+        self.index += 1;
+        let cond = self.expr()?;
+        let body = self.block()?;
+        return Ok(Stmt::While { cond, body });
+      }
+      Some(Token::Return) => {
+        self.index += 1;
+        let value = match self.peek() {
+          Some(Token::Semicolon) => None,
+          _ => Some(self.expr()?),
+        };
+        Stmt::Return { value }
+      }
+      Some(Token::Ident(name)) if self.peek_at(1) == Some(&Token::Assign) => {
+        let name = name.clone();
+        let pos = self.pos();
+        self.index += 2;
+        let value = self.expr()?;
+        Stmt::Assign { name, value, pos }
+      }
+      _ => Stmt::Expr(self.expr()?),
+    };
+    self.expect(Token::Semicolon, "';'")?;
+    Ok(stmt)
+  }
+
+  fn expr(&mut self) -> Result<Expr, CompileError> {
+    self.binary(0)
+  }
+
+  fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+    if level == PRECEDENCE.len() {
+      return self.product();
+    }
+    let mut left = self.binary(level + 1)?;
+    'outer: loop {
+      for (token, op) in PRECEDENCE[level] {
+        if self.accept(token) {
+          let right = self.binary(level + 1)?;
+          left = Expr::Binary {
+            op: *op,
+            left: Box::new(left),
+            right: Box::new(right),
+          };
+          continue 'outer;
+        }
+      }
+      return Ok(left);
+    }
+  }
+
+  fn product(&mut self) -> Result<Expr, CompileError> {
+    let mut left = self.unary()?;
+    while self.accept(&Token::Star) {
+      let right = self.unary()?;
+      left = Expr::Binary {
+        op: BinaryOp::Mul,
+        left: Box::new(left),
+        right: Box::new(right),
+      };
+    }
+    Ok(left)
+  }
+
+  fn unary(&mut self) -> Result<Expr, CompileError> {
+    let op = match self.peek() {
+      Some(Token::Minus) => UnaryOp::Negate,
+      Some(Token::Not) => UnaryOp::Not,
+      _ => return self.primary(),
+    };
+    self.index += 1;
+    let expr = self.unary()?;
+    Ok(Expr::Unary {
+      op,
+      expr: Box::new(expr),
+    })
+  }
+
+  fn primary(&mut self) -> Result<Expr, CompileError> {
+    let pos = self.pos();
+    match self.next("an expression")? {
+      Token::Number(n) => Ok(Expr::Number(n)),
+      Token::LeftParen => {
+        let expr = self.expr()?;
+        self.expect(Token::RightParen, "')'")?;
+        Ok(expr)
+      }
+      Token::Ident(name) if self.accept(&Token::LeftParen) => {
+        let mut args = Vec::new();
+        if !self.accept(&Token::RightParen) {
+          loop {
+            args.push(self.expr()?);
+            if self.accept(&Token::RightParen) {
+              break;
+            }
+            self.expect(Token::Comma, "',' or ')'")?;
+          }
+        }
+        Ok(Expr::Call { name, args, pos })
+      }
+      Token::Ident(name) => Ok(Expr::Var { name, pos }),
+      token => Err(Parser::unexpected(&token, "an expression", pos)),
+    }
+  }
+
+  fn ident(&mut self) -> Result<String, CompileError> {
+    let pos = self.pos();
+    match self.next("a name")? {
+      Token::Ident(name) => Ok(name),
+      token => Err(Parser::unexpected(&token, "a name", pos)),
+    }
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.peek_at(0)
+  }
+
+  fn peek_at(&self, offset: usize) -> Option<&Token> {
+    self.tokens.get(self.index + offset).map(|(t, _)| t)
+  }
+
+  /// The position of the next token, or of the last one at the end of the input.
+  fn pos(&self) -> (usize, usize) {
+    match self.tokens.get(self.index).or_else(|| self.tokens.last()) {
+      Some((_, pos)) => *pos,
+      None => (1, 1),
+    }
+  }
+
+  fn next(&mut self, expected: &str) -> Result<Token, CompileError> {
+    match self.tokens.get(self.index) {
+      Some((token, _)) => {
+        self.index += 1;
+        Ok(token.clone())
+      }
+      None => {
+        let kind = CompileErrorKind::UnexpectedEnd {
+          expected: expected.to_string(),
+        };
+        Err(CompileError::new(kind, self.pos()))
+      }
+    }
+  }
+
+  /// Consumes the next token if it is `token`.
+  fn accept(&mut self, token: &Token) -> bool {
+    match self.peek() == Some(token) {
+      true => {
+        self.index += 1;
+        true
+      }
+      false => false,
+    }
+  }
+
+  /// Consumes the next token, which must be `token`, returning its position.
+  fn expect(&mut self, token: Token, expected: &str) -> Result<(usize, usize), CompileError> {
+    let pos = self.pos();
+    match self.next(expected)? {
+      t if t == token => Ok(pos),
+      t => Err(Parser::unexpected(&t, expected, pos)),
+    }
+  }
+
+  fn unexpected(token: &Token, expected: &str, pos: (usize, usize)) -> CompileError {
+    let kind = CompileErrorKind::UnexpectedToken {
+      found: token.to_string(),
+      expected: expected.to_string(),
+    };
+    CompileError::new(kind, pos)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::compiler_lexer::tokenize;
+
+  fn parse(source: &str) -> Result<Vec<Function>, CompileError> {
+    Parser::new(tokenize(source)?).parse()
+  }
+
+  fn num(n: isize) -> Box<Expr> {
+    Box::new(Expr::Number(n))
+  }
+
+  #[test]
+  fn test_precedence() {
+    let functions = parse("fn main() { 1 + 2 * 3 < 4 == 1 || -5; }").unwrap();
+    let sum = Expr::Binary {
+      op: BinaryOp::Add,
+      left: num(1),
+      right: Box::new(Expr::Binary {
+        op: BinaryOp::Mul,
+        left: num(2),
+        right: num(3),
+      }),
+    };
+    let less = Expr::Binary {
+      op: BinaryOp::Less,
+      left: Box::new(sum),
+      right: num(4),
+    };
+    let equal = Expr::Binary {
+      op: BinaryOp::Equal,
+      left: Box::new(less),
+      right: num(1),
+    };
+    let expected = Expr::Binary {
+      op: BinaryOp::Or,
+      left: Box::new(equal),
+      right: Box::new(Expr::Unary {
+        op: UnaryOp::Negate,
+        expr: num(5),
+      }),
+    };
+    assert_eq!(vec![Stmt::Expr(expected)], functions[0].body);
+  }
+
+  #[test]
+  fn test_statements() {
+    let functions =
+      parse("fn f(a, b) { let x = a; x = b; if x { return; } else if 1 {} }").unwrap();
+    assert_eq!(vec!["a".to_string(), "b".to_string()], functions[0].params);
+    let body = &functions[0].body;
+    assert_eq!(3, body.len());
+    assert!(matches!(&body[1], Stmt::Assign { name, .. } if name == "x"));
+    match &body[2] {
+      Stmt::If {
+        then, otherwise, ..
+      } => {
+        assert_eq!(vec![Stmt::Return { value: None }], *then);
+        assert!(matches!(otherwise[0], Stmt::If { .. }));
+      }
+      _ => panic!("expected if"),
+    }
+  }
+
+  #[test]
+  fn test_errors() {
+    assert_eq!(
+      "1:15: expected ';', found '}'",
+      parse("fn main() { 1 }").unwrap_err().to_string()
+    );
+    assert_eq!(
+      "1:9: expected '{', found end of input",
+      parse("fn main() ").unwrap_err().to_string()
+    );
+    assert_eq!(
+      "1:4: expected a name, found 1",
+      parse("fn 1() {}").unwrap_err().to_string()
+    );
+  }
+}
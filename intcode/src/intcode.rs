@@ -1,8 +1,18 @@
-use super::intcode_error::{IntCodeError, IntCodeErrorKind};
+use super::intcode_device::{IntCodeDevice, MappedDevice};
+use super::intcode_error::{IntCodeError, IntCodeErrorContext, IntCodeErrorKind};
+use super::intcode_recording::{IntCodeRecording, RecordedEvent};
 use super::intcode_result::{IntCodeResult, IntCodeResultKind};
 use super::operation::{Operation, OperationMode};
 use super::operation_result::OperationResult;
-use aoc_util::ToDigits;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+/// The number of executed instructions remembered for error reports.
+const TRACE_LEN: usize = 8;
+/// The number of memory cells before and after the failing instruction included in error reports.
+const CONTEXT_BEFORE: usize = 8;
+const CONTEXT_AFTER: usize = 16;
 
 #[derive(Debug, Clone)]
 pub struct IntCode {
@@ -11,7 +21,14 @@ pub struct IntCode {
   input: Vec<isize>,
   input_index: usize,
   output: Vec<isize>,
+  output_index: usize,
   relative_base: isize,
+  instruction_count: usize,
+  trace: VecDeque<usize>,
+  devices: Vec<MappedDevice>,
+  /// The instruction count when recording started, and the recording so far.
+  recording: Option<(usize, IntCodeRecording)>,
+  instruction_limit: Option<usize>,
 }
 
 impl IntCode {
@@ -22,7 +39,13 @@ impl IntCode {
       input: Vec::new(),
       input_index: 0,
       output: Vec::new(),
+      output_index: 0,
       relative_base: 0,
+      instruction_count: 0,
+      trace: VecDeque::with_capacity(TRACE_LEN),
+      devices: Vec::new(),
+      recording: None,
+      instruction_limit: None,
     }
   }
 
@@ -31,27 +54,135 @@ impl IntCode {
       .split(",")
       .map(|x| x.parse::<isize>().unwrap())
       .collect();
-    IntCode {
-      data,
-      index: 0,
-      input: Vec::new(),
-      input_index: 0,
-      output: Vec::new(),
-      relative_base: 0,
-    }
+    IntCode::from_vec(data)
   }
 
   pub fn input(&mut self, inputs: &Vec<isize>) {
     self.input.extend(inputs.iter());
   }
 
+  /// Every value output since the machine was created. Each `IntCodeResult` only holds the values
+  /// output during its own `execute` call.
   pub fn get_output(&self) -> &Vec<isize> {
     &self.output
   }
 
+  pub fn get_memory(&self) -> &[isize] {
+    &self.data
+  }
+
+  /// The total number of instructions executed since the machine was created.
+  pub fn get_instruction_count(&self) -> usize {
+    self.instruction_count
+  }
+
+  pub fn get_instruction_pointer(&self) -> usize {
+    self.index
+  }
+
+  pub fn get_relative_base(&self) -> isize {
+    self.relative_base
+  }
+
+  /// The number of allocated memory cells. Reads past the end return 0.
+  pub fn memory_len(&self) -> usize {
+    self.data.len()
+  }
+
+  pub fn peek(&self, addr: isize) -> Result<isize, IntCodeError> {
+    if addr < 0 {
+      return Err(self.get_error(IntCodeErrorKind::PeekOutOfRange { index: addr }));
+    }
+    Ok(*self.data.get(addr as usize).unwrap_or(&0))
+  }
+
+  pub fn peek_range(&self, range: Range<isize>) -> Result<Vec<isize>, IntCodeError> {
+    range.map(|addr| self.peek(addr)).collect()
+  }
+
+  /// Writes `value` to `addr`, growing memory if needed.
+  pub fn poke(&mut self, addr: isize, value: isize) -> Result<(), IntCodeError> {
+    if addr < 0 {
+      return Err(self.get_error(IntCodeErrorKind::PokeOutOfRange { index: addr }));
+    }
+    self.store(addr as usize, value);
+    Ok(())
+  }
+
+  /// Writes each `(addr, value)` pair to memory. If any address is invalid, memory is left
+  /// unchanged.
+  pub fn patch(&mut self, patches: &[(isize, isize)]) -> Result<(), IntCodeError> {
+    if let Some((addr, _)) = patches.iter().find(|(addr, _)| *addr < 0) {
+      return Err(self.get_error(IntCodeErrorKind::PokeOutOfRange { index: *addr }));
+    }
+    for (addr, value) in patches {
+      self.store(*addr as usize, *value);
+    }
+    Ok(())
+  }
+
+  /// Maps `device` to the addresses in `range`, returning a handle the host can use to inspect it.
+  /// Only reads and writes made by instructions go to the device; `peek`, `poke`, `patch` and
+  /// `get_memory` still see the memory underneath. Clones of the machine get their own copy of
+  /// each device, which the handle doesn't see. Device reads are recorded like inputs.
+  pub fn map_device<D: IntCodeDevice + 'static>(
+    &mut self,
+    range: Range<usize>,
+    device: D,
+  ) -> Result<Arc<Mutex<D>>, IntCodeError> {
+    if let Some(existing) = self
+      .devices
+      .iter()
+      .find(|d| d.range.start < range.end && range.start < d.range.end)
+    {
+      return Err(self.get_error(IntCodeErrorKind::DeviceOverlap {
+        start: existing.range.start,
+        end: existing.range.end,
+      }));
+    }
+    let device = Arc::new(Mutex::new(device));
+    self.devices.push(MappedDevice {
+      range,
+      device: device.clone(),
+    });
+    Ok(device)
+  }
+
+  /// Starts recording every input consumed and output produced, replacing any recording in
+  /// progress. The recording remembers the current memory, so start it before the first
+  /// `execute` to be able to replay from a freshly loaded program.
+  pub fn start_recording(&mut self) {
+    let recording = IntCodeRecording::new(&self.data);
+    self.recording = Some((self.instruction_count, recording));
+  }
+
+  pub fn recording(&self) -> Option<&IntCodeRecording> {
+    self.recording.as_ref().map(|(_, recording)| recording)
+  }
+
+  pub fn stop_recording(&mut self) -> Option<IntCodeRecording> {
+    self.recording.take().map(|(_, recording)| recording)
+  }
+
+  /// Makes `execute` fail with `InstructionLimit` instead of running instruction number
+  /// `limit + 1`, counting every instruction since the machine was created. Guards against
+  /// programs that never halt.
+  pub fn set_instruction_limit(&mut self, limit: Option<usize>) {
+    self.instruction_limit = limit;
+  }
+
   pub fn execute(&mut self) -> Result<IntCodeResult, IntCodeError> {
+    let mut instructions = 0;
     loop {
-      let result = match self.operation() {
+      if let Some(limit) = self.instruction_limit {
+        if self.instruction_count >= limit {
+          return Err(self.get_error(IntCodeErrorKind::InstructionLimit { limit }));
+        }
+      }
+      let index = self.index;
+      let operation = self.operation()?;
+      let length = operation.length();
+      let result = match operation {
         Operation::Add(i) => self.exec_add(&i),
         Operation::Multiply(i) => self.exec_multiply(&i),
         Operation::Input(i) => self.exec_input(&i),
@@ -65,63 +196,70 @@ impl IntCode {
       }?;
       match result {
         OperationResult::Continue { advance } => {
+          instructions += 1;
+          self.instruction_count += 1;
+          self.record(index);
           if advance == true {
-            self.advance();
+            self.advance(length);
           }
         }
         OperationResult::Halt => {
-          return Ok(self.get_result(IntCodeResultKind::Halt));
+          instructions += 1;
+          self.instruction_count += 1;
+          self.record(index);
+          return Ok(self.get_result(IntCodeResultKind::Halt, instructions));
         }
         OperationResult::Yield => {
-          return Ok(self.get_result(IntCodeResultKind::Yield));
+          return Ok(self.get_result(IntCodeResultKind::Yield, instructions));
         }
       }
     }
   }
 
-  fn operation(&self) -> Operation {
-    let opcode = self.data[self.index].digits();
-    let op = opcode[0] + (opcode.get(1).cloned().unwrap_or(0) * 10);
-    let mut params = [0; 3]; // Increase array len if more params needed
-    for i in 0..params.len() {
-      params[i] = opcode.get(i + 2).cloned().unwrap_or(0);
+  fn operation(&self) -> Result<Operation, IntCodeError> {
+    let opcode = self.opcode();
+    Operation::decode(opcode).ok_or_else(|| {
+      // A known instruction with a bad parameter mode, or no instruction at all.
+      let kind = match opcode > 0 && Operation::decode(opcode % 100).is_some() {
+        true => IntCodeErrorKind::InvalidMode { opcode },
+        false => IntCodeErrorKind::InvalidOpcode { opcode },
+      };
+      self.get_error(kind)
+    })
+  }
+
+  /// The opcode at the instruction pointer, which is 0 past the end of memory.
+  fn opcode(&self) -> isize {
+    self.data.get(self.index).copied().unwrap_or(0)
+  }
+
+  fn jump(&mut self, target: isize) -> Result<OperationResult, IntCodeError> {
+    if target < 0 {
+      return Err(self.get_error(IntCodeErrorKind::JumpOutOfRange { index: target }));
     }
+    self.index = target as usize;
+    Ok(OperationResult::Continue { advance: false })
+  }
 
-    let el0 = OperationMode::from(params[0]);
-    let el1 = OperationMode::from(params[1]);
-    let el2 = OperationMode::from(params[2]);
-    match op {
-      1 => Operation::Add([el0, el1, el2]),
-      2 => Operation::Multiply([el0, el1, el2]),
-      3 => Operation::Input([el0]),
-      4 => Operation::Output([el0]),
-      5 => Operation::JumpIfTrue([el0, el1]),
-      6 => Operation::JumpIfFalse([el0, el1]),
-      7 => Operation::LessThan([el0, el1, el2]),
-      8 => Operation::Equals([el0, el1, el2]),
-      9 => Operation::RelBaseOffset([el0]),
-      99 => Operation::Halt,
-      _ => panic!("No operation for code: {}", op),
+  fn record_event<F: FnOnce(usize) -> RecordedEvent>(&mut self, event: F) {
+    if let Some((start, recording)) = &mut self.recording {
+      let instruction = self.instruction_count - *start;
+      recording.events.push(event(instruction));
     }
   }
 
-  fn operation_length(&self) -> usize {
-    match self.operation() {
-      Operation::Add(_)
-      | Operation::Multiply(_)
-      | Operation::LessThan(_)
-      | Operation::Equals(_) => 4,
-      Operation::JumpIfTrue(_) | Operation::JumpIfFalse(_) => 3,
-      Operation::Input(_) | Operation::Output(_) | Operation::RelBaseOffset(_) => 2,
-      Operation::Halt => 1,
+  fn record(&mut self, index: usize) {
+    if self.trace.len() == TRACE_LEN {
+      self.trace.pop_front();
     }
+    self.trace.push_back(index);
   }
 
-  fn advance(&mut self) {
-    self.index += self.operation_length();
+  fn advance(&mut self, length: usize) {
+    self.index += length;
   }
 
-  fn read(&self, index: usize, mode: &OperationMode) -> Result<isize, IntCodeError> {
+  fn read(&mut self, index: usize, mode: &OperationMode) -> Result<isize, IntCodeError> {
     match mode {
       OperationMode::Position => {
         let pos = *self.data.get(index).unwrap_or(&0);
@@ -131,7 +269,7 @@ impl IntCode {
             mode: mode.clone(),
           }));
         }
-        Ok(*self.data.get(pos as usize).unwrap_or(&0))
+        Ok(self.load(pos as usize))
       }
       OperationMode::Immediate => Ok(*self.data.get(index).unwrap_or(&0)),
       OperationMode::Relative => {
@@ -143,7 +281,7 @@ impl IntCode {
             mode: mode.clone(),
           }));
         }
-        Ok(*self.data.get(rel as usize).unwrap_or(&0))
+        Ok(self.load(rel as usize))
       }
     }
   }
@@ -180,15 +318,37 @@ impl IntCode {
         Ok(out as usize)
       }
     }?;
-    if out > self.data.len() - 1 {
-      self.data.resize(out as usize + 1, 0);
+    match self.device(out) {
+      Some(device) => device.write(out, value),
+      None => self.store(out, value),
     }
-    self.data[out as usize] = value;
     Ok(())
   }
 
+  fn device(&self, index: usize) -> Option<&MappedDevice> {
+    self.devices.iter().find(|d| d.range.contains(&index))
+  }
+
+  fn load(&mut self, index: usize) -> isize {
+    match self.device(index) {
+      Some(device) => {
+        let value = device.read(index);
+        self.record_event(|instruction| RecordedEvent::DeviceRead { instruction, value });
+        value
+      }
+      None => *self.data.get(index).unwrap_or(&0),
+    }
+  }
+
+  fn store(&mut self, index: usize, value: isize) {
+    if index >= self.data.len() {
+      self.data.resize(index + 1, 0);
+    }
+    self.data[index] = value;
+  }
+
   fn read_input(&mut self) -> Result<isize, ()> {
-    if self.input_index > self.input.len() - 1 {
+    if self.input_index >= self.input.len() {
       return Err(());
     }
     let result = Ok(self.input[self.input_index]);
@@ -213,6 +373,10 @@ impl IntCode {
     match input {
       Ok(i) => {
         self.write(self.index + 1, &modes[0], i)?;
+        self.record_event(|instruction| RecordedEvent::Input {
+          instruction,
+          value: i,
+        });
         Ok(Default::default())
       }
       Err(_) => Ok(OperationResult::Yield),
@@ -222,6 +386,10 @@ impl IntCode {
   fn exec_output(&mut self, modes: &[OperationMode; 1]) -> Result<OperationResult, IntCodeError> {
     let result = self.read(self.index + 1, &modes[0])?;
     self.output.push(result);
+    self.record_event(|instruction| RecordedEvent::Output {
+      instruction,
+      value: result,
+    });
     Ok(Default::default())
   }
 
@@ -231,9 +399,8 @@ impl IntCode {
   ) -> Result<OperationResult, IntCodeError> {
     let val = self.read(self.index + 1, &modes[0])?;
     if val != 0 {
-      let next_index = self.read(self.index + 2, &modes[1])?;
-      self.index = next_index as usize;
-      return Ok(OperationResult::Continue { advance: false });
+      let target = self.read(self.index + 2, &modes[1])?;
+      return self.jump(target);
     }
     Ok(Default::default())
   }
@@ -244,9 +411,8 @@ impl IntCode {
   ) -> Result<OperationResult, IntCodeError> {
     let val = self.read(self.index + 1, &modes[0])?;
     if val == 0 {
-      let next_index = self.read(self.index + 2, &modes[1])?;
-      self.index = next_index as usize;
-      return Ok(OperationResult::Continue { advance: false });
+      let target = self.read(self.index + 2, &modes[1])?;
+      return self.jump(target);
     }
     Ok(Default::default())
   }
@@ -289,19 +455,530 @@ impl IntCode {
     Ok(OperationResult::Halt)
   }
 
-  fn get_result(&self, kind: IntCodeResultKind) -> IntCodeResult {
+  fn get_result(&mut self, kind: IntCodeResultKind, instructions: usize) -> IntCodeResult {
+    let output = self.output[self.output_index..].to_vec();
+    self.output_index = self.output.len();
     IntCodeResult {
       kind,
-      first: &self.data[0],
-      output: &self.output,
+      output,
+      instructions,
     }
   }
 
   fn get_error(&self, kind: IntCodeErrorKind) -> IntCodeError {
+    // The instruction pointer may be past the end of memory after a jump.
+    let end = (self.index + CONTEXT_AFTER).min(self.data.len());
+    let start = self.index.saturating_sub(CONTEXT_BEFORE).min(end);
+    let trace = self
+      .trace
+      .iter()
+      .map(|index| {
+        let opcode = self.data.get(*index).copied().unwrap_or(0);
+        let length = Operation::decode(opcode).map_or(1, |o| o.length());
+        let end = (index + length).min(self.data.len());
+        (*index, self.data.get(*index..end).unwrap_or(&[]).to_vec())
+      })
+      .collect();
     IntCodeError {
       kind,
       index: self.index,
-      opcode: self.data[self.index],
+      opcode: self.opcode(),
+      context: IntCodeErrorContext {
+        relative_base: self.relative_base,
+        memory_start: start,
+        memory: self.data[start..end].to_vec(),
+        trace,
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LARGE_COMPARE: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+  fn run(program: &str, input: Vec<isize>) -> IntCode {
+    let mut cpu = IntCode::from_string(program);
+    cpu.input(&input);
+    let result = cpu.execute().unwrap();
+    assert_eq!(IntCodeResultKind::Halt, result.kind);
+    cpu
+  }
+
+  fn assert_output(program: &str, input: Vec<isize>, output: Vec<isize>) {
+    assert_eq!(output, *run(program, input).get_output());
+  }
+
+  fn assert_memory(program: &str, memory: Vec<isize>) {
+    assert_eq!(memory, run(program, vec![]).get_memory());
+  }
+
+  fn error_at(kind: IntCodeErrorKind, opcode: isize, index: usize) -> IntCodeError {
+    IntCodeError {
+      kind,
+      opcode,
+      index,
+      context: IntCodeErrorContext::default(),
+    }
+  }
+
+  fn assert_error(program: Vec<isize>, error: IntCodeError) {
+    let mut cpu = IntCode::from_vec(program);
+    assert_eq!(Err(error), cpu.execute());
+  }
+
+  #[test]
+  fn test_day_02_examples() {
+    assert_memory(
+      "1,9,10,3,2,3,11,0,99,30,40,50",
+      vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50],
+    );
+    assert_memory("1,0,0,0,99", vec![2, 0, 0, 0, 99]);
+    assert_memory("2,3,0,3,99", vec![2, 3, 0, 6, 99]);
+    assert_memory("2,4,4,5,99,0", vec![2, 4, 4, 5, 99, 9801]);
+    assert_memory("1,1,1,4,99,5,6,0,99", vec![30, 1, 1, 4, 2, 5, 6, 0, 99]);
+  }
+
+  #[test]
+  fn test_result() {
+    let mut cpu = IntCode::from_string("1,9,10,3,2,3,11,0,99,30,40,50");
+    assert_eq!(
+      Ok(IntCodeResult {
+        kind: IntCodeResultKind::Halt,
+        output: vec![],
+        instructions: 3,
+      }),
+      cpu.execute()
+    );
+    assert_eq!(3500, cpu.get_memory()[0]);
+    assert_eq!(3, cpu.get_instruction_count());
+  }
+
+  #[test]
+  fn test_day_05_io() {
+    assert_output("3,0,4,0,99", vec![42], vec![42]);
+    assert_output("3,0,4,0,99", vec![-7], vec![-7]);
+  }
+
+  #[test]
+  fn test_day_05_parameter_modes() {
+    assert_memory("1002,4,3,4,33", vec![1002, 4, 3, 4, 99]);
+    assert_memory("1101,100,-1,4,0", vec![1101, 100, -1, 4, 99]);
+  }
+
+  #[test]
+  fn test_day_05_equals_8() {
+    for program in &["3,9,8,9,10,9,4,9,99,-1,8", "3,3,1108,-1,8,3,4,3,99"] {
+      assert_output(program, vec![8], vec![1]);
+      assert_output(program, vec![7], vec![0]);
+      assert_output(program, vec![9], vec![0]);
     }
   }
+
+  #[test]
+  fn test_day_05_less_than_8() {
+    for program in &["3,9,7,9,10,9,4,9,99,-1,8", "3,3,1107,-1,8,3,4,3,99"] {
+      assert_output(program, vec![7], vec![1]);
+      assert_output(program, vec![-100], vec![1]);
+      assert_output(program, vec![8], vec![0]);
+      assert_output(program, vec![9], vec![0]);
+    }
+  }
+
+  #[test]
+  fn test_day_05_jumps() {
+    for program in &[
+      "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+      "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+    ] {
+      assert_output(program, vec![0], vec![0]);
+      assert_output(program, vec![1], vec![1]);
+      assert_output(program, vec![-3], vec![1]);
+    }
+  }
+
+  #[test]
+  fn test_day_05_compare_to_8() {
+    assert_output(LARGE_COMPARE, vec![7], vec![999]);
+    assert_output(LARGE_COMPARE, vec![8], vec![1000]);
+    assert_output(LARGE_COMPARE, vec![9], vec![1001]);
+  }
+
+  #[test]
+  fn test_day_09_quine() {
+    let program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let expected = program
+      .split(",")
+      .map(|x| x.parse::<isize>().unwrap())
+      .collect();
+    assert_output(program, vec![], expected);
+  }
+
+  #[test]
+  fn test_day_09_large_numbers() {
+    assert_output(
+      "1102,34915192,34915192,7,4,7,99,0",
+      vec![],
+      vec![1219070632396864],
+    );
+    assert_output("104,1125899906842624,99", vec![], vec![1125899906842624]);
+  }
+
+  #[test]
+  fn test_relative_base() {
+    let mut program = vec![109, 2000, 109, 19, 204, -34, 99];
+    program.resize(1986, 0);
+    program[1985] = 42;
+    let mut cpu = IntCode::from_vec(program);
+    cpu.execute().unwrap();
+    assert_eq!(2019, cpu.get_relative_base());
+    assert_eq!(vec![42], *cpu.get_output());
+  }
+
+  #[test]
+  fn test_yield_and_resume() {
+    let mut cpu = IntCode::from_string("3,11,4,11,3,11,4,11,99,0,0,0");
+    let waiting = cpu.execute().unwrap();
+    assert_eq!(
+      IntCodeResult {
+        kind: IntCodeResultKind::Yield,
+        output: vec![],
+        instructions: 0,
+      },
+      waiting
+    );
+    cpu.input(&vec![7]);
+    let first = cpu.execute().unwrap();
+    assert_eq!(
+      IntCodeResult {
+        kind: IntCodeResultKind::Yield,
+        output: vec![7],
+        instructions: 2,
+      },
+      first
+    );
+    cpu.input(&vec![8]);
+    let second = cpu.execute().unwrap();
+    assert_eq!(
+      IntCodeResult {
+        kind: IntCodeResultKind::Halt,
+        output: vec![8],
+        instructions: 3,
+      },
+      second
+    );
+    assert_ne!(first, second);
+    assert_eq!(vec![7, 8], *cpu.get_output());
+    assert_eq!(5, cpu.get_instruction_count());
+  }
+
+  #[test]
+  fn test_self_modifying() {
+    // The add writes 99 over the trailing 0, which is then executed as a halt.
+    assert_output("1101,90,9,6,104,5,0", vec![], vec![5]);
+  }
+
+  #[test]
+  fn test_write_beyond_memory_end() {
+    let cpu = run("1101,2,3,10,4,10,99", vec![]);
+    assert_eq!(11, cpu.data.len());
+    assert_eq!(5, cpu.data[10]);
+    assert_eq!(vec![5], *cpu.get_output());
+  }
+
+  #[test]
+  fn test_read_beyond_memory_end() {
+    assert_output("4,100,99", vec![], vec![0]);
+    assert_output("109,50,204,50,99", vec![], vec![0]);
+  }
+
+  #[test]
+  fn test_peek_poke() {
+    let mut cpu = IntCode::from_string("1,0,0,0,99");
+    assert_eq!(Ok(1), cpu.peek(0));
+    assert_eq!(Ok(0), cpu.peek(50));
+    cpu.poke(7, 3).unwrap();
+    assert_eq!(8, cpu.memory_len());
+    assert_eq!(Ok(vec![99, 0, 0, 3, 0]), cpu.peek_range(4..9));
+    assert_eq!(Ok(vec![]), cpu.peek_range(4..4));
+  }
+
+  #[test]
+  fn test_patch() {
+    let mut cpu = IntCode::from_string("1,0,0,0,99,5,6");
+    cpu.patch(&[(1, 5), (2, 6)]).unwrap();
+    cpu.execute().unwrap();
+    assert_eq!(Ok(11), cpu.peek(0));
+    assert_eq!(4, cpu.get_instruction_pointer());
+  }
+
+  #[test]
+  fn test_patch_negative() {
+    let mut cpu = IntCode::from_string("1,0,0,0,99");
+    let error = error_at(IntCodeErrorKind::PokeOutOfRange { index: -2 }, 1, 0);
+    assert_eq!(Err(error.clone()), cpu.patch(&[(1, 3), (-2, 6)]));
+    assert_eq!(Err(error), cpu.poke(-2, 6));
+    assert_eq!(vec![1, 0, 0, 0, 99], cpu.get_memory());
+    assert_eq!(
+      Err(error_at(
+        IntCodeErrorKind::PeekOutOfRange { index: -1 },
+        1,
+        0,
+      )),
+      cpu.peek_range(-1..2)
+    );
+  }
+
+  #[test]
+  fn test_read_negative_position() {
+    assert_error(
+      vec![4, -1, 99],
+      error_at(
+        IntCodeErrorKind::ReadOutOfRange {
+          index: -1,
+          mode: OperationMode::Position,
+        },
+        4,
+        0,
+      ),
+    );
+  }
+
+  #[test]
+  fn test_read_negative_relative() {
+    assert_error(
+      vec![109, -5, 204, 0, 99],
+      error_at(
+        IntCodeErrorKind::ReadOutOfRange {
+          index: -5,
+          mode: OperationMode::Relative,
+        },
+        204,
+        2,
+      ),
+    );
+  }
+
+  #[test]
+  fn test_write_negative_position() {
+    assert_error(
+      vec![1101, 1, 1, -1, 99],
+      error_at(
+        IntCodeErrorKind::WriteOutOfRange {
+          index: -1,
+          mode: OperationMode::Position,
+        },
+        1101,
+        0,
+      ),
+    );
+  }
+
+  #[test]
+  fn test_write_negative_relative() {
+    assert_error(
+      vec![109, -3, 21101, 1, 1, 0, 99],
+      error_at(
+        IntCodeErrorKind::WriteOutOfRange {
+          index: -3,
+          mode: OperationMode::Relative,
+        },
+        21101,
+        2,
+      ),
+    );
+  }
+
+  #[test]
+  fn test_write_immediate() {
+    assert_error(
+      vec![11101, 1, 1, 5, 99],
+      error_at(
+        IntCodeErrorKind::WriteInvalidOperationMode {
+          mode: OperationMode::Immediate,
+        },
+        11101,
+        0,
+      ),
+    );
+  }
+
+  #[test]
+  fn test_error_report() {
+    let mut cpu = IntCode::from_vec(vec![1101, 2, 3, 9, 109, -9, 204, 1, 99]);
+    let error = cpu.execute().unwrap_err();
+    assert_eq!(
+      vec![(0, vec![1101, 2, 3, 9]), (4, vec![109, -9])],
+      error.context.trace
+    );
+    assert_eq!(
+      "read from negative address -8 in relative mode at address 6: out [rb+1]\n\
+       \x20 relative base: -9\n\
+       \x20 memory:\n\
+       \x20        0:  1101     2     3     9   109    -9   204     1\n\
+       \x20        8:    99     5\n\
+       \x20 recent instructions:\n\
+       \x20        0: add 2, 3, [9]\n\
+       \x20        4: arb -9",
+      error.to_string()
+    );
+  }
+
+  #[test]
+  fn test_error_trace_limit() {
+    // Counts cell 20 down from 5, then outputs from a negative address.
+    let mut cpu = IntCode::from_vec(vec![1001, 20, -1, 20, 1005, 20, 0, 4, -1]);
+    cpu.poke(20, 5).unwrap();
+    let error = cpu.execute().unwrap_err();
+    assert_eq!(7, error.index);
+    let trace: Vec<usize> = error.context.trace.iter().map(|(addr, _)| *addr).collect();
+    assert_eq!(vec![0, 4, 0, 4, 0, 4, 0, 4], trace);
+  }
+
+  /// Reads as 10 times the number of reads so far plus the offset, and records writes.
+  #[derive(Debug, Clone)]
+  struct Port {
+    reads: isize,
+    writes: Vec<(usize, isize)>,
+  }
+
+  impl IntCodeDevice for Port {
+    fn read(&mut self, offset: usize) -> isize {
+      self.reads += 1;
+      self.reads * 10 + offset as isize
+    }
+
+    fn write(&mut self, offset: usize, value: isize) {
+      self.writes.push((offset, value));
+    }
+
+    fn box_clone(&self) -> Box<dyn IntCodeDevice> {
+      Box::new(self.clone())
+    }
+  }
+
+  fn port() -> Port {
+    Port {
+      reads: 0,
+      writes: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn test_device() {
+    // Reads 1000 in position mode and 1001 in relative mode, then writes to 1002 both ways.
+    let mut cpu = IntCode::from_string("4,1000,109,998,204,3,1101,7,8,1002,21101,1,2,4,99");
+    let device = cpu.map_device(1000..1003, port()).unwrap();
+    let mut copy = cpu.clone();
+    assert_eq!(vec![10, 21], cpu.execute().unwrap().output);
+    assert_eq!(vec![(2, 15), (2, 3)], device.lock().unwrap().writes);
+    assert_eq!(15, cpu.memory_len());
+    assert_eq!(Ok(0), cpu.peek(1002));
+
+    // The copy has its own device, in the state it was in when copied.
+    assert_eq!(vec![10, 21], copy.execute().unwrap().output);
+    assert_eq!(2, device.lock().unwrap().writes.len());
+  }
+
+  #[test]
+  fn test_device_recording() {
+    let mut cpu = IntCode::from_string("4,1000,109,998,204,3,1101,7,8,1002,21101,1,2,4,99");
+    cpu.map_device(1000..1003, port()).unwrap();
+    cpu.start_recording();
+    let start = cpu.clone();
+    cpu.execute().unwrap();
+    let recording = cpu.stop_recording().unwrap();
+    let reads: Vec<isize> = recording
+      .events
+      .iter()
+      .filter_map(|e| match e {
+        RecordedEvent::DeviceRead { value, .. } => Some(*value),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(vec![10, 21], reads);
+    assert!(recording.replay(start).is_ok());
+
+    // A device that reads differently makes the replay diverge.
+    let mut other = IntCode::from_string("4,1000,109,998,204,3,1101,7,8,1002,21101,1,2,4,99");
+    let mut busy = port();
+    busy.reads = 5;
+    other.map_device(1000..1003, busy).unwrap();
+    assert_eq!(0, recording.replay(other).unwrap_err().event);
+  }
+
+  #[test]
+  fn test_device_overlap() {
+    let mut cpu = IntCode::from_string("99");
+    cpu.map_device(10..20, port()).unwrap();
+    assert_eq!(
+      IntCodeErrorKind::DeviceOverlap { start: 10, end: 20 },
+      cpu.map_device(15..25, port()).unwrap_err().kind
+    );
+    assert!(cpu.map_device(20..30, port()).is_ok());
+    assert!(cpu.map_device(5..10, port()).is_ok());
+  }
+
+  #[test]
+  fn test_instruction_limit() {
+    // An endless loop.
+    let mut cpu = IntCode::from_string("1105,1,0");
+    cpu.set_instruction_limit(Some(5));
+    let error = cpu.execute().unwrap_err();
+    assert_eq!(
+      error_at(IntCodeErrorKind::InstructionLimit { limit: 5 }, 1105, 0),
+      error
+    );
+    assert_eq!(5, cpu.get_instruction_count());
+
+    cpu.set_instruction_limit(Some(7));
+    assert!(cpu.execute().is_err());
+    assert_eq!(7, cpu.get_instruction_count());
+  }
+
+  #[test]
+  fn test_invalid_opcode() {
+    assert_error(
+      vec![98],
+      error_at(IntCodeErrorKind::InvalidOpcode { opcode: 98 }, 98, 0),
+    );
+    assert_error(
+      vec![1101, 1, 1, 5, -1],
+      error_at(IntCodeErrorKind::InvalidOpcode { opcode: -1 }, -1, 4),
+    );
+    assert_error(
+      vec![301, 0, 0, 0, 99],
+      error_at(IntCodeErrorKind::InvalidMode { opcode: 301 }, 301, 0),
+    );
+    let error = IntCode::from_vec(vec![98]).execute().unwrap_err();
+    assert!(error
+      .to_string()
+      .starts_with("invalid opcode 98 at address 0"));
+  }
+
+  #[test]
+  fn test_jump_out_of_range() {
+    assert_error(
+      vec![1105, 1, -4],
+      error_at(IntCodeErrorKind::JumpOutOfRange { index: -4 }, 1105, 0),
+    );
+    assert_error(
+      vec![1106, 0, -1],
+      error_at(IntCodeErrorKind::JumpOutOfRange { index: -1 }, 1106, 0),
+    );
+    // Past the end of memory reads as opcode 0.
+    assert_error(
+      vec![1105, 1, 100],
+      error_at(IntCodeErrorKind::InvalidOpcode { opcode: 0 }, 0, 100),
+    );
+
+    let mut cpu = IntCode::from_vec(vec![1105, 1, 100]);
+    cpu.set_instruction_limit(Some(1));
+    assert_eq!(
+      error_at(IntCodeErrorKind::InstructionLimit { limit: 1 }, 0, 100),
+      cpu.execute().unwrap_err()
+    );
+  }
 }
@@ -0,0 +1,232 @@
+use super::intcode::IntCode;
+use super::intcode_error::IntCodeError;
+use super::intcode_result::IntCodeResultKind;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// Wraps an `IntCode` machine that communicates in ASCII, translating lines of text into inputs
+/// and outputs back into text.
+#[derive(Debug, Clone)]
+pub struct IntCodeAscii {
+  cpu: IntCode,
+}
+
+/// The output produced by a single `IntCodeAscii::execute` call. Values outside of the ASCII range
+/// cannot be represented as text, and are passed through in `values` instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntCodeAsciiOutput {
+  pub kind: IntCodeResultKind,
+  pub text: String,
+  pub values: Vec<isize>,
+}
+
+/// A failure while talking to an `IntCodeAscii` machine, either of the machine itself or of the
+/// reader or writer it's connected to.
+#[derive(Debug)]
+pub enum IntCodeAsciiError {
+  Io(io::Error),
+  IntCode(IntCodeError),
+}
+
+impl IntCodeAscii {
+  pub fn new(cpu: IntCode) -> Self {
+    IntCodeAscii { cpu }
+  }
+
+  pub fn from_string(raw: &str) -> Self {
+    IntCodeAscii::new(IntCode::from_string(raw))
+  }
+
+  pub fn cpu(&self) -> &IntCode {
+    &self.cpu
+  }
+
+  /// Queues each character of `line` as input, followed by a newline.
+  pub fn input_line(&mut self, line: &str) {
+    let mut input: Vec<isize> = line.chars().map(|c| c as isize).collect();
+    input.push('\n' as isize);
+    self.cpu.input(&input);
+  }
+
+  /// Runs the machine until it halts or needs more input, decoding any output produced since the
+  /// last call.
+  pub fn execute(&mut self) -> Result<IntCodeAsciiOutput, IntCodeError> {
+    let result = self.cpu.execute()?;
+    let mut text = String::new();
+    let mut values = Vec::new();
+    for value in result.output {
+      match IntCodeAscii::to_char(value) {
+        Some(c) => text.push(c),
+        None => values.push(value),
+      }
+    }
+    Ok(IntCodeAsciiOutput {
+      kind: result.kind,
+      text,
+      values,
+    })
+  }
+
+  /// Connects the machine to the terminal, reading lines from stdin whenever it needs input and
+  /// printing its output to stdout.
+  pub fn interactive(&mut self) -> Result<IntCodeResultKind, IntCodeAsciiError> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    self.interact(stdin.lock(), stdout.lock())
+  }
+
+  /// Like `interactive`, but over any reader and writer. Returns `Yield` if the reader runs out of
+  /// lines before the machine halts. Values outside of the ASCII range are written on their own
+  /// line, in the order they were output.
+  pub fn interact<R, W>(
+    &mut self,
+    mut reader: R,
+    mut writer: W,
+  ) -> Result<IntCodeResultKind, IntCodeAsciiError>
+  where
+    R: BufRead,
+    W: Write,
+  {
+    loop {
+      let result = self.cpu.execute()?;
+      for value in result.output {
+        match IntCodeAscii::to_char(value) {
+          Some(c) => write!(writer, "{}", c)?,
+          None => writeln!(writer, "{}", value)?,
+        }
+      }
+      writer.flush()?;
+      if let IntCodeResultKind::Halt = result.kind {
+        return Ok(result.kind);
+      }
+
+      let mut line = String::new();
+      if reader.read_line(&mut line)? == 0 {
+        return Ok(result.kind);
+      }
+      self.input_line(line.trim_end_matches(&['\n', '\r'][..]));
+    }
+  }
+
+  fn to_char(value: isize) -> Option<char> {
+    match value {
+      0..=127 => Some(value as u8 as char),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for IntCodeAsciiError {
+  fn from(error: io::Error) -> Self {
+    IntCodeAsciiError::Io(error)
+  }
+}
+
+impl From<IntCodeError> for IntCodeAsciiError {
+  fn from(error: IntCodeError) -> Self {
+    IntCodeAsciiError::IntCode(error)
+  }
+}
+
+impl fmt::Display for IntCodeAsciiError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      IntCodeAsciiError::Io(error) => write!(f, "{}", error),
+      IntCodeAsciiError::IntCode(error) => write!(f, "{}", error),
+    }
+  }
+}
+
+impl Error for IntCodeAsciiError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      IntCodeAsciiError::Io(error) => Some(error),
+      IntCodeAsciiError::IntCode(error) => Some(error),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  // Echoes characters until it sees a newline, then outputs 1000 and halts.
+  const ECHO_LINE: &str = "3,100,4,100,1008,100,10,101,1006,101,0,104,1000,99";
+
+  #[test]
+  fn test_execute() {
+    let mut ascii = IntCodeAscii::from_string(ECHO_LINE);
+    assert_eq!(
+      IntCodeAsciiOutput {
+        kind: IntCodeResultKind::Yield,
+        text: String::new(),
+        values: vec![],
+      },
+      ascii.execute().unwrap()
+    );
+    ascii.input_line("hi");
+    assert_eq!(
+      IntCodeAsciiOutput {
+        kind: IntCodeResultKind::Halt,
+        text: String::from("hi\n"),
+        values: vec![1000],
+      },
+      ascii.execute().unwrap()
+    );
+  }
+
+  #[test]
+  fn test_interact() {
+    let mut ascii = IntCodeAscii::from_string(ECHO_LINE);
+    let mut out = Vec::new();
+    let kind = ascii.interact(Cursor::new("hello\r\n"), &mut out).unwrap();
+    assert_eq!(IntCodeResultKind::Halt, kind);
+    assert_eq!("hello\n1000\n", String::from_utf8(out).unwrap());
+  }
+
+  #[test]
+  fn test_interact_end_of_input() {
+    let mut ascii = IntCodeAscii::from_string(ECHO_LINE);
+    let mut out = Vec::new();
+    let kind = ascii.interact(Cursor::new(""), &mut out).unwrap();
+    assert_eq!(IntCodeResultKind::Yield, kind);
+    assert!(out.is_empty());
+  }
+
+  #[test]
+  fn test_interact_values_in_order() {
+    // Outputs 'a', 1000, 'b'.
+    let mut ascii = IntCodeAscii::from_string("104,97,104,1000,104,98,104,10,99");
+    let mut out = Vec::new();
+    ascii.interact(Cursor::new(""), &mut out).unwrap();
+    assert_eq!("a1000\nb\n", String::from_utf8(out).unwrap());
+  }
+
+  #[test]
+  fn test_interact_errors() {
+    let mut ascii = IntCodeAscii::from_string("98");
+    match ascii.interact(Cursor::new(""), Vec::new()) {
+      Err(IntCodeAsciiError::IntCode(error)) => assert_eq!(0, error.index),
+      other => panic!("expected an IntCode error, got {:?}", other),
+    }
+
+    // A writer that always fails.
+    struct Broken;
+    impl Write for Broken {
+      fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken"))
+      }
+      fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+      }
+    }
+    let mut ascii = IntCodeAscii::from_string(ECHO_LINE);
+    ascii.input_line("hi");
+    match ascii.interact(Cursor::new(""), Broken) {
+      Err(IntCodeAsciiError::Io(error)) => assert_eq!(io::ErrorKind::BrokenPipe, error.kind()),
+      other => panic!("expected an IO error, got {:?}", other),
+    }
+  }
+}
@@ -0,0 +1,669 @@
+use super::compile_error::{CompileError, CompileErrorKind};
+use super::compiler_lexer::tokenize;
+use super::compiler_parser::{BinaryOp, Expr, Function, Parser, Stmt, UnaryOp};
+use super::intcode_linker::{IntCodeLinker, IntCodeModule};
+use std::collections::HashMap;
+
+/// Functions every program can call, and how many arguments they take.
+const BUILTINS: [(&str, usize); 2] = [("input", 0), ("output", 1)];
+
+/// Compiles a small C-like language to Intcode.
+///
+/// A program is a list of functions, and runs by calling `main`:
+///
+/// ```text
+/// fn square(x) { return x * x; }
+///
+/// fn main() {
+///   let n = input();
+///   let i = 0;
+///   while i < n {
+///     output(square(i));
+///     i = i + 1;
+///   }
+/// }
+/// ```
+///
+/// Every value is an integer. The operators are `+ - * < <= > >= == != && || !` and unary `-`,
+/// where comparisons give 1 or 0 and `&&` and `||` short-circuit. `input()` reads a value and
+/// `output(x)` writes one. Functions return 0 if they finish without a `return`.
+///
+/// Each function is compiled into its own module and linked with `IntCodeLinker`, so functions
+/// follow its calling convention and keep their variables on the stack.
+pub struct IntCodeCompiler;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+  Immediate(isize),
+  /// A variable or temporary in the current stack frame.
+  Slot(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Param {
+  Operand(Operand),
+  /// An address relative to the relative base, whatever the frame size.
+  Relative(isize),
+  /// The address of a label in the current function.
+  Label(usize),
+  /// The address of another function.
+  Function(String),
+  /// The size of the current frame, times `sign`.
+  Frame(isize),
+}
+
+/// Generates the code for a single function.
+///
+/// The frame is a run of slots ending at the relative base: slot 0 is the return address, slot 1
+/// up are the arguments, and after them come local variables and temporaries. Slot 1 is always
+/// reserved since it receives the return value. The frame size is only known once the whole
+/// function has been generated, so references to slots are patched at the end.
+struct FunctionCompiler<'a> {
+  signatures: &'a HashMap<String, usize>,
+  code: Vec<isize>,
+  relocations: Vec<usize>,
+  imports: Vec<(usize, String)>,
+  slot_refs: Vec<(usize, usize)>,
+  frame_refs: Vec<(usize, isize)>,
+  labels: Vec<Option<usize>>,
+  label_refs: Vec<(usize, usize)>,
+  scopes: Vec<HashMap<String, usize>>,
+  next_slot: usize,
+  frame_size: usize,
+}
+
+impl IntCodeCompiler {
+  pub fn compile(source: &str) -> Result<Vec<isize>, CompileError> {
+    let functions = Parser::new(tokenize(source)?).parse()?;
+    let mut signatures: HashMap<String, usize> = HashMap::new();
+    for (name, arity) in BUILTINS.iter() {
+      signatures.insert(name.to_string(), *arity);
+    }
+    for function in &functions {
+      if signatures
+        .insert(function.name.clone(), function.params.len())
+        .is_some()
+      {
+        let kind = CompileErrorKind::DuplicateFunction {
+          name: function.name.clone(),
+        };
+        return Err(CompileError::new(kind, function.pos));
+      }
+    }
+    if signatures.get("main") != Some(&0) {
+      return Err(CompileError::new(CompileErrorKind::MissingMain, (1, 1)));
+    }
+
+    let mut linker = IntCodeLinker::new();
+    for function in &functions {
+      linker = linker.module(FunctionCompiler::new(&signatures).compile(function)?);
+    }
+    let linked = linker
+      .link()
+      .expect("every called function was checked to exist");
+    Ok(linked.program)
+  }
+}
+
+impl<'a> FunctionCompiler<'a> {
+  fn new(signatures: &'a HashMap<String, usize>) -> Self {
+    FunctionCompiler {
+      signatures,
+      code: Vec::new(),
+      relocations: Vec::new(),
+      imports: Vec::new(),
+      slot_refs: Vec::new(),
+      frame_refs: Vec::new(),
+      labels: Vec::new(),
+      label_refs: Vec::new(),
+      scopes: Vec::new(),
+      next_slot: 0,
+      frame_size: 0,
+    }
+  }
+
+  fn compile(mut self, function: &Function) -> Result<IntCodeModule, CompileError> {
+    let mut params = HashMap::new();
+    for (i, param) in function.params.iter().enumerate() {
+      if params.insert(param.clone(), i + 1).is_some() {
+        let kind = CompileErrorKind::DuplicateVariable {
+          name: param.clone(),
+        };
+        return Err(CompileError::new(kind, function.pos));
+      }
+    }
+    self.scopes.push(params);
+    self.next_slot = function.params.len().max(1) + 1;
+    self.frame_size = self.next_slot;
+
+    self.emit(9, vec![Param::Frame(1)]);
+    self.block(&function.body)?;
+    self.copy(Operand::Immediate(0), 1);
+    self.ret();
+
+    for (cell, slot) in &self.slot_refs {
+      self.code[*cell] = *slot as isize - self.frame_size as isize;
+    }
+    for (cell, sign) in &self.frame_refs {
+      self.code[*cell] = sign * self.frame_size as isize;
+    }
+    for (cell, label) in &self.label_refs {
+      self.code[*cell] = self.labels[*label].unwrap() as isize;
+    }
+    let mut module = IntCodeModule::new(&function.name, self.code).export(&function.name, 0);
+    module.relocations = self.relocations;
+    module.imports = self.imports;
+    Ok(module)
+  }
+
+  fn block(&mut self, stmts: &[Stmt]) -> Result<(), CompileError> {
+    let mark = self.next_slot;
+    self.scopes.push(HashMap::new());
+    for stmt in stmts {
+      self.stmt(stmt)?;
+    }
+    self.scopes.pop();
+    self.next_slot = mark;
+    Ok(())
+  }
+
+  fn stmt(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+    let mark = self.next_slot;
+    match stmt {
+      Stmt::Let { name, value, pos } => {
+        if self.scopes.last().unwrap().contains_key(name) {
+          let kind = CompileErrorKind::DuplicateVariable { name: name.clone() };
+          return Err(CompileError::new(kind, *pos));
+        }
+        let slot = self.alloc();
+        self.expr_into(value, slot)?;
+        self.scopes.last_mut().unwrap().insert(name.clone(), slot);
+        self.next_slot = slot + 1;
+        return Ok(());
+      }
+      Stmt::Assign { name, value, pos } => {
+        let slot = self.lookup(name, *pos)?;
+        self.expr_into(value, slot)?;
+      }
+      Stmt::If {
+        cond,
+        then,
+        otherwise,
+      } => {
+        let (otherwise_label, end) = (self.label(), self.label());
+        self.jump_if_false(cond, otherwise_label)?;
+        self.next_slot = mark;
+        self.block(then)?;
+        self.jump(end);
+        self.place(otherwise_label);
+        self.block(otherwise)?;
+        self.place(end);
+      }
+      Stmt::While { cond, body } => {
+        let (start, end) = (self.label(), self.label());
+        self.place(start);
+        self.jump_if_false(cond, end)?;
+        self.next_slot = mark;
+        self.block(body)?;
+        self.jump(start);
+        self.place(end);
+      }
+      Stmt::Return { value } => {
+        match value {
+          Some(value) => self.expr_into(value, 1)?,
+          None => self.copy(Operand::Immediate(0), 1),
+        }
+        self.ret();
+      }
+      Stmt::Expr(expr) => {
+        self.operand(expr)?;
+      }
+    }
+    self.next_slot = mark;
+    Ok(())
+  }
+
+  /// Returns an operand holding the value of `expr`, generating code to compute it into a new
+  /// temporary if it isn't a constant or variable.
+  fn operand(&mut self, expr: &Expr) -> Result<Operand, CompileError> {
+    match expr {
+      Expr::Number(n) => Ok(Operand::Immediate(*n)),
+      Expr::Var { name, pos } => Ok(Operand::Slot(self.lookup(name, *pos)?)),
+      _ => {
+        let slot = self.alloc();
+        self.expr_into(expr, slot)?;
+        Ok(Operand::Slot(slot))
+      }
+    }
+  }
+
+  /// Generates code that computes `expr` into `dst`. `dst` is only written once every operand has
+  /// been read, so it may be one of the variables in `expr`.
+  fn expr_into(&mut self, expr: &Expr, dst: usize) -> Result<(), CompileError> {
+    let dst_param = Param::Operand(Operand::Slot(dst));
+    match expr {
+      Expr::Number(_) | Expr::Var { .. } => {
+        let src = self.operand(expr)?;
+        self.copy(src, dst);
+      }
+      Expr::Unary { op, expr } => {
+        let a = Param::Operand(self.operand(expr)?);
+        match op {
+          UnaryOp::Negate => self.emit(
+            2,
+            vec![a, Param::Operand(Operand::Immediate(-1)), dst_param],
+          ),
+          UnaryOp::Not => self.emit(8, vec![a, Param::Operand(Operand::Immediate(0)), dst_param]),
+        }
+      }
+      Expr::Binary {
+        op: op @ BinaryOp::And,
+        left,
+        right,
+      }
+      | Expr::Binary {
+        op: op @ BinaryOp::Or,
+        left,
+        right,
+      } => {
+        // Skip the right hand side when the left decides the result.
+        let (short_circuit, end) = (self.label(), self.label());
+        let a = Param::Operand(self.operand(left)?);
+        let jump = if *op == BinaryOp::And { 6 } else { 5 };
+        self.emit(jump, vec![a, Param::Label(short_circuit)]);
+        let b = self.operand(right)?;
+        self.truth(b, dst);
+        self.jump(end);
+        self.place(short_circuit);
+        self.copy(Operand::Immediate((*op == BinaryOp::Or) as isize), dst);
+        self.place(end);
+      }
+      Expr::Binary { op, left, right } => {
+        let a = self.operand(left)?;
+        let b = self.operand(right)?;
+        self.binary(*op, a, b, dst);
+      }
+      Expr::Call { name, args, pos } => self.call(name, args, *pos, dst)?,
+    }
+    Ok(())
+  }
+
+  fn binary(&mut self, op: BinaryOp, a: Operand, b: Operand, dst: usize) {
+    let (opcode, a, b, negate) = match op {
+      BinaryOp::Add => (1, a, b, false),
+      BinaryOp::Mul => (2, a, b, false),
+      BinaryOp::Sub => match b {
+        Operand::Immediate(v) => (1, a, Operand::Immediate(-v), false),
+        Operand::Slot(_) => {
+          let t = self.alloc();
+          self.emit(
+            2,
+            vec![
+              Param::Operand(b),
+              Param::Operand(Operand::Immediate(-1)),
+              Param::Operand(Operand::Slot(t)),
+            ],
+          );
+          (1, a, Operand::Slot(t), false)
+        }
+      },
+      BinaryOp::Less => (7, a, b, false),
+      BinaryOp::Greater => (7, b, a, false),
+      BinaryOp::LessEqual => (7, b, a, true),
+      BinaryOp::GreaterEqual => (7, a, b, true),
+      BinaryOp::Equal => (8, a, b, false),
+      BinaryOp::NotEqual => (8, a, b, true),
+      BinaryOp::And | BinaryOp::Or => unreachable!("logical operators short-circuit"),
+    };
+    let (a, b) = (Param::Operand(a), Param::Operand(b));
+    match negate {
+      true => {
+        let t = self.alloc();
+        self.emit(opcode, vec![a, b, Param::Operand(Operand::Slot(t))]);
+        self.emit(
+          8,
+          vec![
+            Param::Operand(Operand::Slot(t)),
+            Param::Operand(Operand::Immediate(0)),
+            Param::Operand(Operand::Slot(dst)),
+          ],
+        );
+      }
+      false => self.emit(opcode, vec![a, b, Param::Operand(Operand::Slot(dst))]),
+    }
+  }
+
+  fn call(
+    &mut self,
+    name: &str,
+    args: &[Expr],
+    pos: (usize, usize),
+    dst: usize,
+  ) -> Result<(), CompileError> {
+    let expected = match self.signatures.get(name) {
+      Some(n) => *n,
+      None => {
+        let kind = CompileErrorKind::UndefinedFunction {
+          name: name.to_string(),
+        };
+        return Err(CompileError::new(kind, pos));
+      }
+    };
+    if args.len() != expected {
+      let kind = CompileErrorKind::WrongArgumentCount {
+        name: name.to_string(),
+        expected,
+        found: args.len(),
+      };
+      return Err(CompileError::new(kind, pos));
+    }
+    let mut operands = Vec::new();
+    for arg in args {
+      operands.push(self.operand(arg)?);
+    }
+
+    match name {
+      "input" => self.emit(3, vec![Param::Operand(Operand::Slot(dst))]),
+      "output" => {
+        self.emit(4, vec![Param::Operand(operands[0])]);
+        self.copy(Operand::Immediate(0), dst);
+      }
+      _ => {
+        let zero = Param::Operand(Operand::Immediate(0));
+        let ret = self.label();
+        self.emit(1, vec![Param::Label(ret), zero.clone(), Param::Relative(0)]);
+        for (i, operand) in operands.iter().enumerate() {
+          let arg = Param::Relative(i as isize + 1);
+          self.emit(1, vec![Param::Operand(*operand), zero.clone(), arg]);
+        }
+        let one = Param::Operand(Operand::Immediate(1));
+        self.emit(5, vec![one, Param::Function(name.to_string())]);
+        self.place(ret);
+        self.emit(
+          1,
+          vec![Param::Relative(1), zero, Param::Operand(Operand::Slot(dst))],
+        );
+      }
+    }
+    Ok(())
+  }
+
+  fn jump_if_false(&mut self, cond: &Expr, label: usize) -> Result<(), CompileError> {
+    let cond = Param::Operand(self.operand(cond)?);
+    self.emit(6, vec![cond, Param::Label(label)]);
+    Ok(())
+  }
+
+  fn jump(&mut self, label: usize) {
+    let one = Param::Operand(Operand::Immediate(1));
+    self.emit(5, vec![one, Param::Label(label)]);
+  }
+
+  /// Releases the frame and jumps to the return address.
+  fn ret(&mut self) {
+    self.emit(9, vec![Param::Frame(-1)]);
+    let zero = Param::Operand(Operand::Immediate(0));
+    self.emit(6, vec![zero, Param::Relative(0)]);
+  }
+
+  fn copy(&mut self, src: Operand, dst: usize) {
+    let zero = Param::Operand(Operand::Immediate(0));
+    let dst = Param::Operand(Operand::Slot(dst));
+    self.emit(1, vec![Param::Operand(src), zero, dst]);
+  }
+
+  /// Stores 1 in `dst` if `src` is non-zero, or 0 otherwise.
+  fn truth(&mut self, src: Operand, dst: usize) {
+    let zero = Param::Operand(Operand::Immediate(0));
+    let t = self.alloc();
+    let t = Param::Operand(Operand::Slot(t));
+    self.emit(8, vec![Param::Operand(src), zero.clone(), t.clone()]);
+    self.emit(8, vec![t, zero, Param::Operand(Operand::Slot(dst))]);
+  }
+
+  fn emit(&mut self, opcode: isize, params: Vec<Param>) {
+    let start = self.code.len();
+    let mut modes = 0;
+    let mut scale = 100;
+    self.code.push(0);
+    for param in params {
+      let cell = self.code.len();
+      let (mode, value) = match param {
+        Param::Operand(Operand::Immediate(v)) => (1, v),
+        Param::Operand(Operand::Slot(slot)) => {
+          self.slot_refs.push((cell, slot));
+          (2, 0)
+        }
+        Param::Relative(offset) => (2, offset),
+        Param::Label(label) => {
+          self.label_refs.push((cell, label));
+          self.relocations.push(cell);
+          (1, 0)
+        }
+        Param::Function(name) => {
+          self.imports.push((cell, name));
+          (1, 0)
+        }
+        Param::Frame(sign) => {
+          self.frame_refs.push((cell, sign));
+          (1, 0)
+        }
+      };
+      modes += mode * scale;
+      scale *= 10;
+      self.code.push(value);
+    }
+    self.code[start] = opcode + modes;
+  }
+
+  fn alloc(&mut self) -> usize {
+    let slot = self.next_slot;
+    self.next_slot += 1;
+    self.frame_size = self.frame_size.max(self.next_slot);
+    slot
+  }
+
+  fn lookup(&self, name: &str, pos: (usize, usize)) -> Result<usize, CompileError> {
+    match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+      Some(slot) => Ok(*slot),
+      None => {
+        let kind = CompileErrorKind::UndefinedVariable {
+          name: name.to_string(),
+        };
+        Err(CompileError::new(kind, pos))
+      }
+    }
+  }
+
+  fn label(&mut self) -> usize {
+    self.labels.push(None);
+    self.labels.len() - 1
+  }
+
+  fn place(&mut self, label: usize) {
+    self.labels[label] = Some(self.code.len());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::IntCode;
+  use crate::intcode_ascii::IntCodeAscii;
+  use crate::intcode_result::IntCodeResultKind;
+
+  fn run(source: &str, input: Vec<isize>) -> Vec<isize> {
+    let program = IntCodeCompiler::compile(source).unwrap();
+    let mut cpu = IntCode::from_vec(program);
+    cpu.input(&input);
+    let result = cpu.execute().unwrap();
+    assert_eq!(IntCodeResultKind::Halt, result.kind);
+    result.output
+  }
+
+  fn error(source: &str) -> String {
+    IntCodeCompiler::compile(source).unwrap_err().to_string()
+  }
+
+  #[test]
+  fn test_arithmetic() {
+    let source = "fn main() {
+      output(1 + 2 * 3 - 4);
+      output(-5 * -(1 - 2));
+      let x = input();
+      let y = input();
+      output(x - y);
+      x = x - y * 2;
+      output(x);
+    }";
+    assert_eq!(vec![3, -5, 10, 6], run(source, vec![14, 4]));
+  }
+
+  #[test]
+  fn test_comparisons() {
+    let source = "fn main() {
+      let a = input();
+      let b = input();
+      output(a < b);
+      output(a <= b);
+      output(a > b);
+      output(a >= b);
+      output(a == b);
+      output(a != b);
+      output(!a);
+    }";
+    assert_eq!(vec![1, 1, 0, 0, 0, 1, 0], run(source, vec![3, 4]));
+    assert_eq!(vec![0, 1, 0, 1, 1, 0, 0], run(source, vec![4, 4]));
+    assert_eq!(vec![0, 0, 1, 1, 0, 1, 1], run(source, vec![0, -2]));
+  }
+
+  #[test]
+  fn test_control_flow() {
+    // Outputs the sum of the inputs, then classifies it.
+    let source = "fn main() {
+      let n = input();
+      let sum = 0;
+      while n > 0 {
+        sum = sum + input();
+        n = n - 1;
+      }
+      output(sum);
+      if sum < 0 {
+        output(-1);
+      } else if sum == 0 {
+        output(0);
+      } else {
+        output(1);
+      }
+    }";
+    assert_eq!(vec![15, 1], run(source, vec![5, 1, 2, 3, 4, 5]));
+    assert_eq!(vec![0, 0], run(source, vec![0]));
+    assert_eq!(vec![-3, -1], run(source, vec![2, 1, -4]));
+  }
+
+  #[test]
+  fn test_functions() {
+    let source = "
+      fn fact(n) {
+        if n <= 1 { return 1; }
+        return n * fact(n - 1);
+      }
+
+      fn fib(n) {
+        if n < 2 { return n; }
+        return fib(n - 1) + fib(n - 2);
+      }
+
+      fn sum3(a, b, c) { return a + b + c; }
+
+      fn nothing() {}
+
+      fn main() {
+        output(fact(10));
+        output(fib(15));
+        output(sum3(fib(5), fact(3), sum3(1, 2, 3)));
+        output(nothing());
+      }";
+    assert_eq!(vec![3628800, 610, 17, 0], run(source, vec![]));
+  }
+
+  #[test]
+  fn test_scopes() {
+    let source = "fn main() {
+      let x = 1;
+      if 1 {
+        let x = x + 10;
+        output(x);
+        let y = 5;
+        x = x + y;
+        output(x);
+      }
+      output(x);
+    }";
+    assert_eq!(vec![11, 16, 1], run(source, vec![]));
+  }
+
+  #[test]
+  fn test_short_circuit() {
+    let source = "
+      fn loud(x) { output(99); return x; }
+
+      fn main() {
+        output(0 && loud(1));
+        output(1 || loud(0));
+        output(2 && loud(3));
+        output(0 || loud(0));
+      }";
+    assert_eq!(vec![0, 1, 99, 1, 99, 0], run(source, vec![]));
+  }
+
+  #[test]
+  fn test_ascii() {
+    // Greets whoever is named on the first line of input.
+    let source = "
+      fn print_hello() {
+        output(72); output(101); output(108); output(108); output(111); output(32);
+      }
+
+      fn main() {
+        print_hello();
+        let c = input();
+        while c != 10 {
+          output(c);
+          c = input();
+        }
+        output(33);
+        output(10);
+      }";
+    let program = IntCodeCompiler::compile(source).unwrap();
+    let mut ascii = IntCodeAscii::new(IntCode::from_vec(program));
+    ascii.input_line("Intcode");
+    assert_eq!("Hello Intcode!\n", ascii.execute().unwrap().text);
+  }
+
+  #[test]
+  fn test_errors() {
+    assert_eq!(
+      "1:20: undefined variable y",
+      error("fn main() { output(y); }")
+    );
+    assert_eq!("1:13: undefined function f", error("fn main() { f(); }"));
+    assert_eq!(
+      "1:13: function output takes 1 arguments but 2 were given",
+      error("fn main() { output(1, 2); }")
+    );
+    assert_eq!(
+      "1:28: variable x is already defined in this scope",
+      error("fn main() { let x = 1; let x = 2; }")
+    );
+    assert_eq!(
+      "1:1: function input is already defined",
+      error("fn input() {} fn main() {}")
+    );
+    assert_eq!(
+      "1:1: no main function without parameters",
+      error("fn main(x) {}")
+    );
+    assert_eq!("1:1: no main function without parameters", error(""));
+  }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+/// A peripheral mapped into a range of an `IntCode` machine's memory.
+///
+/// Instructions that read or write a cell in the range call the device instead of touching
+/// memory. Offsets are relative to the start of the range.
+pub trait IntCodeDevice: Send {
+  fn read(&mut self, offset: usize) -> isize;
+  fn write(&mut self, offset: usize, value: isize);
+
+  /// A copy of the device in its current state, for a clone of the machine it's mapped into.
+  /// Usually `Box::new(self.clone())`.
+  fn box_clone(&self) -> Box<dyn IntCodeDevice>;
+}
+
+impl IntCodeDevice for Box<dyn IntCodeDevice> {
+  fn read(&mut self, offset: usize) -> isize {
+    (**self).read(offset)
+  }
+
+  fn write(&mut self, offset: usize, value: isize) {
+    (**self).write(offset, value)
+  }
+
+  fn box_clone(&self) -> Box<dyn IntCodeDevice> {
+    (**self).box_clone()
+  }
+}
+
+/// A device and the addresses it is mapped to. Cloning copies the device, so clones of a machine
+/// never affect each other.
+pub struct MappedDevice {
+  pub range: Range<usize>,
+  pub device: Arc<Mutex<dyn IntCodeDevice>>,
+}
+
+impl MappedDevice {
+  pub fn read(&self, addr: usize) -> isize {
+    self.device.lock().unwrap().read(addr - self.range.start)
+  }
+
+  pub fn write(&self, addr: usize, value: isize) {
+    self
+      .device
+      .lock()
+      .unwrap()
+      .write(addr - self.range.start, value)
+  }
+}
+
+impl Clone for MappedDevice {
+  fn clone(&self) -> Self {
+    let device = self.device.lock().unwrap().box_clone();
+    MappedDevice {
+      range: self.range.clone(),
+      device: Arc::new(Mutex::new(device)),
+    }
+  }
+}
+
+impl fmt::Debug for MappedDevice {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("MappedDevice")
+      .field("range", &self.range)
+      .finish()
+  }
+}
@@ -0,0 +1,223 @@
+use super::intcode::IntCode;
+use super::operation::{Operation, OperationMode};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The differences between two memory images, such as the same program before and after a run,
+/// or two runs with different input.
+///
+/// Changed cells are grouped into ranges of consecutive addresses. A range is code if it touches
+/// an instruction of the `before` image, found by decoding from address 0 and following every
+/// jump with a constant target. Jumps through memory can't be followed, so code only reached that
+/// way, such as the return site of a call, is treated as data. Memory past the end of the shorter
+/// image counts as 0, the same as reads past the end of memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntCodeDiff {
+  pub ranges: Vec<DiffRange>,
+  /// The instructions of the `before` image that were overwritten, oldest address first.
+  pub modified_instructions: Vec<ModifiedInstruction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffRange {
+  pub start: usize,
+  pub before: Vec<isize>,
+  pub after: Vec<isize>,
+  pub code: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifiedInstruction {
+  pub addr: usize,
+  pub before: String,
+  pub after: String,
+}
+
+impl IntCodeDiff {
+  pub fn new(before: &[isize], after: &[isize]) -> Self {
+    let cell = |memory: &[isize], addr: usize| *memory.get(addr).unwrap_or(&0);
+    let instructions = IntCodeDiff::instructions(before);
+    let mut ranges: Vec<DiffRange> = Vec::new();
+    let mut modified: BTreeMap<usize, ModifiedInstruction> = BTreeMap::new();
+
+    for addr in 0..before.len().max(after.len()) {
+      let (old, new) = (cell(before, addr), cell(after, addr));
+      if old == new {
+        continue;
+      }
+      let instruction = instructions
+        .range(..=addr)
+        .next_back()
+        .filter(|(start, length)| addr < *start + *length);
+      if let Some((start, _)) = instruction {
+        modified
+          .entry(*start)
+          .or_insert_with(|| ModifiedInstruction {
+            addr: *start,
+            before: Operation::disassemble(&before[*start..]),
+            after: Operation::disassemble(after.get(*start..).unwrap_or(&[])),
+          });
+      }
+      match ranges.last_mut() {
+        Some(range) if range.start + range.before.len() == addr => {
+          range.before.push(old);
+          range.after.push(new);
+          range.code |= instruction.is_some();
+        }
+        _ => ranges.push(DiffRange {
+          start: addr,
+          before: vec![old],
+          after: vec![new],
+          code: instruction.is_some(),
+        }),
+      }
+    }
+
+    IntCodeDiff {
+      ranges,
+      modified_instructions: modified.into_values().collect(),
+    }
+  }
+
+  pub fn between(before: &IntCode, after: &IntCode) -> Self {
+    IntCodeDiff::new(before.get_memory(), after.get_memory())
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.ranges.is_empty()
+  }
+
+  /// The number of cells that differ.
+  pub fn changed_cells(&self) -> usize {
+    self.ranges.iter().map(|r| r.before.len()).sum()
+  }
+
+  /// The address and length of every instruction reachable from address 0.
+  fn instructions(memory: &[isize]) -> BTreeMap<usize, usize> {
+    let mut instructions = BTreeMap::new();
+    let mut pending = vec![0];
+    while let Some(addr) = pending.pop() {
+      if addr >= memory.len() || instructions.contains_key(&addr) {
+        continue;
+      }
+      let operation = match Operation::decode(memory[addr]) {
+        Some(operation) => operation,
+        None => continue,
+      };
+      instructions.insert(addr, operation.length());
+      let next = addr + operation.length();
+      let (modes, jump_if) = match &operation {
+        Operation::Halt => continue,
+        Operation::JumpIfTrue(modes) => (modes, true),
+        Operation::JumpIfFalse(modes) => (modes, false),
+        _ => {
+          pending.push(next);
+          continue;
+        }
+      };
+      let cell = |param: usize| *memory.get(addr + param).unwrap_or(&0);
+      // Only the side of a jump with a constant condition that can be taken is followed.
+      let taken = match modes[0] {
+        OperationMode::Immediate => Some((cell(1) != 0) == jump_if),
+        _ => None,
+      };
+      if taken != Some(true) {
+        pending.push(next);
+      }
+      if taken != Some(false) && modes[1] == OperationMode::Immediate && cell(2) >= 0 {
+        pending.push(cell(2) as usize);
+      }
+    }
+    instructions
+  }
+}
+
+impl fmt::Display for IntCodeDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} cells changed in {} ranges",
+      self.changed_cells(),
+      self.ranges.len()
+    )?;
+    for range in &self.ranges {
+      let kind = match range.code {
+        true => "code",
+        false => "data",
+      };
+      let end = range.start + range.before.len();
+      write!(f, "\n  {}..{} ({}):", range.start, end, kind)?;
+      let before: Vec<String> = range.before.iter().map(|c| format!("{:>6}", c)).collect();
+      let after: Vec<String> = range.after.iter().map(|c| format!("{:>6}", c)).collect();
+      write!(f, "\n    before:{}", before.join(""))?;
+      write!(f, "\n    after: {}", after.join(""))?;
+    }
+    if !self.modified_instructions.is_empty() {
+      write!(f, "\n  modified instructions:")?;
+    }
+    for m in &self.modified_instructions {
+      write!(f, "\n    {:>6}: {} => {}", m.addr, m.before, m.after)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_data_ranges() {
+    let diff = IntCodeDiff::new(&[1, 0, 0, 0, 99, 5, 6, 7], &[1, 0, 0, 0, 99, 5, 8, 9, 1]);
+    assert_eq!(
+      vec![DiffRange {
+        start: 6,
+        before: vec![6, 7, 0],
+        after: vec![8, 9, 1],
+        code: false,
+      }],
+      diff.ranges
+    );
+    assert_eq!(3, diff.changed_cells());
+    assert!(diff.modified_instructions.is_empty());
+    assert!(IntCodeDiff::new(&[1, 2], &[1, 2]).is_empty());
+  }
+
+  #[test]
+  fn test_self_modified() {
+    // Overwrites the opcode of the instruction at address 4, turning it from hlt into out.
+    let program = "1101,3,1,4,99,0,99";
+    let mut cpu = IntCode::from_string(program);
+    cpu.execute().unwrap();
+    let before = IntCode::from_string(program);
+    let diff = IntCodeDiff::between(&before, &cpu);
+    assert_eq!(1, diff.ranges.len());
+    assert!(diff.ranges[0].code);
+    assert_eq!(
+      vec![ModifiedInstruction {
+        addr: 4,
+        before: "hlt".to_string(),
+        after: "out [0]".to_string(),
+      }],
+      diff.modified_instructions
+    );
+  }
+
+  #[test]
+  fn test_follows_jumps() {
+    // Jumps over data at 3 to a halt at 5.
+    let diff = IntCodeDiff::new(&[1105, 1, 5, 1, 2, 99], &[1105, 1, 5, 7, 2, 98]);
+    assert_eq!(
+      "2 cells changed in 2 ranges\n\
+       \x20 3..4 (data):\n\
+       \x20   before:     1\n\
+       \x20   after:      7\n\
+       \x20 5..6 (code):\n\
+       \x20   before:    99\n\
+       \x20   after:     98\n\
+       \x20 modified instructions:\n\
+       \x20        5: hlt => invalid opcode 98",
+      diff.to_string()
+    );
+  }
+}
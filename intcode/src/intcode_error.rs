@@ -1,15 +1,108 @@
-use super::operation::OperationMode;
+use super::operation::{Operation, OperationMode};
+use std::error::Error;
+use std::fmt;
+
+/// The number of memory cells shown on each line of an error report.
+const MEMORY_ROW: usize = 8;
 
 #[derive(Debug, Clone)]
 pub struct IntCodeError {
   pub kind: IntCodeErrorKind,
   pub opcode: isize,
   pub index: usize,
+  pub context: IntCodeErrorContext,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum IntCodeErrorKind {
   ReadOutOfRange { index: isize, mode: OperationMode },
   WriteOutOfRange { index: isize, mode: OperationMode },
   WriteInvalidOperationMode { mode: OperationMode },
+  PeekOutOfRange { index: isize },
+  PokeOutOfRange { index: isize },
+  DeviceOverlap { start: usize, end: usize },
+  InstructionLimit { limit: usize },
+  InvalidOpcode { opcode: isize },
+  InvalidMode { opcode: isize },
+  JumpOutOfRange { index: isize },
+}
+
+/// The state of the machine when an error occurred, used to report it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntCodeErrorContext {
+  pub relative_base: isize,
+  /// The address of the first cell in `memory`.
+  pub memory_start: usize,
+  /// The memory surrounding the instruction that failed.
+  pub memory: Vec<isize>,
+  /// The most recently executed instructions, oldest first, as their address and the cells they
+  /// occupied when the error occurred.
+  pub trace: Vec<(usize, Vec<isize>)>,
+}
+
+/// Errors are compared by what went wrong and where; the context only matters when reporting them.
+impl PartialEq for IntCodeError {
+  fn eq(&self, other: &Self) -> bool {
+    self.kind == other.kind && self.opcode == other.opcode && self.index == other.index
+  }
 }
+
+impl fmt::Display for IntCodeErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      IntCodeErrorKind::ReadOutOfRange { index, mode } => {
+        write!(f, "read from negative address {} in {} mode", index, mode)
+      }
+      IntCodeErrorKind::WriteOutOfRange { index, mode } => {
+        write!(f, "write to negative address {} in {} mode", index, mode)
+      }
+      IntCodeErrorKind::WriteInvalidOperationMode { mode } => {
+        write!(f, "write parameter in {} mode", mode)
+      }
+      IntCodeErrorKind::PeekOutOfRange { index } => write!(f, "peek at negative address {}", index),
+      IntCodeErrorKind::PokeOutOfRange { index } => write!(f, "poke at negative address {}", index),
+      IntCodeErrorKind::DeviceOverlap { start, end } => {
+        write!(f, "device overlaps the one mapped to {}..{}", start, end)
+      }
+      IntCodeErrorKind::InstructionLimit { limit } => {
+        write!(f, "instruction limit of {} reached", limit)
+      }
+      IntCodeErrorKind::InvalidOpcode { opcode } => write!(f, "invalid opcode {}", opcode),
+      IntCodeErrorKind::InvalidMode { opcode } => {
+        write!(f, "invalid parameter mode in opcode {}", opcode)
+      }
+      IntCodeErrorKind::JumpOutOfRange { index } => write!(f, "jump to negative address {}", index),
+    }
+  }
+}
+
+impl fmt::Display for IntCodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let offset = self.index.saturating_sub(self.context.memory_start);
+    let instruction = self.context.memory.get(offset..).unwrap_or(&[]);
+    writeln!(
+      f,
+      "{} at address {}: {}",
+      self.kind,
+      self.index,
+      Operation::disassemble(instruction)
+    )?;
+    writeln!(f, "  relative base: {}", self.context.relative_base)?;
+    writeln!(f, "  memory:")?;
+    for (row, cells) in self.context.memory.chunks(MEMORY_ROW).enumerate() {
+      let addr = self.context.memory_start + row * MEMORY_ROW;
+      let cells: Vec<String> = cells.iter().map(|c| format!("{:>6}", c)).collect();
+      writeln!(f, "    {:>6}:{}", addr, cells.join(""))?;
+    }
+    write!(f, "  recent instructions:")?;
+    if self.context.trace.is_empty() {
+      write!(f, " none")?;
+    }
+    for (addr, cells) in &self.context.trace {
+      write!(f, "\n    {:>6}: {}", addr, Operation::disassemble(cells))?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for IntCodeError {}
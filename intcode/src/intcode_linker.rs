@@ -0,0 +1,362 @@
+use super::operation::{Operation, OperationMode};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+/// The code that starts a linked program: it points the relative base at the stack, calls the
+/// entry symbol and halts when it returns.
+const STARTUP_LEN: usize = 10;
+
+/// A fragment of Intcode that can be loaded at any address.
+///
+/// Addresses in `code` are relative to the start of the module. Cells listed in `relocations` hold
+/// such addresses and have the module's load address added to them when linked. Cells listed in
+/// `imports` have the address of a symbol exported by another module added to them instead, and
+/// are never relocated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntCodeModule {
+  pub name: String,
+  pub code: Vec<isize>,
+  pub relocations: Vec<usize>,
+  /// The offset into `code` of each symbol the module defines.
+  pub exports: BTreeMap<String, usize>,
+  /// The cells that refer to symbols defined by other modules.
+  pub imports: Vec<(usize, String)>,
+}
+
+/// Combines modules into a single flat program.
+///
+/// Linked programs share one stack, addressed through the relative base, which starts just past
+/// the end of the program. Functions are called with this convention:
+///
+/// * The caller writes the return address to `[rb+0]` and the arguments to `[rb+1]`, `[rb+2]`, ...
+///   then jumps to the function.
+/// * The function reserves its frame with `109, n`, where `n` covers the return address, its
+///   arguments and any locals, so they are found at `[rb-n]` onwards.
+/// * To return, the function writes its result over its first argument, releases its frame with
+///   `109, -n` and jumps to the return address with `2106, 0, 0`. The caller then finds the
+///   result at `[rb+1]`.
+pub struct IntCodeLinker {
+  modules: Vec<IntCodeModule>,
+  entry: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedProgram {
+  pub program: Vec<isize>,
+  /// The address of every exported symbol.
+  pub symbols: BTreeMap<String, usize>,
+  /// The name and load address of each module, in the order they were added.
+  pub modules: Vec<(String, usize)>,
+  /// The initial relative base, just past the end of the program.
+  pub stack_base: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkError {
+  pub kind: LinkErrorKind,
+  /// The module the error was found in, if any.
+  pub module: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkErrorKind {
+  DuplicateSymbol { symbol: String },
+  UndefinedSymbol { symbol: String },
+  CellOutOfRange { cell: usize },
+}
+
+impl IntCodeModule {
+  pub fn new(name: &str, code: Vec<isize>) -> Self {
+    IntCodeModule {
+      name: name.to_string(),
+      code,
+      relocations: Vec::new(),
+      exports: BTreeMap::new(),
+      imports: Vec::new(),
+    }
+  }
+
+  pub fn from_string(name: &str, raw: &str) -> Self {
+    let code: Vec<isize> = raw
+      .split(",")
+      .map(|x| x.trim().parse::<isize>().unwrap())
+      .collect();
+    IntCodeModule::new(name, code)
+  }
+
+  pub fn export(mut self, symbol: &str, offset: usize) -> Self {
+    self.exports.insert(symbol.to_string(), offset);
+    self
+  }
+
+  pub fn import(mut self, cell: usize, symbol: &str) -> Self {
+    self.imports.push((cell, symbol.to_string()));
+    self
+  }
+
+  pub fn relocate(mut self, cell: usize) -> Self {
+    self.relocations.push(cell);
+    self
+  }
+
+  /// Decodes the instructions in `range` one after another and relocates every position mode
+  /// parameter and every immediate jump target. Cells that aren't valid instructions are skipped
+  /// as data. Immediate values that are addresses, such as return addresses, still need to be
+  /// added with `relocate`.
+  pub fn relocate_code(mut self, range: Range<usize>) -> Self {
+    let mut addr = range.start;
+    while addr < range.end && addr < self.code.len() {
+      let operation = match Operation::decode(self.code[addr]) {
+        Some(o) => o,
+        None => {
+          addr += 1;
+          continue;
+        }
+      };
+      let jump = matches!(
+        operation,
+        Operation::JumpIfTrue(_) | Operation::JumpIfFalse(_)
+      );
+      for (i, mode) in operation.modes().iter().enumerate() {
+        let target = jump && i == 1 && *mode == OperationMode::Immediate;
+        if *mode == OperationMode::Position || target {
+          self.relocations.push(addr + i + 1);
+        }
+      }
+      addr += operation.length();
+    }
+    self
+  }
+}
+
+impl IntCodeLinker {
+  pub fn new() -> Self {
+    IntCodeLinker {
+      modules: Vec::new(),
+      entry: "main".to_string(),
+    }
+  }
+
+  /// Adds a module. Modules are laid out in the order they are added.
+  pub fn module(mut self, module: IntCodeModule) -> Self {
+    self.modules.push(module);
+    self
+  }
+
+  /// Sets the symbol called when the program starts. Defaults to `main`.
+  pub fn entry(mut self, symbol: &str) -> Self {
+    self.entry = symbol.to_string();
+    self
+  }
+
+  pub fn link(&self) -> Result<LinkedProgram, LinkError> {
+    let mut modules = Vec::new();
+    let mut symbols = BTreeMap::new();
+    let mut addr = STARTUP_LEN;
+    for module in &self.modules {
+      modules.push((module.name.clone(), addr));
+      for (symbol, offset) in &module.exports {
+        if *offset >= module.code.len() {
+          let kind = LinkErrorKind::CellOutOfRange { cell: *offset };
+          return Err(LinkError::new(kind, Some(&module.name)));
+        }
+        if symbols.insert(symbol.clone(), addr + offset).is_some() {
+          let kind = LinkErrorKind::DuplicateSymbol {
+            symbol: symbol.clone(),
+          };
+          return Err(LinkError::new(kind, Some(&module.name)));
+        }
+      }
+      addr += module.code.len();
+    }
+    let stack_base = addr;
+
+    let entry = match symbols.get(&self.entry) {
+      Some(a) => *a as isize,
+      None => {
+        let kind = LinkErrorKind::UndefinedSymbol {
+          symbol: self.entry.clone(),
+        };
+        return Err(LinkError::new(kind, None));
+      }
+    };
+    let mut program = vec![109, stack_base as isize, 21101, 9, 0, 0, 1105, 1, entry, 99];
+    for (module, (_, base)) in self.modules.iter().zip(modules.iter()) {
+      let mut code = module.code.clone();
+      let mut imported = HashSet::new();
+      for (cell, symbol) in &module.imports {
+        let target = match symbols.get(symbol) {
+          Some(a) => *a as isize,
+          None => {
+            let kind = LinkErrorKind::UndefinedSymbol {
+              symbol: symbol.clone(),
+            };
+            return Err(LinkError::new(kind, Some(&module.name)));
+          }
+        };
+        match code.get_mut(*cell) {
+          Some(value) => *value += target,
+          None => {
+            let kind = LinkErrorKind::CellOutOfRange { cell: *cell };
+            return Err(LinkError::new(kind, Some(&module.name)));
+          }
+        }
+        imported.insert(*cell);
+      }
+      let relocations: HashSet<usize> = module.relocations.iter().cloned().collect();
+      for cell in relocations.difference(&imported) {
+        match code.get_mut(*cell) {
+          Some(value) => *value += *base as isize,
+          None => {
+            let kind = LinkErrorKind::CellOutOfRange { cell: *cell };
+            return Err(LinkError::new(kind, Some(&module.name)));
+          }
+        }
+      }
+      program.extend(code);
+    }
+
+    Ok(LinkedProgram {
+      program,
+      symbols,
+      modules,
+      stack_base,
+    })
+  }
+}
+
+impl Default for IntCodeLinker {
+  fn default() -> Self {
+    IntCodeLinker::new()
+  }
+}
+
+impl LinkError {
+  fn new(kind: LinkErrorKind, module: Option<&str>) -> Self {
+    LinkError {
+      kind,
+      module: module.map(|m| m.to_string()),
+    }
+  }
+}
+
+impl fmt::Display for LinkError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let Some(module) = &self.module {
+      write!(f, "{}: ", module)?;
+    }
+    match &self.kind {
+      LinkErrorKind::DuplicateSymbol { symbol } => {
+        write!(f, "symbol {} is already defined", symbol)
+      }
+      LinkErrorKind::UndefinedSymbol { symbol } => write!(f, "symbol {} is not defined", symbol),
+      LinkErrorKind::CellOutOfRange { cell } => write!(f, "cell {} is outside the module", cell),
+    }
+  }
+}
+
+impl Error for LinkError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::IntCode;
+
+  // Doubles its argument: reserves a frame for the return address and argument, multiplies the
+  // argument by 2 in place, then returns.
+  const DOUBLE: &str = "109,2,21202,-1,2,-1,109,-2,2106,0,0";
+
+  // Calls `double` on the value stored at offset 20 and outputs the result.
+  const MAIN: &str = "109,1,21101,13,0,0,21001,20,0,1,1105,1,0,204,1,109,-1,2106,0,0,21";
+
+  fn modules() -> (IntCodeModule, IntCodeModule) {
+    let double = IntCodeModule::from_string("math", DOUBLE).export("double", 0);
+    let main = IntCodeModule::from_string("main", MAIN)
+      .export("main", 0)
+      .relocate(3)
+      .relocate_code(0..20)
+      .import(12, "double");
+    (double, main)
+  }
+
+  #[test]
+  fn test_link() {
+    let (double, main) = modules();
+    let linked = IntCodeLinker::new()
+      .module(double)
+      .module(main)
+      .link()
+      .unwrap();
+    assert_eq!(
+      vec![("math".to_string(), 10), ("main".to_string(), 21)],
+      linked.modules
+    );
+    assert_eq!(Some(&21), linked.symbols.get("main"));
+    assert_eq!(Some(&10), linked.symbols.get("double"));
+    assert_eq!(42, linked.stack_base);
+
+    let mut cpu = IntCode::from_vec(linked.program);
+    let result = cpu.execute().unwrap();
+    assert_eq!(vec![42], result.output);
+  }
+
+  #[test]
+  fn test_relocate_code() {
+    let (_, main) = modules();
+    let mut relocations = main.relocations.clone();
+    relocations.sort();
+    // The jump target at 12 is found too, but the import takes precedence when linking.
+    assert_eq!(vec![3, 7, 12], relocations);
+  }
+
+  #[test]
+  fn test_errors() {
+    let (double, main) = modules();
+    let error = IntCodeLinker::new()
+      .module(double.clone())
+      .module(double.clone())
+      .link();
+    assert_eq!(
+      Err(LinkError::new(
+        LinkErrorKind::DuplicateSymbol {
+          symbol: "double".to_string()
+        },
+        Some("math")
+      )),
+      error
+    );
+
+    let error = IntCodeLinker::new()
+      .module(main.clone())
+      .link()
+      .unwrap_err();
+    assert_eq!("main: symbol double is not defined", error.to_string());
+
+    let error = IntCodeLinker::new()
+      .module(double.clone())
+      .link()
+      .unwrap_err();
+    assert_eq!("symbol main is not defined", error.to_string());
+
+    let error = IntCodeLinker::new()
+      .module(double.clone())
+      .module(main.clone().relocate(50))
+      .link()
+      .unwrap_err();
+    assert_eq!(LinkErrorKind::CellOutOfRange { cell: 50 }, error.kind);
+
+    let error = IntCodeLinker::new()
+      .module(double.export("far", 999))
+      .module(main)
+      .link();
+    assert_eq!(
+      Err(LinkError::new(
+        LinkErrorKind::CellOutOfRange { cell: 999 },
+        Some("math")
+      )),
+      error
+    );
+  }
+}
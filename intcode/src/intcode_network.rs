@@ -0,0 +1,441 @@
+use super::intcode::IntCode;
+use super::intcode_error::IntCodeError;
+use super::intcode_result::IntCodeResultKind;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+/// A network of Intcode machines that send each other `(dest, x, y)` packets.
+///
+/// Each machine is given its address as its first input. Machines are run one at a time in
+/// address order, each until it waits for input; a full pass over every machine is a round. A
+/// machine that reads from an empty queue gets -1. Every packet sent is passed to the router,
+/// which decides where it goes, so a run is fully deterministic.
+pub struct IntCodeNetwork<R: IntCodeRouter> {
+  machines: Vec<Machine>,
+  router: R,
+  round: usize,
+  idle_rounds: usize,
+  idle_threshold: usize,
+  log: Option<Vec<PacketLogEntry>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+  /// The address of the sender. Packets sent by a router use the router's own address.
+  pub src: isize,
+  pub dest: isize,
+  pub x: isize,
+  pub y: isize,
+}
+
+/// What happens to a packet after it is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+  /// Queues the packet for the machine at this address.
+  Deliver(usize),
+  /// The router has taken the packet for itself.
+  Keep,
+  Drop,
+  /// Stops the network immediately.
+  Stop,
+}
+
+/// What a router does when the network goes idle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdleAction {
+  /// Sends these packets, routing them like any others.
+  Send(Vec<Packet>),
+  Wait,
+  Stop,
+}
+
+/// Decides where packets go and what happens when the network goes idle.
+pub trait IntCodeRouter {
+  /// Called for every packet sent, in the order they are sent. By default packets are delivered
+  /// to the machine they are addressed to, or dropped if there is no such machine.
+  fn route(&mut self, packet: &Packet, machines: usize) -> Route {
+    match packet.dest >= 0 && (packet.dest as usize) < machines {
+      true => Route::Deliver(packet.dest as usize),
+      false => Route::Drop,
+    }
+  }
+
+  /// Called at the end of every idle round: one where every queue was empty and no machine sent
+  /// anything.
+  fn idle(&mut self) -> IdleAction {
+    IdleAction::Wait
+  }
+}
+
+/// Delivers packets to the machine they are addressed to and drops everything else.
+pub struct DirectRouter;
+
+impl IntCodeRouter for DirectRouter {}
+
+/// A monitor that takes every packet sent to its address and, when the network goes idle, sends
+/// the last one it received to machine 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nat {
+  address: isize,
+  stop_on_first: bool,
+  first: Option<Packet>,
+  last: Option<Packet>,
+  last_sent_y: Option<isize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketLogEntry {
+  pub round: usize,
+  pub packet: Packet,
+  pub route: Route,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkOutcome {
+  /// The router stopped the network during `round`.
+  Stopped { round: usize },
+  /// Every machine halted.
+  Halted { round: usize },
+  /// The round limit passed to `run` was reached.
+  RoundLimit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntCodeNetworkError {
+  /// The address of the machine that failed.
+  pub machine: usize,
+  pub error: IntCodeError,
+}
+
+struct Machine {
+  cpu: IntCode,
+  queue: VecDeque<(isize, isize)>,
+  /// Output that doesn't yet make up a whole packet.
+  partial: Vec<isize>,
+  halted: bool,
+}
+
+impl<R: IntCodeRouter> IntCodeNetwork<R> {
+  /// Creates `size` copies of `program` with addresses 0 to `size - 1`.
+  pub fn new(program: &IntCode, size: usize, router: R) -> Self {
+    let machines = (0..size)
+      .map(|address| {
+        let mut cpu = program.clone();
+        cpu.input(&vec![address as isize]);
+        Machine {
+          cpu,
+          queue: VecDeque::new(),
+          partial: Vec::new(),
+          halted: false,
+        }
+      })
+      .collect();
+    IntCodeNetwork {
+      machines,
+      router,
+      round: 0,
+      idle_rounds: 0,
+      idle_threshold: 1,
+      log: None,
+    }
+  }
+
+  /// Records every packet sent, see `log`.
+  pub fn log_packets(mut self) -> Self {
+    self.log = Some(Vec::new());
+    self
+  }
+
+  /// Sets how many idle rounds in a row it takes for the router to be told the network is idle.
+  /// Defaults to 1; raise it for machines that need several empty reads before they send.
+  pub fn idle_threshold(mut self, rounds: usize) -> Self {
+    self.idle_threshold = rounds.max(1);
+    self
+  }
+
+  pub fn router(&self) -> &R {
+    &self.router
+  }
+
+  pub fn machine(&self, address: usize) -> &IntCode {
+    &self.machines[address].cpu
+  }
+
+  /// The number of rounds run so far.
+  pub fn round(&self) -> usize {
+    self.round
+  }
+
+  /// Every packet sent so far, if logging is enabled.
+  pub fn log(&self) -> Option<&[PacketLogEntry]> {
+    self.log.as_deref()
+  }
+
+  /// Runs rounds until the router stops the network, every machine halts, or `max_rounds` more
+  /// rounds have been run.
+  pub fn run(&mut self, max_rounds: usize) -> Result<NetworkOutcome, IntCodeNetworkError> {
+    for _ in 0..max_rounds {
+      if let Some(outcome) = self.step()? {
+        return Ok(outcome);
+      }
+    }
+    Ok(NetworkOutcome::RoundLimit)
+  }
+
+  /// Runs a single round, returning how the network stopped if it did.
+  pub fn step(&mut self) -> Result<Option<NetworkOutcome>, IntCodeNetworkError> {
+    self.round += 1;
+    let mut idle = true;
+    for address in 0..self.machines.len() {
+      let machine = &mut self.machines[address];
+      if machine.halted {
+        continue;
+      }
+      match machine.queue.is_empty() {
+        true => machine.cpu.input(&vec![-1]),
+        false => {
+          idle = false;
+          let input: Vec<isize> = machine
+            .queue
+            .drain(..)
+            .flat_map(|(x, y)| vec![x, y])
+            .collect();
+          machine.cpu.input(&input);
+        }
+      }
+      let result = machine.cpu.execute().map_err(|error| IntCodeNetworkError {
+        machine: address,
+        error,
+      })?;
+      machine.halted = result.kind == IntCodeResultKind::Halt;
+      machine.partial.extend(result.output);
+
+      let complete = machine.partial.len() - machine.partial.len() % 3;
+      let packets: Vec<Packet> = machine
+        .partial
+        .drain(..complete)
+        .collect::<Vec<isize>>()
+        .chunks(3)
+        .map(|p| Packet {
+          src: address as isize,
+          dest: p[0],
+          x: p[1],
+          y: p[2],
+        })
+        .collect();
+      for packet in packets {
+        idle = false;
+        if self.send(packet) {
+          return Ok(Some(NetworkOutcome::Stopped { round: self.round }));
+        }
+      }
+    }
+
+    if self.machines.iter().all(|m| m.halted) {
+      return Ok(Some(NetworkOutcome::Halted { round: self.round }));
+    }
+    self.idle_rounds = if idle { self.idle_rounds + 1 } else { 0 };
+    if self.idle_rounds >= self.idle_threshold {
+      match self.router.idle() {
+        IdleAction::Send(packets) => {
+          for packet in packets {
+            if self.send(packet) {
+              return Ok(Some(NetworkOutcome::Stopped { round: self.round }));
+            }
+          }
+        }
+        IdleAction::Wait => {}
+        IdleAction::Stop => return Ok(Some(NetworkOutcome::Stopped { round: self.round })),
+      }
+    }
+    Ok(None)
+  }
+
+  /// Routes a packet, returning true if the router stopped the network.
+  fn send(&mut self, packet: Packet) -> bool {
+    let route = self.router.route(&packet, self.machines.len());
+    if let Some(log) = &mut self.log {
+      log.push(PacketLogEntry {
+        round: self.round,
+        packet,
+        route,
+      });
+    }
+    match route {
+      Route::Deliver(address) => {
+        if let Some(machine) = self.machines.get_mut(address) {
+          machine.queue.push_back((packet.x, packet.y));
+        }
+        false
+      }
+      Route::Keep | Route::Drop => false,
+      Route::Stop => true,
+    }
+  }
+}
+
+impl Nat {
+  pub fn new(address: isize) -> Self {
+    Nat {
+      address,
+      stop_on_first: false,
+      first: None,
+      last: None,
+      last_sent_y: None,
+    }
+  }
+
+  /// Stops the network as soon as the first packet addressed to the NAT is sent.
+  pub fn stop_on_first(mut self) -> Self {
+    self.stop_on_first = true;
+    self
+  }
+
+  /// The first packet sent to the NAT.
+  pub fn first(&self) -> Option<Packet> {
+    self.first
+  }
+
+  /// The most recent packet sent to the NAT.
+  pub fn last(&self) -> Option<Packet> {
+    self.last
+  }
+
+  /// The `y` value of the last packet the NAT sent to machine 0.
+  pub fn last_sent_y(&self) -> Option<isize> {
+    self.last_sent_y
+  }
+}
+
+/// The NAT stops the network when it is about to send machine 0 the same `y` value twice in a
+/// row.
+impl IntCodeRouter for Nat {
+  fn route(&mut self, packet: &Packet, machines: usize) -> Route {
+    if packet.dest != self.address {
+      return DirectRouter.route(packet, machines);
+    }
+    self.first = self.first.or(Some(*packet));
+    self.last = Some(*packet);
+    match self.stop_on_first {
+      true => Route::Stop,
+      false => Route::Keep,
+    }
+  }
+
+  fn idle(&mut self) -> IdleAction {
+    let last = match self.last {
+      Some(p) => p,
+      None => return IdleAction::Wait,
+    };
+    if self.last_sent_y == Some(last.y) {
+      return IdleAction::Stop;
+    }
+    self.last_sent_y = Some(last.y);
+    IdleAction::Send(vec![Packet {
+      src: self.address,
+      dest: 0,
+      x: last.x,
+      y: last.y,
+    }])
+  }
+}
+
+impl fmt::Display for IntCodeNetworkError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "machine {}: {}", self.machine, self.error)
+  }
+}
+
+impl Error for IntCodeNetworkError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Sends `(addr + 1, addr, 10 * addr)`, then forwards every packet it receives to `addr + 1`
+  /// with `increment` added to `y`.
+  fn relay(increment: isize) -> IntCode {
+    IntCode::from_vec(vec![
+      3, 100, 1001, 100, 1, 101, 4, 101, 4, 100, 1002, 100, 10, 102, 4, 102, 3, 103, 1008, 103, -1,
+      104, 1005, 104, 16, 3, 105, 4, 101, 4, 103, 1001, 105, increment, 105, 4, 105, 1105, 1, 16,
+    ])
+  }
+
+  #[test]
+  fn test_direct() {
+    let mut network = IntCodeNetwork::new(&relay(1), 3, DirectRouter).log_packets();
+    assert_eq!(Ok(None), network.step());
+    let sent: Vec<(isize, isize, isize, isize)> = network
+      .log()
+      .unwrap()
+      .iter()
+      .map(|e| (e.packet.src, e.packet.dest, e.packet.x, e.packet.y))
+      .collect();
+    assert_eq!(
+      vec![
+        (0, 1, 0, 0),
+        (1, 2, 1, 10),
+        (1, 2, 0, 1),
+        (2, 3, 2, 20),
+        (2, 3, 1, 11),
+        (2, 3, 0, 2),
+      ],
+      sent
+    );
+    assert_eq!(Route::Drop, network.log().unwrap()[5].route);
+    assert_eq!(Ok(NetworkOutcome::RoundLimit), network.run(5));
+    assert_eq!(6, network.round());
+  }
+
+  #[test]
+  fn test_nat_first() {
+    let nat = Nat::new(3).stop_on_first();
+    let mut network = IntCodeNetwork::new(&relay(1), 3, nat);
+    assert_eq!(Ok(NetworkOutcome::Stopped { round: 1 }), network.run(10));
+    assert_eq!(Some(20), network.router().first().map(|p| p.y));
+  }
+
+  #[test]
+  fn test_nat_repeat() {
+    // Round 1 primes the relays, round 2 is idle, round 3 relays the NAT's packet and round 4 is
+    // idle again with the same value to send.
+    let mut network = IntCodeNetwork::new(&relay(0), 3, Nat::new(3)).log_packets();
+    assert_eq!(Ok(NetworkOutcome::Stopped { round: 4 }), network.run(10));
+    assert_eq!(Some(0), network.router().last_sent_y());
+    let from_nat = network.log().unwrap().iter().filter(|e| e.packet.src == 3);
+    assert_eq!(1, from_nat.count());
+
+    // With the relays incrementing `y`, the NAT never sends the same value twice.
+    let mut network = IntCodeNetwork::new(&relay(1), 3, Nat::new(3));
+    assert_eq!(Ok(NetworkOutcome::RoundLimit), network.run(10));
+    assert_eq!(Some(14), network.router().last_sent_y());
+  }
+
+  #[test]
+  fn test_custom_router() {
+    // Sends every packet back to machine 0 and stops at the first value over 50.
+    struct Loopback;
+    impl IntCodeRouter for Loopback {
+      fn route(&mut self, packet: &Packet, _: usize) -> Route {
+        match packet.y > 50 {
+          true => Route::Stop,
+          false => Route::Deliver(0),
+        }
+      }
+    }
+    let mut network = IntCodeNetwork::new(&relay(7), 1, Loopback);
+    assert_eq!(Ok(NetworkOutcome::Stopped { round: 9 }), network.run(20));
+  }
+
+  #[test]
+  fn test_halted_and_errors() {
+    let mut network = IntCodeNetwork::new(&IntCode::from_string("3,0,99"), 2, DirectRouter);
+    assert_eq!(Ok(NetworkOutcome::Halted { round: 1 }), network.run(10));
+
+    let mut network = IntCodeNetwork::new(&IntCode::from_string("3,0,4,-1"), 2, DirectRouter);
+    let error = network.run(10).unwrap_err();
+    assert_eq!(0, error.machine);
+    assert_eq!(2, error.error.index);
+  }
+}
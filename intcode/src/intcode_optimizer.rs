@@ -0,0 +1,671 @@
+use super::intcode::IntCode;
+use super::intcode_result::IntCodeResultKind;
+use super::operation::{Operation, OperationMode};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Rewrites an Intcode program into a smaller, equivalent one by folding constant arithmetic,
+/// short-circuiting chains of jumps and dropping unreachable code and unused data.
+///
+/// Only programs whose control flow and memory accesses can be fully determined up front are
+/// supported: every reachable instruction must be decodable, jump targets must be constant, no
+/// instruction may overwrite reachable code, and relative mode may not be used. Anything else is
+/// reported as an `OptimizeError` rather than risk changing the program's behavior.
+pub struct IntCodeOptimizer {
+  program: Vec<isize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizedProgram {
+  pub program: Vec<isize>,
+  /// The new address of each cell in the original program, or `None` if it was removed.
+  pub address_map: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizeError {
+  pub kind: OptimizeErrorKind,
+  /// The address of the instruction that could not be optimized.
+  pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptimizeErrorKind {
+  InvalidOpcode { opcode: isize },
+  InvalidAddress { address: isize },
+  RelativeMode,
+  SelfModifying { address: usize },
+  DynamicJump,
+  OverlappingInstructions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+  Immediate(isize),
+  Address(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arithmetic {
+  Add,
+  Multiply,
+  LessThan,
+  Equals,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+  Arithmetic {
+    op: Arithmetic,
+    left: Operand,
+    right: Operand,
+    dst: usize,
+  },
+  Input {
+    dst: usize,
+  },
+  Output {
+    src: Operand,
+  },
+  /// Jumps to `target` when `cond` is non-zero, or zero if `if_true` is false.
+  Jump {
+    if_true: bool,
+    cond: Operand,
+    target: usize,
+  },
+  Halt,
+  /// An instruction that has been optimized away; execution continues at `next`.
+  Removed {
+    next: usize,
+  },
+}
+
+impl IntCodeOptimizer {
+  pub fn new(program: Vec<isize>) -> Self {
+    IntCodeOptimizer { program }
+  }
+
+  pub fn from_string(raw: &str) -> Self {
+    let program: Vec<isize> = raw
+      .split(",")
+      .map(|x| x.parse::<isize>().unwrap())
+      .collect();
+    IntCodeOptimizer::new(program)
+  }
+
+  pub fn optimize(&self) -> Result<OptimizedProgram, OptimizeError> {
+    let operations = self.decode()?;
+    let written = self.written_cells(&operations)?;
+    let mut nodes = self.lower(&operations, &written)?;
+    let mut lengths = HashMap::new();
+    for (addr, operation) in &operations {
+      lengths.insert(*addr, operation.length());
+    }
+    IntCodeOptimizer::simplify(&mut nodes, &lengths);
+    Ok(self.layout(&nodes, &lengths))
+  }
+
+  /// Decodes every instruction reachable from address 0.
+  fn decode(&self) -> Result<BTreeMap<usize, Operation>, OptimizeError> {
+    let mut operations = BTreeMap::new();
+    let mut pending = vec![0];
+    while let Some(addr) = pending.pop() {
+      if operations.contains_key(&addr) {
+        continue;
+      }
+      let opcode = self.cell(addr);
+      let operation = match Operation::decode(opcode) {
+        Some(o) => o,
+        None => {
+          return Err(OptimizeError::new(
+            OptimizeErrorKind::InvalidOpcode { opcode },
+            addr,
+          ))
+        }
+      };
+      if operation.modes().contains(&OperationMode::Relative) {
+        return Err(OptimizeError::new(OptimizeErrorKind::RelativeMode, addr));
+      }
+      let next = addr + operation.length();
+      match &operation {
+        Operation::Halt => {}
+        Operation::RelBaseOffset(_) => {
+          return Err(OptimizeError::new(OptimizeErrorKind::RelativeMode, addr))
+        }
+        Operation::JumpIfTrue(m) | Operation::JumpIfFalse(m) => {
+          let target = match m[1] {
+            OperationMode::Immediate => self.cell(addr + 2),
+            _ => self.cell(self.address(addr, 2)?),
+          };
+          if target < 0 {
+            let kind = OptimizeErrorKind::InvalidAddress { address: target };
+            return Err(OptimizeError::new(kind, addr));
+          }
+          // A jump on an immediate condition only ever goes one way.
+          let taken = match m[0] {
+            OperationMode::Immediate => Some(self.cell(addr + 1) != 0),
+            _ => None,
+          };
+          let if_true = matches!(operation, Operation::JumpIfTrue(_));
+          if taken != Some(!if_true) {
+            pending.push(target as usize);
+          }
+          if taken != Some(if_true) {
+            pending.push(next);
+          }
+        }
+        _ => pending.push(next),
+      }
+      operations.insert(addr, operation);
+    }
+
+    let mut end = 0;
+    for (addr, operation) in &operations {
+      if *addr < end {
+        return Err(OptimizeError::new(
+          OptimizeErrorKind::OverlappingInstructions,
+          *addr,
+        ));
+      }
+      end = addr + operation.length();
+    }
+    Ok(operations)
+  }
+
+  /// Finds every cell that may be written to, checking that none of them hold code or jump
+  /// targets.
+  fn written_cells(
+    &self,
+    operations: &BTreeMap<usize, Operation>,
+  ) -> Result<HashSet<usize>, OptimizeError> {
+    let mut written = HashSet::new();
+    for (addr, operation) in operations {
+      let param = match operation {
+        Operation::Add(_)
+        | Operation::Multiply(_)
+        | Operation::LessThan(_)
+        | Operation::Equals(_) => 3,
+        Operation::Input(_) => 1,
+        _ => continue,
+      };
+      let dst = self.address(*addr, param)?;
+      if operations
+        .range(..=dst)
+        .next_back()
+        .filter(|(start, o)| dst < *start + o.length())
+        .is_some()
+      {
+        let kind = OptimizeErrorKind::SelfModifying { address: dst };
+        return Err(OptimizeError::new(kind, *addr));
+      }
+      written.insert(dst);
+    }
+    for (addr, operation) in operations {
+      if let Operation::JumpIfTrue(m) | Operation::JumpIfFalse(m) = operation {
+        if m[1] == OperationMode::Position && written.contains(&self.address(*addr, 2)?) {
+          return Err(OptimizeError::new(OptimizeErrorKind::DynamicJump, *addr));
+        }
+      }
+    }
+    Ok(written)
+  }
+
+  /// Converts each instruction into a `Node`, replacing reads of cells that are never written with
+  /// their values and folding any arithmetic or jump whose inputs are all constant.
+  fn lower(
+    &self,
+    operations: &BTreeMap<usize, Operation>,
+    written: &HashSet<usize>,
+  ) -> Result<BTreeMap<usize, Node>, OptimizeError> {
+    let mut nodes = BTreeMap::new();
+    for (addr, operation) in operations {
+      let addr = *addr;
+      let operand = |param: usize, mode: &OperationMode| -> Result<Operand, OptimizeError> {
+        if *mode == OperationMode::Immediate {
+          return Ok(Operand::Immediate(self.cell(addr + param)));
+        }
+        let src = self.address(addr, param)?;
+        match written.contains(&src) {
+          true => Ok(Operand::Address(src)),
+          false => Ok(Operand::Immediate(self.cell(src))),
+        }
+      };
+      let node = match operation {
+        Operation::Add(m)
+        | Operation::Multiply(m)
+        | Operation::LessThan(m)
+        | Operation::Equals(m) => {
+          let op = match operation {
+            Operation::Add(_) => Arithmetic::Add,
+            Operation::Multiply(_) => Arithmetic::Multiply,
+            Operation::LessThan(_) => Arithmetic::LessThan,
+            _ => Arithmetic::Equals,
+          };
+          let left = operand(1, &m[0])?;
+          let right = operand(2, &m[1])?;
+          let dst = self.address(addr, 3)?;
+          match (left, right) {
+            (Operand::Immediate(a), Operand::Immediate(b)) => Node::Arithmetic {
+              op: Arithmetic::Add,
+              left: Operand::Immediate(op.apply(a, b)),
+              right: Operand::Immediate(0),
+              dst,
+            },
+            _ => Node::Arithmetic {
+              op,
+              left,
+              right,
+              dst,
+            },
+          }
+        }
+        Operation::Input(_) => Node::Input {
+          dst: self.address(addr, 1)?,
+        },
+        Operation::Output(m) => Node::Output {
+          src: operand(1, &m[0])?,
+        },
+        Operation::JumpIfTrue(m) | Operation::JumpIfFalse(m) => {
+          let if_true = matches!(operation, Operation::JumpIfTrue(_));
+          let target = match operand(2, &m[1])? {
+            Operand::Immediate(t) => t as usize,
+            Operand::Address(_) => unreachable!("jump targets are checked by written_cells"),
+          };
+          match operand(1, &m[0])? {
+            Operand::Immediate(c) if (c != 0) != if_true => Node::Removed {
+              next: addr + operation.length(),
+            },
+            Operand::Immediate(_) => Node::Jump {
+              if_true: true,
+              cond: Operand::Immediate(1),
+              target,
+            },
+            cond => Node::Jump {
+              if_true,
+              cond,
+              target,
+            },
+          }
+        }
+        Operation::Halt => Node::Halt,
+        Operation::RelBaseOffset(_) => unreachable!("relative mode is rejected by decode"),
+      };
+      nodes.insert(addr, node);
+    }
+    Ok(nodes)
+  }
+
+  /// Retargets jumps past removed instructions and unconditional jumps, then removes any jump
+  /// that would land on the instruction that follows it anyway, until nothing changes.
+  fn simplify(nodes: &mut BTreeMap<usize, Node>, lengths: &HashMap<usize, usize>) {
+    loop {
+      let mut changed = false;
+      let jumps: Vec<usize> = nodes
+        .iter()
+        .filter(|(_, n)| matches!(n, Node::Jump { .. }))
+        .map(|(a, _)| *a)
+        .collect();
+      for addr in jumps {
+        let resolved = match &nodes[&addr] {
+          Node::Jump { target, .. } => IntCodeOptimizer::resolve(nodes, *target),
+          _ => continue,
+        };
+        if let Some(Node::Jump { target, .. }) = nodes.get_mut(&addr) {
+          if *target != resolved {
+            *target = resolved;
+            changed = true;
+          }
+        }
+      }
+
+      let live = IntCodeOptimizer::live(nodes, lengths);
+      for (i, addr) in live.iter().enumerate() {
+        if let Node::Jump { target, .. } = &nodes[addr] {
+          if live.get(i + 1) == Some(target) {
+            let next = *target;
+            nodes.insert(*addr, Node::Removed { next });
+            changed = true;
+            break;
+          }
+        }
+      }
+
+      if !changed {
+        return;
+      }
+    }
+  }
+
+  fn skip_removed(nodes: &BTreeMap<usize, Node>, mut addr: usize) -> usize {
+    while let Some(Node::Removed { next }) = nodes.get(&addr) {
+      addr = *next;
+    }
+    addr
+  }
+
+  /// Finds where execution actually ends up after jumping to `addr`.
+  fn resolve(nodes: &BTreeMap<usize, Node>, addr: usize) -> usize {
+    let mut seen = HashSet::new();
+    let mut addr = IntCodeOptimizer::skip_removed(nodes, addr);
+    while seen.insert(addr) {
+      match nodes.get(&addr) {
+        Some(Node::Jump {
+          cond: Operand::Immediate(_),
+          target,
+          ..
+        }) => addr = IntCodeOptimizer::skip_removed(nodes, *target),
+        _ => break,
+      }
+    }
+    addr
+  }
+
+  /// The addresses of every node reachable from the start of the program, in address order.
+  fn live(nodes: &BTreeMap<usize, Node>, lengths: &HashMap<usize, usize>) -> Vec<usize> {
+    let mut live = BTreeSet::new();
+    let mut pending = vec![IntCodeOptimizer::skip_removed(nodes, 0)];
+    while let Some(addr) = pending.pop() {
+      if !live.insert(addr) {
+        continue;
+      }
+      let next = IntCodeOptimizer::skip_removed(nodes, addr + lengths[&addr]);
+      match &nodes[&addr] {
+        Node::Halt => {}
+        Node::Jump { cond, target, .. } => {
+          pending.push(IntCodeOptimizer::resolve(nodes, *target));
+          if let Operand::Address(_) = cond {
+            pending.push(next);
+          }
+        }
+        _ => pending.push(next),
+      }
+    }
+    live.into_iter().collect()
+  }
+
+  fn layout(
+    &self,
+    nodes: &BTreeMap<usize, Node>,
+    lengths: &HashMap<usize, usize>,
+  ) -> OptimizedProgram {
+    let live = IntCodeOptimizer::live(nodes, lengths);
+    let mut code_map = HashMap::new();
+    let mut code_len = 0;
+    let mut data = BTreeSet::new();
+    for addr in &live {
+      code_map.insert(*addr, code_len);
+      code_len += lengths[addr];
+      match &nodes[addr] {
+        Node::Arithmetic {
+          left, right, dst, ..
+        } => {
+          data.extend(left.address());
+          data.extend(right.address());
+          data.insert(*dst);
+        }
+        Node::Input { dst } => {
+          data.insert(*dst);
+        }
+        Node::Output { src } => data.extend(src.address()),
+        Node::Jump { cond, .. } => data.extend(cond.address()),
+        Node::Halt | Node::Removed { .. } => {}
+      }
+    }
+
+    let old_len = self.program.len();
+    let in_program: Vec<usize> = data.iter().cloned().filter(|a| *a < old_len).collect();
+    let new_len = code_len + in_program.len();
+    let mut data_map = HashMap::new();
+    for (i, addr) in in_program.iter().enumerate() {
+      data_map.insert(*addr, code_len + i);
+    }
+    let map_data = |addr: usize| match data_map.get(&addr) {
+      Some(a) => *a,
+      None => addr - old_len + new_len,
+    };
+    let encode = |operand: &Operand| match operand {
+      Operand::Immediate(v) => (1, *v),
+      Operand::Address(a) => (0, map_data(*a) as isize),
+    };
+
+    let mut program = Vec::with_capacity(new_len);
+    for addr in &live {
+      match &nodes[addr] {
+        Node::Arithmetic {
+          op,
+          left,
+          right,
+          dst,
+        } => {
+          let (lm, l) = encode(left);
+          let (rm, r) = encode(right);
+          program.extend(&[
+            op.opcode() + lm * 100 + rm * 1000,
+            l,
+            r,
+            map_data(*dst) as isize,
+          ]);
+        }
+        Node::Input { dst } => program.extend(&[3, map_data(*dst) as isize]),
+        Node::Output { src } => {
+          let (m, v) = encode(src);
+          program.extend(&[4 + m * 100, v]);
+        }
+        Node::Jump {
+          if_true,
+          cond,
+          target,
+        } => {
+          let (m, c) = encode(cond);
+          let op = if *if_true { 5 } else { 6 };
+          program.extend(&[op + m * 100 + 1000, c, code_map[target] as isize]);
+        }
+        Node::Halt => program.push(99),
+        Node::Removed { .. } => {}
+      }
+    }
+    program.extend(in_program.iter().map(|a| self.program[*a]));
+
+    let mut address_map = vec![None; old_len];
+    for addr in &live {
+      for offset in 0..lengths[addr] {
+        if let Some(cell) = address_map.get_mut(addr + offset) {
+          *cell = Some(code_map[addr] + offset);
+        }
+      }
+    }
+    for (addr, new) in &data_map {
+      address_map[*addr] = Some(*new);
+    }
+    OptimizedProgram {
+      program,
+      address_map,
+    }
+  }
+
+  fn cell(&self, addr: usize) -> isize {
+    *self.program.get(addr).unwrap_or(&0)
+  }
+
+  /// Reads parameter `param` of the instruction at `addr` as an address.
+  fn address(&self, addr: usize, param: usize) -> Result<usize, OptimizeError> {
+    let address = self.cell(addr + param);
+    match address < 0 {
+      true => Err(OptimizeError::new(
+        OptimizeErrorKind::InvalidAddress { address },
+        addr,
+      )),
+      false => Ok(address as usize),
+    }
+  }
+}
+
+impl OptimizedProgram {
+  /// Runs both programs on each set of inputs, returning the first set of inputs for which they
+  /// produce different outputs or one halts where the other waits for input. Final memory isn't
+  /// compared, as the optimizer rewrites instructions and the addresses they use. A program that
+  /// fails counts as different unless both fail.
+  pub fn verify(&self, original: &[isize], inputs: &[Vec<isize>]) -> Result<(), Vec<isize>> {
+    for input in inputs {
+      if OptimizedProgram::run(original, input) != OptimizedProgram::run(&self.program, input) {
+        return Err(input.clone());
+      }
+    }
+    Ok(())
+  }
+
+  fn run(program: &[isize], input: &[isize]) -> Option<(IntCodeResultKind, Vec<isize>)> {
+    let mut cpu = IntCode::from_vec(program.to_vec());
+    cpu.input(&input.to_vec());
+    let result = cpu.execute().ok()?;
+    Some((result.kind, result.output))
+  }
+}
+
+impl OptimizeError {
+  fn new(kind: OptimizeErrorKind, index: usize) -> Self {
+    OptimizeError { kind, index }
+  }
+}
+
+impl Operand {
+  fn address(&self) -> Option<usize> {
+    match self {
+      Operand::Immediate(_) => None,
+      Operand::Address(a) => Some(*a),
+    }
+  }
+}
+
+impl Arithmetic {
+  fn apply(&self, a: isize, b: isize) -> isize {
+    match self {
+      Arithmetic::Add => a + b,
+      Arithmetic::Multiply => a * b,
+      Arithmetic::LessThan => (a < b) as isize,
+      Arithmetic::Equals => (a == b) as isize,
+    }
+  }
+
+  fn opcode(&self) -> isize {
+    match self {
+      Arithmetic::Add => 1,
+      Arithmetic::Multiply => 2,
+      Arithmetic::LessThan => 7,
+      Arithmetic::Equals => 8,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 0: m32 = m30 + m31, both constant
+  // 4: jump to 10, which jumps to 16
+  // 7: dead code
+  // 16: m33 = input
+  // 18: jump if m34, which is never written and so never taken
+  // 21: m35 = m32 + m33
+  // 25: output m35
+  const PROGRAM: &str =
+    "1,30,31,32,1105,1,10,104,666,99,1106,0,16,104,777,99,3,33,1005,34,25,1,32,33,35,4,35,99,0,0,5,7,0,0,0,0";
+
+  fn parse(raw: &str) -> Vec<isize> {
+    raw
+      .split(",")
+      .map(|x| x.parse::<isize>().unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_optimize() {
+    let optimized = IntCodeOptimizer::from_string(PROGRAM).optimize().unwrap();
+    assert_eq!(
+      vec![1101, 12, 0, 13, 3, 14, 1, 13, 14, 15, 4, 15, 99, 0, 0, 0],
+      optimized.program
+    );
+    assert_eq!(Some(0), optimized.address_map[0]);
+    assert_eq!(None, optimized.address_map[4]);
+    assert_eq!(None, optimized.address_map[7]);
+    assert_eq!(Some(4), optimized.address_map[16]);
+    assert_eq!(None, optimized.address_map[18]);
+    assert_eq!(Some(12), optimized.address_map[27]);
+    assert_eq!(None, optimized.address_map[30]);
+    assert_eq!(Some(15), optimized.address_map[35]);
+    let inputs = vec![vec![0], vec![5], vec![-30], vec![]];
+    assert_eq!(Ok(()), optimized.verify(&parse(PROGRAM), &inputs));
+  }
+
+  #[test]
+  fn test_jumps() {
+    let program = "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9";
+    let optimized = IntCodeOptimizer::from_string(program).optimize().unwrap();
+    let inputs = vec![vec![0], vec![1], vec![-3]];
+    assert_eq!(Ok(()), optimized.verify(&parse(program), &inputs));
+
+    // The immediate mode version reads its input into its own jump instruction.
+    assert_eq!(
+      Err(OptimizeError {
+        kind: OptimizeErrorKind::SelfModifying { address: 3 },
+        index: 0,
+      }),
+      IntCodeOptimizer::from_string("3,3,1105,-1,9,1101,0,0,12,4,12,99,1").optimize()
+    );
+  }
+
+  #[test]
+  fn test_compare_to_8() {
+    let program = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+    let optimized = IntCodeOptimizer::from_string(program).optimize().unwrap();
+    assert!(optimized.program.len() < parse(program).len());
+    let inputs: Vec<Vec<isize>> = (0..16).map(|i| vec![i]).collect();
+    assert_eq!(Ok(()), optimized.verify(&parse(program), &inputs));
+  }
+
+  #[test]
+  fn test_verify_mismatch() {
+    let optimized = OptimizedProgram {
+      program: vec![104, 1, 99],
+      address_map: vec![],
+    };
+    assert_eq!(
+      Err(vec![2]),
+      optimized.verify(&[3, 0, 4, 0, 99], &[vec![1], vec![2]])
+    );
+  }
+
+  #[test]
+  fn test_unsupported() {
+    let cases = vec![
+      (
+        "1,0,0,0,99",
+        OptimizeErrorKind::SelfModifying { address: 0 },
+        0,
+      ),
+      ("109,1,99", OptimizeErrorKind::RelativeMode, 0),
+      ("204,1,99", OptimizeErrorKind::RelativeMode, 0),
+      ("3,7,6,8,7,99,0,0,0", OptimizeErrorKind::DynamicJump, 2),
+      (
+        "1105,1,2,99,99,99,99",
+        OptimizeErrorKind::OverlappingInstructions,
+        2,
+      ),
+      (
+        "104,0,42",
+        OptimizeErrorKind::InvalidOpcode { opcode: 42 },
+        2,
+      ),
+      (
+        "4,-1,99",
+        OptimizeErrorKind::InvalidAddress { address: -1 },
+        0,
+      ),
+    ];
+    for (program, kind, index) in cases {
+      assert_eq!(
+        Err(OptimizeError { kind, index }),
+        IntCodeOptimizer::from_string(program).optimize()
+      );
+    }
+  }
+}
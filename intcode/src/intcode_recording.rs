@@ -0,0 +1,352 @@
+use super::intcode::IntCode;
+use super::intcode_error::IntCodeError;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode recording v1";
+
+/// Every input an `IntCode` machine consumed and output it produced, in order, so a session can
+/// be saved and replayed exactly. Start one with `IntCode::start_recording`.
+///
+/// Recordings are saved as text:
+///
+/// ```text
+/// intcode recording v1
+/// program 5e1a3c0b9d2f4e61
+/// i 12 -1
+/// d 30 4
+/// o 40 7
+/// ```
+///
+/// where each `i` (input), `d` (device read) or `o` (output) line gives the number of
+/// instructions executed since recording started and the value read or written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntCodeRecording {
+  /// A hash of memory when recording started, used to check a replay runs the same program.
+  pub fingerprint: u64,
+  pub events: Vec<RecordedEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordedEvent {
+  Input {
+    instruction: usize,
+    value: isize,
+  },
+  Output {
+    instruction: usize,
+    value: isize,
+  },
+  /// A value an instruction read from a mapped device.
+  DeviceRead {
+    instruction: usize,
+    value: isize,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayError {
+  pub kind: ReplayErrorKind,
+  /// The index of the first event that didn't match.
+  pub event: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayErrorKind {
+  ProgramMismatch {
+    expected: u64,
+    found: u64,
+  },
+  /// The replay produced a different event, or ran out of events, where `None`.
+  Diverged {
+    expected: Option<RecordedEvent>,
+    found: Option<RecordedEvent>,
+  },
+  Failed {
+    error: IntCodeError,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseRecordingError {
+  pub line: usize,
+}
+
+impl IntCodeRecording {
+  pub fn new(memory: &[isize]) -> Self {
+    IntCodeRecording {
+      fingerprint: IntCodeRecording::fingerprint(memory),
+      events: Vec::new(),
+    }
+  }
+
+  /// A 64-bit FNV-1a hash of `memory`.
+  pub fn fingerprint(memory: &[isize]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for value in memory {
+      for byte in (*value as i64).to_le_bytes().iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100_0000_01b3);
+      }
+    }
+    hash
+  }
+
+  /// The values that were input, in order.
+  pub fn inputs(&self) -> Vec<isize> {
+    self
+      .events
+      .iter()
+      .filter_map(|e| match e {
+        RecordedEvent::Input { value, .. } => Some(*value),
+        RecordedEvent::Output { .. } | RecordedEvent::DeviceRead { .. } => None,
+      })
+      .collect()
+  }
+
+  /// Re-runs the session on `cpu`, which should be in the state it was in when recording started
+  /// with no input queued. Every recorded input is fed back and every event must happen again at
+  /// the same instruction. Returns the machine in its final state.
+  pub fn replay(&self, mut cpu: IntCode) -> Result<IntCode, ReplayError> {
+    let found = IntCodeRecording::fingerprint(cpu.get_memory());
+    if found != self.fingerprint {
+      let kind = ReplayErrorKind::ProgramMismatch {
+        expected: self.fingerprint,
+        found,
+      };
+      return Err(ReplayError { kind, event: 0 });
+    }
+
+    cpu.start_recording();
+    cpu.input(&self.inputs());
+    if let Err(error) = cpu.execute() {
+      let event = cpu.recording().map_or(0, |r| r.events.len());
+      let kind = ReplayErrorKind::Failed { error };
+      return Err(ReplayError { kind, event });
+    }
+
+    let replayed = cpu.stop_recording().unwrap();
+    let len = self.events.len().max(replayed.events.len());
+    for event in 0..len {
+      let expected = self.events.get(event).cloned();
+      let found = replayed.events.get(event).cloned();
+      if expected != found {
+        let kind = ReplayErrorKind::Diverged { expected, found };
+        return Err(ReplayError { kind, event });
+      }
+    }
+    Ok(cpu)
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    fs::write(path, self.to_string())
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    fs::read_to_string(path)?
+      .parse()
+      .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+}
+
+impl fmt::Display for IntCodeRecording {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{}", HEADER)?;
+    writeln!(f, "program {:016x}", self.fingerprint)?;
+    for event in &self.events {
+      match event {
+        RecordedEvent::Input { instruction, value } => writeln!(f, "i {} {}", instruction, value)?,
+        RecordedEvent::Output { instruction, value } => writeln!(f, "o {} {}", instruction, value)?,
+        RecordedEvent::DeviceRead { instruction, value } => {
+          writeln!(f, "d {} {}", instruction, value)?
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for IntCodeRecording {
+  type Err = ParseRecordingError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut lines = s.lines().enumerate().map(|(i, l)| (i + 1, l.trim()));
+    match lines.next() {
+      Some((_, HEADER)) => {}
+      _ => return Err(ParseRecordingError { line: 1 }),
+    }
+    let fingerprint = match lines.next() {
+      Some((line, l)) => l
+        .strip_prefix("program ")
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        .ok_or(ParseRecordingError { line })?,
+      None => return Err(ParseRecordingError { line: 2 }),
+    };
+
+    let mut events = Vec::new();
+    for (line, l) in lines.filter(|(_, l)| !l.is_empty()) {
+      let error = ParseRecordingError { line };
+      let parts: Vec<&str> = l.split_whitespace().collect();
+      if parts.len() != 3 {
+        return Err(error);
+      }
+      let instruction = parts[1].parse().map_err(|_| error.clone())?;
+      let value = parts[2].parse().map_err(|_| error.clone())?;
+      events.push(match parts[0] {
+        "i" => RecordedEvent::Input { instruction, value },
+        "o" => RecordedEvent::Output { instruction, value },
+        "d" => RecordedEvent::DeviceRead { instruction, value },
+        _ => return Err(error),
+      });
+    }
+    Ok(IntCodeRecording {
+      fingerprint,
+      events,
+    })
+  }
+}
+
+impl fmt::Display for ReplayError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.kind {
+      ReplayErrorKind::ProgramMismatch { expected, found } => write!(
+        f,
+        "recorded program {:016x} does not match {:016x}",
+        expected, found
+      ),
+      ReplayErrorKind::Diverged { expected, found } => write!(
+        f,
+        "replay diverged at event {}: expected {:?}, found {:?}",
+        self.event, expected, found
+      ),
+      ReplayErrorKind::Failed { error } => {
+        write!(f, "replay failed after event {}: {}", self.event, error)
+      }
+    }
+  }
+}
+
+impl Error for ReplayError {}
+
+impl fmt::Display for ParseRecordingError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid recording on line {}", self.line)
+  }
+}
+
+impl Error for ParseRecordingError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Echoes inputs until it reads a 0, then outputs the number of inputs and halts.
+  const ECHO: &str = "3,20,1005,20,9,4,21,99,0,4,20,1001,21,1,21,1105,1,0,0,0,0,0";
+
+  fn session() -> (IntCode, IntCodeRecording) {
+    let mut cpu = IntCode::from_string(ECHO);
+    cpu.start_recording();
+    for input in &[5, 7, 0] {
+      cpu.input(&vec![*input]);
+      cpu.execute().unwrap();
+    }
+    let recording = cpu.stop_recording().unwrap();
+    (cpu, recording)
+  }
+
+  #[test]
+  fn test_record() {
+    let (cpu, recording) = session();
+    assert_eq!(vec![5, 7, 0], recording.inputs());
+    assert_eq!(
+      vec![
+        RecordedEvent::Input {
+          instruction: 0,
+          value: 5
+        },
+        RecordedEvent::Output {
+          instruction: 2,
+          value: 5
+        },
+      ],
+      recording.events[..2].to_vec()
+    );
+    assert_eq!(
+      Some(&RecordedEvent::Output {
+        instruction: 12,
+        value: 2
+      }),
+      recording.events.last()
+    );
+    assert_eq!(None, cpu.recording());
+  }
+
+  #[test]
+  fn test_replay() {
+    let (original, recording) = session();
+    let replayed = recording.replay(IntCode::from_string(ECHO)).unwrap();
+    assert_eq!(original.get_output(), replayed.get_output());
+    assert_eq!(
+      original.get_instruction_count(),
+      replayed.get_instruction_count()
+    );
+  }
+
+  #[test]
+  fn test_replay_mismatch() {
+    let (_, mut recording) = session();
+    let error = recording
+      .replay(IntCode::from_string("3,20,99"))
+      .unwrap_err();
+    assert!(matches!(
+      error.kind,
+      ReplayErrorKind::ProgramMismatch { .. }
+    ));
+
+    recording.events[1] = RecordedEvent::Output {
+      instruction: 2,
+      value: 6,
+    };
+    let error = recording.replay(IntCode::from_string(ECHO)).unwrap_err();
+    assert_eq!(1, error.event);
+
+    let (_, mut recording) = session();
+    recording.events.truncate(5);
+    let error = recording.replay(IntCode::from_string(ECHO)).unwrap_err();
+    assert_eq!(5, error.event);
+    assert!(matches!(
+      error.kind,
+      ReplayErrorKind::Diverged { expected: None, .. }
+    ));
+  }
+
+  #[test]
+  fn test_text_format() {
+    let (_, recording) = session();
+    let text = recording.to_string();
+    assert!(text.starts_with("intcode recording v1\nprogram "));
+    assert!(text.contains("\ni 0 5\no 2 5\n"));
+    assert_eq!(Ok(recording), text.parse());
+    assert_eq!(
+      Ok(vec![RecordedEvent::DeviceRead {
+        instruction: 3,
+        value: -4
+      }]),
+      "intcode recording v1\nprogram 0\nd 3 -4\n"
+        .parse::<IntCodeRecording>()
+        .map(|r| r.events)
+    );
+    assert_eq!(
+      Err(ParseRecordingError { line: 3 }),
+      "intcode recording v1\nprogram 0\nx 1 2\n".parse::<IntCodeRecording>()
+    );
+    assert_eq!(
+      Err(ParseRecordingError { line: 1 }),
+      "".parse::<IntCodeRecording>()
+    );
+  }
+}
@@ -1,12 +1,15 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum IntCodeResultKind {
   Yield,
   Halt,
 }
 
-#[derive(Debug)]
-pub struct IntCodeResult<'a> {
+/// A summary of a single `IntCode::execute` call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntCodeResult {
   pub kind: IntCodeResultKind,
-  pub first: &'a isize,
-  pub output: &'a Vec<isize>,
+  /// Values output since the previous `execute` call.
+  pub output: Vec<isize>,
+  /// Instructions executed since the previous `execute` call.
+  pub instructions: usize,
 }
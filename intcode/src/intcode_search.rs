@@ -0,0 +1,295 @@
+use super::intcode::IntCode;
+use super::intcode_result::IntCodeResult;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The default number of instructions each candidate may run before it counts as an error.
+pub const DEFAULT_INSTRUCTION_LIMIT: usize = 1_000_000;
+
+/// Brute-forces a program over every combination of values for a set of memory cells, running
+/// candidates in parallel.
+///
+/// Candidates are enumerated like nested loops over the cells in the order they were added, with
+/// the last cell varying fastest. Candidates that fail or exceed the instruction limit are
+/// counted in `errors` rather than stopping the search. Searching panics if the number of
+/// candidates doesn't fit in a `usize`.
+pub struct IntCodeSearch {
+  base: IntCode,
+  cells: Vec<(isize, RangeInclusive<isize>)>,
+  threads: usize,
+  instruction_limit: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IntCodeSearchMatch {
+  /// The value patched into each varied cell, in the order the cells were added.
+  pub values: Vec<isize>,
+  pub result: IntCodeResult,
+  /// The machine in its final state.
+  pub cpu: IntCode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntCodeSearchStats {
+  /// The size of the search space.
+  pub candidates: usize,
+  /// The number of candidates that were actually run.
+  pub executed: usize,
+  /// The number of candidates that stopped with an `IntCodeError`.
+  pub errors: usize,
+  /// The total number of instructions executed across all candidates.
+  pub instructions: usize,
+  pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct IntCodeSearchResult {
+  pub matches: Vec<IntCodeSearchMatch>,
+  pub stats: IntCodeSearchStats,
+}
+
+impl IntCodeSearch {
+  pub fn new(base: IntCode) -> Self {
+    IntCodeSearch {
+      base,
+      cells: Vec::new(),
+      threads: thread::available_parallelism().map_or(1, |n| n.get()),
+      instruction_limit: Some(DEFAULT_INSTRUCTION_LIMIT),
+    }
+  }
+
+  /// Adds a memory cell to vary across every value in `range`.
+  pub fn vary(mut self, addr: isize, range: RangeInclusive<isize>) -> Self {
+    self.cells.push((addr, range));
+    self
+  }
+
+  /// Sets the number of worker threads. Defaults to the available parallelism.
+  pub fn threads(mut self, threads: usize) -> Self {
+    self.threads = threads.max(1);
+    self
+  }
+
+  /// Sets how many instructions each candidate may run, on top of any the base machine already
+  /// ran. Defaults to `DEFAULT_INSTRUCTION_LIMIT`; `None` lets candidates run forever.
+  pub fn instruction_limit(mut self, limit: Option<usize>) -> Self {
+    self.instruction_limit = limit;
+    self
+  }
+
+  /// Finds the first candidate, in enumeration order, whose result satisfies `predicate`. Workers
+  /// stop as soon as no earlier candidate can still match.
+  pub fn find_first<F>(&self, predicate: F) -> IntCodeSearchResult
+  where
+    F: Fn(&IntCode, &IntCodeResult) -> bool + Sync,
+  {
+    self.search(predicate, true)
+  }
+
+  /// Finds every candidate whose result satisfies `predicate`, in enumeration order.
+  pub fn find_all<F>(&self, predicate: F) -> IntCodeSearchResult
+  where
+    F: Fn(&IntCode, &IntCodeResult) -> bool + Sync,
+  {
+    self.search(predicate, false)
+  }
+
+  fn search<F>(&self, predicate: F, first_only: bool) -> IntCodeSearchResult
+  where
+    F: Fn(&IntCode, &IntCodeResult) -> bool + Sync,
+  {
+    let start = Instant::now();
+    let candidates = self.candidates();
+    let best = AtomicUsize::new(usize::MAX);
+    let found: Mutex<Vec<(usize, IntCodeSearchMatch)>> = Mutex::new(Vec::new());
+    let totals: Mutex<(usize, usize, usize)> = Mutex::new((0, 0, 0));
+
+    thread::scope(|scope| {
+      for worker in 0..self.threads {
+        let predicate = &predicate;
+        let best = &best;
+        let found = &found;
+        let totals = &totals;
+        scope.spawn(move || {
+          let (mut executed, mut errors, mut instructions) = (0, 0, 0);
+          let mut candidate = worker;
+          while candidate < candidates {
+            if first_only && candidate > best.load(Ordering::Relaxed) {
+              break;
+            }
+            let values = self.values(candidate);
+            let mut cpu = self.base.clone();
+            let limit = self.instruction_limit;
+            cpu.set_instruction_limit(limit.map(|limit| cpu.get_instruction_count() + limit));
+            let patches: Vec<(isize, isize)> = self
+              .cells
+              .iter()
+              .zip(values.iter())
+              .map(|((addr, _), value)| (*addr, *value))
+              .collect();
+            executed += 1;
+            let result = cpu.patch(&patches).and_then(|_| cpu.execute());
+            match result {
+              Ok(result) => {
+                instructions += result.instructions;
+                if predicate(&cpu, &result) {
+                  best.fetch_min(candidate, Ordering::Relaxed);
+                  let m = IntCodeSearchMatch {
+                    values,
+                    result,
+                    cpu,
+                  };
+                  found.lock().unwrap().push((candidate, m));
+                }
+              }
+              Err(_) => errors += 1,
+            }
+            candidate += self.threads;
+          }
+          let mut totals = totals.lock().unwrap();
+          totals.0 += executed;
+          totals.1 += errors;
+          totals.2 += instructions;
+        });
+      }
+    });
+
+    let mut found = found.into_inner().unwrap();
+    found.sort_by_key(|(candidate, _)| *candidate);
+    if first_only {
+      found.truncate(1);
+    }
+    let (executed, errors, instructions) = totals.into_inner().unwrap();
+    IntCodeSearchResult {
+      matches: found.into_iter().map(|(_, m)| m).collect(),
+      stats: IntCodeSearchStats {
+        candidates,
+        executed,
+        errors,
+        instructions,
+        elapsed: start.elapsed(),
+      },
+    }
+  }
+
+  fn candidates(&self) -> usize {
+    self
+      .cells
+      .iter()
+      .try_fold(1usize, |total, (_, range)| {
+        total.checked_mul(IntCodeSearch::range_len(range)?)
+      })
+      .expect("too many candidates to search")
+  }
+
+  /// Decodes a candidate number into the value for each cell, treating it as a mixed-radix number.
+  fn values(&self, mut candidate: usize) -> Vec<isize> {
+    let mut values = vec![0; self.cells.len()];
+    for (i, (_, range)) in self.cells.iter().enumerate().rev() {
+      let len = IntCodeSearch::range_len(range).unwrap();
+      // The offset can be more than `isize::MAX`, but the value it leads to always fits.
+      values[i] = range.start().wrapping_add((candidate % len) as isize);
+      candidate /= len;
+    }
+    values
+  }
+
+  fn range_len(range: &RangeInclusive<isize>) -> Option<usize> {
+    match range.is_empty() {
+      true => Some(0),
+      false => (range.end().wrapping_sub(*range.start()) as usize).checked_add(1),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Stores the sum of cells 1 and 2 into cell 0.
+  const SUM: &str = "1101,0,0,0,99";
+
+  fn sum_search(threads: usize) -> IntCodeSearch {
+    IntCodeSearch::new(IntCode::from_string(SUM))
+      .vary(1, 0..=3)
+      .vary(2, 0..=3)
+      .threads(threads)
+  }
+
+  #[test]
+  fn test_find_all() {
+    for threads in 1..5 {
+      let result = sum_search(threads).find_all(|cpu, _| cpu.peek(0) == Ok(3));
+      let values: Vec<Vec<isize>> = result.matches.iter().map(|m| m.values.clone()).collect();
+      assert_eq!(vec![vec![0, 3], vec![1, 2], vec![2, 1], vec![3, 0]], values);
+      assert_eq!(16, result.stats.candidates);
+      assert_eq!(16, result.stats.executed);
+      assert_eq!(0, result.stats.errors);
+      assert_eq!(32, result.stats.instructions);
+    }
+  }
+
+  #[test]
+  fn test_find_first() {
+    for threads in 1..5 {
+      let result = sum_search(threads).find_first(|cpu, _| cpu.peek(0) == Ok(5));
+      assert_eq!(1, result.matches.len());
+      assert_eq!(vec![2, 3], result.matches[0].values);
+      assert_eq!(Ok(5), result.matches[0].cpu.peek(0));
+      assert!(result.stats.executed < result.stats.candidates);
+    }
+  }
+
+  #[test]
+  fn test_no_match() {
+    let result = sum_search(2).find_first(|cpu, _| cpu.peek(0) == Ok(100));
+    assert!(result.matches.is_empty());
+    assert_eq!(16, result.stats.executed);
+  }
+
+  #[test]
+  fn test_errors() {
+    let result = IntCodeSearch::new(IntCode::from_string(SUM))
+      .vary(3, -2..=1)
+      .find_all(|_, _| true);
+    assert_eq!(4, result.stats.candidates);
+    assert_eq!(2, result.stats.errors);
+    assert_eq!(2, result.matches.len());
+  }
+
+  #[test]
+  fn test_instruction_limit() {
+    // Loops forever if cell 1 is zero.
+    let search = IntCodeSearch::new(IntCode::from_string("1106,0,0,99")).vary(1, 0..=2);
+    let result = search.instruction_limit(Some(10)).find_all(|_, _| true);
+    assert_eq!(1, result.stats.errors);
+    assert_eq!(2, result.matches.len());
+
+    // Invalid opcodes are errors too.
+    let result = IntCodeSearch::new(IntCode::from_string("1,0,0,0,99"))
+      .vary(0, 97..=99)
+      .find_all(|_, _| true);
+    assert_eq!(2, result.stats.errors);
+    assert_eq!(vec![99], result.matches[0].values);
+  }
+
+  #[test]
+  fn test_wide_range() {
+    let search = IntCodeSearch::new(IntCode::from_string("99")).vary(1, -1..=isize::MAX);
+    assert_eq!(vec![-1], search.values(0));
+    assert_eq!(vec![isize::MAX], search.values(1 << 63));
+    assert_eq!(vec![isize::MAX - 1], search.values((1 << 63) - 1));
+  }
+
+  #[test]
+  #[should_panic(expected = "too many candidates")]
+  fn test_too_many_candidates() {
+    IntCodeSearch::new(IntCode::from_string("99"))
+      .vary(1, 0..=isize::MAX)
+      .vary(2, 0..=isize::MAX)
+      .find_all(|_, _| true);
+  }
+}
@@ -0,0 +1,654 @@
+use super::intcode_result::IntCodeResultKind;
+use super::linear_expr::{Constraint, LinearExpr, Relation};
+use super::linear_solver::LinearSolver;
+use super::operation::{Operation, OperationMode};
+use std::ops::RangeInclusive;
+
+/// Runs an Intcode program with some memory cells or inputs replaced by symbols, following every
+/// feasible branch and recording the conditions under which each path is taken.
+///
+/// Values are tracked as linear expressions over the symbols. Anything that can't be represented
+/// that way, such as the product of two symbols or a read through a symbolic address, becomes
+/// unknown; this is only an error if an unknown value is later output, branched on or used as an
+/// address to write to.
+#[derive(Debug, Clone)]
+pub struct SymbolicIntCode {
+  memory: Vec<Option<LinearExpr>>,
+  inputs: Vec<LinearExpr>,
+  domains: Vec<RangeInclusive<isize>>,
+  max_paths: usize,
+  max_steps: usize,
+}
+
+/// One way through the program, along with the constraints that must hold for it to be taken.
+#[derive(Debug, Clone)]
+pub struct SymbolicPath {
+  pub kind: IntCodeResultKind,
+  pub constraints: Vec<Constraint>,
+  pub outputs: Vec<LinearExpr>,
+  memory: Vec<Option<LinearExpr>>,
+  domains: Vec<RangeInclusive<isize>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolicError {
+  pub kind: SymbolicErrorKind,
+  pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolicErrorKind {
+  /// The opcode at the instruction pointer depends on a symbol.
+  SymbolicInstruction,
+  InvalidOpcode {
+    opcode: isize,
+  },
+  /// A write, jump or relative base offset depends on a symbol.
+  SymbolicAddress,
+  /// A value that could not be tracked was output, branched on or used as an address.
+  UnknownValue,
+  ReadOutOfRange {
+    index: isize,
+  },
+  WriteOutOfRange {
+    index: isize,
+  },
+  WriteInvalidOperationMode,
+  JumpOutOfRange {
+    index: isize,
+  },
+  /// A single path executed more than the configured number of instructions.
+  StepLimit,
+  /// More than the configured number of paths were forked.
+  PathLimit,
+}
+
+#[derive(Clone)]
+struct State {
+  memory: Vec<Option<LinearExpr>>,
+  index: usize,
+  relative_base: isize,
+  input_index: usize,
+  outputs: Vec<LinearExpr>,
+  constraints: Vec<Constraint>,
+  steps: usize,
+}
+
+enum Step {
+  Continue,
+  Fork(State),
+  /// The path's constraints can't all hold, so it is dropped.
+  Infeasible,
+  Yield,
+  Halt,
+}
+
+impl SymbolicIntCode {
+  pub fn from_vec(raw: Vec<isize>) -> Self {
+    SymbolicIntCode {
+      memory: raw
+        .into_iter()
+        .map(|x| Some(LinearExpr::constant(x)))
+        .collect(),
+      inputs: Vec::new(),
+      domains: Vec::new(),
+      max_paths: 1024,
+      max_steps: 100_000,
+    }
+  }
+
+  pub fn from_string(raw: &str) -> Self {
+    let data: Vec<isize> = raw
+      .split(",")
+      .map(|x| x.parse::<isize>().unwrap())
+      .collect();
+    SymbolicIntCode::from_vec(data)
+  }
+
+  /// Replaces the memory cell at `addr` with a new symbol that may take any value in `domain`,
+  /// returning the symbol's index.
+  pub fn symbolic_cell(&mut self, addr: usize, domain: RangeInclusive<isize>) -> usize {
+    let symbol = self.new_symbol(domain);
+    if addr >= self.memory.len() {
+      self.memory.resize(addr + 1, Some(LinearExpr::constant(0)));
+    }
+    self.memory[addr] = Some(LinearExpr::symbol(symbol));
+    symbol
+  }
+
+  /// Queues a new symbol as the next input, returning the symbol's index.
+  pub fn symbolic_input(&mut self, domain: RangeInclusive<isize>) -> usize {
+    let symbol = self.new_symbol(domain);
+    self.inputs.push(LinearExpr::symbol(symbol));
+    symbol
+  }
+
+  pub fn input(&mut self, inputs: &[isize]) {
+    self
+      .inputs
+      .extend(inputs.iter().map(|x| LinearExpr::constant(*x)));
+  }
+
+  pub fn max_paths(mut self, max_paths: usize) -> Self {
+    self.max_paths = max_paths;
+    self
+  }
+
+  pub fn max_steps(mut self, max_steps: usize) -> Self {
+    self.max_steps = max_steps;
+    self
+  }
+
+  /// Follows every path through the program until it halts or runs out of input. A branch is only
+  /// followed if some symbol values satisfy every constraint along the way, so each path returned
+  /// can actually be taken.
+  pub fn explore(&self) -> Result<Vec<SymbolicPath>, SymbolicError> {
+    let mut pending = vec![State {
+      memory: self.memory.clone(),
+      index: 0,
+      relative_base: 0,
+      input_index: 0,
+      outputs: Vec::new(),
+      constraints: Vec::new(),
+      steps: 0,
+    }];
+    let mut forks = 1;
+    let mut paths = Vec::new();
+    while let Some(mut state) = pending.pop() {
+      let kind = loop {
+        if state.steps >= self.max_steps {
+          return Err(SymbolicIntCode::error(&state, SymbolicErrorKind::StepLimit));
+        }
+        state.steps += 1;
+        let index = state.index;
+        match self.step(&mut state)? {
+          Step::Continue => {}
+          Step::Fork(other) => {
+            forks += 1;
+            if forks > self.max_paths {
+              return Err(SymbolicError {
+                kind: SymbolicErrorKind::PathLimit,
+                index,
+              });
+            }
+            pending.push(other);
+          }
+          Step::Infeasible => break None,
+          Step::Yield => break Some(IntCodeResultKind::Yield),
+          Step::Halt => break Some(IntCodeResultKind::Halt),
+        }
+      };
+      let kind = match kind {
+        Some(kind) => kind,
+        None => continue,
+      };
+      paths.push(SymbolicPath {
+        kind,
+        constraints: state.constraints,
+        outputs: state.outputs,
+        memory: state.memory,
+        domains: self.domains.clone(),
+      });
+    }
+    Ok(paths)
+  }
+
+  fn new_symbol(&mut self, domain: RangeInclusive<isize>) -> usize {
+    self.domains.push(domain);
+    self.domains.len() - 1
+  }
+
+  fn step(&self, state: &mut State) -> Result<Step, SymbolicError> {
+    let opcode = match SymbolicIntCode::cell(state, state.index).as_ref() {
+      Some(e) => e.as_constant(),
+      None => None,
+    };
+    let operation = match opcode {
+      Some(op) => match Operation::decode(op) {
+        Some(operation) => operation,
+        None => {
+          return Err(SymbolicIntCode::error(
+            state,
+            SymbolicErrorKind::InvalidOpcode { opcode: op },
+          ))
+        }
+      },
+      None => {
+        return Err(SymbolicIntCode::error(
+          state,
+          SymbolicErrorKind::SymbolicInstruction,
+        ))
+      }
+    };
+    let next = state.index + operation.length();
+    match &operation {
+      Operation::Add(m) => {
+        let val = match (self.read(state, 1, &m[0])?, self.read(state, 2, &m[1])?) {
+          (Some(a), Some(b)) => Some(&a + &b),
+          _ => None,
+        };
+        self.write(state, 3, &m[2], val)?;
+      }
+      Operation::Multiply(m) => {
+        let val = match (self.read(state, 1, &m[0])?, self.read(state, 2, &m[1])?) {
+          (Some(a), Some(b)) => a.checked_mul(&b),
+          _ => None,
+        };
+        self.write(state, 3, &m[2], val)?;
+      }
+      Operation::Input(m) => match self.inputs.get(state.input_index).cloned() {
+        Some(val) => {
+          self.write(state, 1, &m[0], Some(val))?;
+          state.input_index += 1;
+        }
+        None => return Ok(Step::Yield),
+      },
+      Operation::Output(m) => {
+        let val = self.read_known(state, 1, &m[0])?;
+        state.outputs.push(val);
+      }
+      Operation::JumpIfTrue(m) | Operation::JumpIfFalse(m) => {
+        let jump_if_true = matches!(operation, Operation::JumpIfTrue(_));
+        let val = self.read_known(state, 1, &m[0])?;
+        // Jumps when `val != 0` for JumpIfTrue, or `val == 0` for JumpIfFalse.
+        let jump = match jump_if_true {
+          true => Constraint::new(val, Relation::NotEqual),
+          false => Constraint::new(val, Relation::Equal),
+        };
+        // The target is only read when the jump is taken, as it needn't be valid otherwise.
+        return self.branch(state, jump, |s, taken| {
+          s.index = match taken {
+            true => self.jump_target(s, &m[1])?,
+            false => next,
+          };
+          Ok(())
+        });
+      }
+      Operation::LessThan(m) | Operation::Equals(m) => {
+        let relation = match operation {
+          Operation::LessThan(_) => Relation::LessThan,
+          _ => Relation::Equal,
+        };
+        let val = match (self.read(state, 1, &m[0])?, self.read(state, 2, &m[1])?) {
+          (Some(a), Some(b)) => Some(&a - &b),
+          _ => None,
+        };
+        let val = match val {
+          Some(v) => v,
+          None => {
+            self.write(state, 3, &m[2], None)?;
+            state.index = next;
+            return Ok(Step::Continue);
+          }
+        };
+        return self.branch(state, Constraint::new(val, relation), |s, holds| {
+          let result = LinearExpr::constant(if holds { 1 } else { 0 });
+          self.write(s, 3, &m[2], Some(result))?;
+          s.index = next;
+          Ok(())
+        });
+      }
+      Operation::RelBaseOffset(m) => {
+        let val = self.read_known(state, 1, &m[0])?;
+        match val.as_constant() {
+          Some(offset) => state.relative_base += offset,
+          None => {
+            return Err(SymbolicIntCode::error(
+              state,
+              SymbolicErrorKind::SymbolicAddress,
+            ))
+          }
+        }
+      }
+      Operation::Halt => return Ok(Step::Halt),
+    }
+    state.index = next;
+    Ok(Step::Continue)
+  }
+
+  /// Applies `apply` to `state` for whichever outcomes of `constraint` are still possible,
+  /// forking a copy of the state if both are.
+  fn branch<F>(
+    &self,
+    state: &mut State,
+    constraint: Constraint,
+    apply: F,
+  ) -> Result<Step, SymbolicError>
+  where
+    F: Fn(&mut State, bool) -> Result<(), SymbolicError>,
+  {
+    if let Some(k) = constraint.expr.as_constant() {
+      apply(state, constraint.relation.holds(k))?;
+      return Ok(Step::Continue);
+    }
+    let negated = constraint.negate();
+    match (
+      self.feasible(state, &constraint),
+      self.feasible(state, &negated),
+    ) {
+      (true, true) => {
+        let mut other = state.clone();
+        other.constraints.push(negated);
+        apply(&mut other, false)?;
+        state.constraints.push(constraint);
+        apply(state, true)?;
+        Ok(Step::Fork(other))
+      }
+      // Only one outcome is possible, and it already follows from the existing constraints.
+      (true, false) => apply(state, true).map(|_| Step::Continue),
+      (false, true) => apply(state, false).map(|_| Step::Continue),
+      (false, false) => Ok(Step::Infeasible),
+    }
+  }
+
+  /// Whether some symbol values satisfy both the path's constraints and `constraint`.
+  fn feasible(&self, state: &State, constraint: &Constraint) -> bool {
+    let mut constraints = state.constraints.clone();
+    constraints.push(constraint.clone());
+    LinearSolver::new(self.domains.clone())
+      .solve(&constraints)
+      .is_some()
+  }
+
+  /// Reads the target of the jump at the instruction pointer, which must be a known, non-negative
+  /// address.
+  fn jump_target(&self, state: &State, mode: &OperationMode) -> Result<usize, SymbolicError> {
+    let target = self.read_known(state, 2, mode)?;
+    match target.as_constant() {
+      Some(t) if t < 0 => Err(SymbolicIntCode::error(
+        state,
+        SymbolicErrorKind::JumpOutOfRange { index: t },
+      )),
+      Some(t) => Ok(t as usize),
+      None => Err(SymbolicIntCode::error(
+        state,
+        SymbolicErrorKind::SymbolicAddress,
+      )),
+    }
+  }
+
+  fn cell(state: &State, addr: usize) -> Option<LinearExpr> {
+    match state.memory.get(addr) {
+      Some(val) => val.clone(),
+      None => Some(LinearExpr::constant(0)),
+    }
+  }
+
+  /// Reads parameter `param` of the current instruction. Returns `None` if the value is unknown.
+  fn read(
+    &self,
+    state: &State,
+    param: usize,
+    mode: &OperationMode,
+  ) -> Result<Option<LinearExpr>, SymbolicError> {
+    let raw = SymbolicIntCode::cell(state, state.index + param);
+    let base = match mode {
+      OperationMode::Immediate => return Ok(raw),
+      OperationMode::Position => 0,
+      OperationMode::Relative => state.relative_base,
+    };
+    let addr = match raw.and_then(|r| r.as_constant()) {
+      Some(a) => a + base,
+      None => return Ok(None),
+    };
+    if addr < 0 {
+      return Err(SymbolicIntCode::error(
+        state,
+        SymbolicErrorKind::ReadOutOfRange { index: addr },
+      ));
+    }
+    Ok(SymbolicIntCode::cell(state, addr as usize))
+  }
+
+  fn read_known(
+    &self,
+    state: &State,
+    param: usize,
+    mode: &OperationMode,
+  ) -> Result<LinearExpr, SymbolicError> {
+    match self.read(state, param, mode)? {
+      Some(val) => Ok(val),
+      None => Err(SymbolicIntCode::error(
+        state,
+        SymbolicErrorKind::UnknownValue,
+      )),
+    }
+  }
+
+  fn write(
+    &self,
+    state: &mut State,
+    param: usize,
+    mode: &OperationMode,
+    value: Option<LinearExpr>,
+  ) -> Result<(), SymbolicError> {
+    let base = match mode {
+      OperationMode::Immediate => {
+        return Err(SymbolicIntCode::error(
+          state,
+          SymbolicErrorKind::WriteInvalidOperationMode,
+        ))
+      }
+      OperationMode::Position => 0,
+      OperationMode::Relative => state.relative_base,
+    };
+    let raw = SymbolicIntCode::cell(state, state.index + param);
+    let addr = match raw.map(|r| r.as_constant()) {
+      Some(Some(a)) => a + base,
+      Some(None) => {
+        return Err(SymbolicIntCode::error(
+          state,
+          SymbolicErrorKind::SymbolicAddress,
+        ))
+      }
+      None => {
+        return Err(SymbolicIntCode::error(
+          state,
+          SymbolicErrorKind::UnknownValue,
+        ))
+      }
+    };
+    if addr < 0 {
+      return Err(SymbolicIntCode::error(
+        state,
+        SymbolicErrorKind::WriteOutOfRange { index: addr },
+      ));
+    }
+    let addr = addr as usize;
+    if addr >= state.memory.len() {
+      state.memory.resize(addr + 1, Some(LinearExpr::constant(0)));
+    }
+    state.memory[addr] = value;
+    Ok(())
+  }
+
+  fn error(state: &State, kind: SymbolicErrorKind) -> SymbolicError {
+    SymbolicError {
+      kind,
+      index: state.index,
+    }
+  }
+}
+
+impl SymbolicPath {
+  /// The value of a memory cell at the end of the path, or `None` if it could not be tracked.
+  pub fn cell(&self, addr: usize) -> Option<&LinearExpr> {
+    match self.memory.get(addr) {
+      Some(val) => val.as_ref(),
+      None => None,
+    }
+  }
+
+  /// Finds symbol values that lead down this path and also satisfy `goals`, indexed by symbol.
+  pub fn solve(&self, goals: &[Constraint]) -> Option<Vec<isize>> {
+    let mut constraints = self.constraints.clone();
+    constraints.extend(goals.iter().cloned());
+    LinearSolver::new(self.domains.clone()).solve(&constraints)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LARGE_COMPARE: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+  #[test]
+  fn test_noun_verb() {
+    // Like day 2: the first instruction reads through the symbolic cells, but its result is
+    // overwritten before it matters. Cell 0 ends up as 100 * noun + verb.
+    let mut cpu = SymbolicIntCode::from_string("1,0,0,3,1002,1,100,3,1,3,2,0,99");
+    let noun = cpu.symbolic_cell(1, 0..=99);
+    let verb = cpu.symbolic_cell(2, 0..=99);
+    let paths = cpu.explore().unwrap();
+    assert_eq!(1, paths.len());
+    let cell = paths[0].cell(0).unwrap();
+    assert_eq!("100*s0 + s1", cell.to_string());
+    let solution = paths[0].solve(&[Constraint::equals(cell, 1234)]).unwrap();
+    assert_eq!(12, solution[noun]);
+    assert_eq!(34, solution[verb]);
+    assert_eq!(None, paths[0].solve(&[Constraint::equals(cell, 10000)]));
+  }
+
+  #[test]
+  fn test_linear_output() {
+    let mut cpu = SymbolicIntCode::from_string("3,0,102,3,0,0,4,0,99");
+    cpu.symbolic_input(-100..=100);
+    let paths = cpu.explore().unwrap();
+    assert_eq!(1, paths.len());
+    assert_eq!(IntCodeResultKind::Halt, paths[0].kind);
+    let goal = Constraint::equals(&paths[0].outputs[0], -21);
+    assert_eq!(Some(vec![-7]), paths[0].solve(&[goal]));
+  }
+
+  #[test]
+  fn test_branches() {
+    let mut cpu = SymbolicIntCode::from_string("3,9,8,9,10,9,4,9,99,-1,8");
+    cpu.symbolic_input(0..=20);
+    let paths = cpu.explore().unwrap();
+    let mut solved: Vec<(isize, Vec<isize>)> = paths
+      .iter()
+      .map(|p| (p.outputs[0].as_constant().unwrap(), p.solve(&[]).unwrap()))
+      .collect();
+    solved.sort();
+    assert_eq!(vec![(0, vec![0]), (1, vec![8])], solved);
+  }
+
+  #[test]
+  fn test_find_input_for_output() {
+    let mut cpu = SymbolicIntCode::from_string(LARGE_COMPARE);
+    cpu.symbolic_input(0..=20);
+    let paths = cpu.explore().unwrap();
+    assert_eq!(3, paths.len());
+    let path = paths
+      .iter()
+      .find(|p| p.outputs[0].as_constant() == Some(1001))
+      .unwrap();
+    assert_eq!(Some(vec![9]), path.solve(&[]));
+  }
+
+  #[test]
+  fn test_concrete() {
+    let mut cpu = SymbolicIntCode::from_string(LARGE_COMPARE);
+    cpu.input(&[8]);
+    let paths = cpu.explore().unwrap();
+    assert_eq!(1, paths.len());
+    assert_eq!(vec![LinearExpr::constant(1000)], paths[0].outputs);
+    assert!(paths[0].constraints.is_empty());
+  }
+
+  #[test]
+  fn test_yield() {
+    let paths = SymbolicIntCode::from_string("3,0,99").explore().unwrap();
+    assert_eq!(IntCodeResultKind::Yield, paths[0].kind);
+  }
+
+  #[test]
+  fn test_unknown_output() {
+    let mut cpu = SymbolicIntCode::from_string("1,0,0,3,4,3,99");
+    cpu.symbolic_cell(1, 0..=10);
+    assert_eq!(
+      Err(SymbolicError {
+        kind: SymbolicErrorKind::UnknownValue,
+        index: 4,
+      }),
+      cpu.explore().map(|_| ())
+    );
+  }
+
+  #[test]
+  fn test_limits() {
+    // Counts down from the input, forking on every comparison.
+    let mut cpu = SymbolicIntCode::from_string("3,100,1001,100,-1,100,1005,100,2,99").max_paths(10);
+    cpu.symbolic_input(0..=1000);
+    assert_eq!(
+      Err(SymbolicError {
+        kind: SymbolicErrorKind::PathLimit,
+        index: 6,
+      }),
+      cpu.explore().map(|_| ())
+    );
+
+    let cpu = SymbolicIntCode::from_string("1105,1,0").max_steps(50);
+    assert_eq!(
+      Err(SymbolicError {
+        kind: SymbolicErrorKind::StepLimit,
+        index: 0,
+      }),
+      cpu.explore().map(|_| ())
+    );
+  }
+
+  #[test]
+  fn test_invalid_opcode() {
+    assert_eq!(
+      Err(SymbolicError {
+        kind: SymbolicErrorKind::InvalidOpcode { opcode: 98 },
+        index: 2,
+      }),
+      SymbolicIntCode::from_string("104,1,98")
+        .explore()
+        .map(|_| ())
+    );
+  }
+
+  #[test]
+  fn test_prune_infeasible() {
+    // Branches on `s0 == 8`, then on `s0 != 0`, which always holds when `s0 == 8`.
+    let mut cpu = SymbolicIntCode::from_string("3,100,1008,100,8,101,1005,100,9,99");
+    cpu.symbolic_input(0..=20);
+    let paths = cpu.explore().unwrap();
+    assert_eq!(3, paths.len());
+    assert!(paths.iter().all(|p| p.solve(&[]).is_some()));
+
+    // Every input is at least 5, so the jump is always taken.
+    let mut cpu = SymbolicIntCode::from_string("3,100,1007,100,5,101,1006,101,10,98,99");
+    cpu.symbolic_input(5..=9);
+    let paths = cpu.explore().unwrap();
+    assert_eq!(1, paths.len());
+    assert!(paths[0].constraints.is_empty());
+  }
+
+  #[test]
+  fn test_jump_out_of_range() {
+    let mut cpu = SymbolicIntCode::from_string("3,100,1005,100,-1,99");
+    cpu.symbolic_input(0..=1);
+    assert_eq!(
+      Err(SymbolicError {
+        kind: SymbolicErrorKind::JumpOutOfRange { index: -1 },
+        index: 2,
+      }),
+      cpu.explore().map(|_| ())
+    );
+  }
+
+  #[test]
+  fn test_jump_not_taken() {
+    // The jump's target is unknown, but the jump is never taken.
+    let mut cpu = SymbolicIntCode::from_string("1,0,0,20,105,0,20,99");
+    cpu.symbolic_cell(1, 0..=7);
+    let paths = cpu.explore().unwrap();
+    assert_eq!(1, paths.len());
+    assert_eq!(IntCodeResultKind::Halt, paths[0].kind);
+  }
+}
@@ -1,8 +1,44 @@
+mod compile_error;
+mod compiler_lexer;
+mod compiler_parser;
 mod intcode;
+mod intcode_ascii;
+mod intcode_compiler;
+mod intcode_device;
+mod intcode_diff;
 mod intcode_error;
+mod intcode_linker;
+mod intcode_network;
+mod intcode_optimizer;
+mod intcode_recording;
 mod intcode_result;
+mod intcode_search;
+mod intcode_symbolic;
+mod linear_expr;
+mod linear_solver;
 mod operation;
 mod operation_result;
 
+pub use compile_error::{CompileError, CompileErrorKind};
 pub use intcode::IntCode;
-pub use intcode_error::{IntCodeError, IntCodeErrorKind};pub use intcode_result::{IntCodeResult, IntCodeResultKind};
+pub use intcode_ascii::{IntCodeAscii, IntCodeAsciiError, IntCodeAsciiOutput};
+pub use intcode_compiler::IntCodeCompiler;
+pub use intcode_device::IntCodeDevice;
+pub use intcode_diff::{DiffRange, IntCodeDiff, ModifiedInstruction};
+pub use intcode_error::{IntCodeError, IntCodeErrorContext, IntCodeErrorKind};
+pub use intcode_linker::{IntCodeLinker, IntCodeModule, LinkError, LinkErrorKind, LinkedProgram};
+pub use intcode_network::{
+  DirectRouter, IdleAction, IntCodeNetwork, IntCodeNetworkError, IntCodeRouter, Nat,
+  NetworkOutcome, Packet, PacketLogEntry, Route,
+};
+pub use intcode_optimizer::{IntCodeOptimizer, OptimizeError, OptimizeErrorKind, OptimizedProgram};
+pub use intcode_recording::{
+  IntCodeRecording, ParseRecordingError, RecordedEvent, ReplayError, ReplayErrorKind,
+};
+pub use intcode_result::{IntCodeResult, IntCodeResultKind};
+pub use intcode_search::{
+  IntCodeSearch, IntCodeSearchMatch, IntCodeSearchResult, IntCodeSearchStats,
+};
+pub use intcode_symbolic::{SymbolicError, SymbolicErrorKind, SymbolicIntCode, SymbolicPath};
+pub use linear_expr::{Constraint, LinearExpr, Relation};
+pub use linear_solver::LinearSolver;
@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, Sub};
+
+/// A linear combination of symbols plus a constant, e.g. `100*s0 + s1 + 3`. Symbols are
+/// identified by the index they were created with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinearExpr {
+  constant: isize,
+  terms: BTreeMap<usize, isize>,
+}
+
+impl LinearExpr {
+  pub fn constant(value: isize) -> Self {
+    LinearExpr {
+      constant: value,
+      terms: BTreeMap::new(),
+    }
+  }
+
+  pub fn symbol(symbol: usize) -> Self {
+    let mut terms = BTreeMap::new();
+    terms.insert(symbol, 1);
+    LinearExpr { constant: 0, terms }
+  }
+
+  /// The value of the expression, if it does not depend on any symbols.
+  pub fn as_constant(&self) -> Option<isize> {
+    match self.terms.is_empty() {
+      true => Some(self.constant),
+      false => None,
+    }
+  }
+
+  pub fn constant_term(&self) -> isize {
+    self.constant
+  }
+
+  /// The coefficient of each symbol in the expression, ignoring the constant.
+  pub fn terms(&self) -> &BTreeMap<usize, isize> {
+    &self.terms
+  }
+
+  pub fn scale(&self, factor: isize) -> LinearExpr {
+    if factor == 0 {
+      return LinearExpr::constant(0);
+    }
+    LinearExpr {
+      constant: self.constant * factor,
+      terms: self.terms.iter().map(|(s, c)| (*s, c * factor)).collect(),
+    }
+  }
+
+  /// Multiplies two expressions, returning `None` if the product would not be linear.
+  pub fn checked_mul(&self, other: &LinearExpr) -> Option<LinearExpr> {
+    match (self.as_constant(), other.as_constant()) {
+      (Some(a), _) => Some(other.scale(a)),
+      (_, Some(b)) => Some(self.scale(b)),
+      (None, None) => None,
+    }
+  }
+
+  /// Replaces every symbol that has a value in `values` with that value.
+  pub fn substitute(&self, values: &[Option<isize>]) -> LinearExpr {
+    let mut result = LinearExpr::constant(self.constant);
+    for (symbol, coefficient) in &self.terms {
+      match values.get(*symbol).cloned().flatten() {
+        Some(v) => result.constant += coefficient * v,
+        None => {
+          result.terms.insert(*symbol, *coefficient);
+        }
+      }
+    }
+    result
+  }
+}
+
+impl Add for &LinearExpr {
+  type Output = LinearExpr;
+
+  fn add(self, other: &LinearExpr) -> LinearExpr {
+    let mut result = self.clone();
+    result.constant += other.constant;
+    for (symbol, coefficient) in &other.terms {
+      let c = result.terms.entry(*symbol).or_insert(0);
+      *c += coefficient;
+      if *c == 0 {
+        result.terms.remove(symbol);
+      }
+    }
+    result
+  }
+}
+
+impl Sub for &LinearExpr {
+  type Output = LinearExpr;
+
+  fn sub(self, other: &LinearExpr) -> LinearExpr {
+    self + &other.scale(-1)
+  }
+}
+
+impl fmt::Display for LinearExpr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, (symbol, coefficient)) in self.terms.iter().enumerate() {
+      match (i, *coefficient) {
+        (0, 1) => write!(f, "s{}", symbol)?,
+        (0, -1) => write!(f, "-s{}", symbol)?,
+        (0, c) => write!(f, "{}*s{}", c, symbol)?,
+        (_, 1) => write!(f, " + s{}", symbol)?,
+        (_, -1) => write!(f, " - s{}", symbol)?,
+        (_, c) if c < 0 => write!(f, " - {}*s{}", -c, symbol)?,
+        (_, c) => write!(f, " + {}*s{}", c, symbol)?,
+      }
+    }
+    match (self.terms.is_empty(), self.constant) {
+      (true, c) => write!(f, "{}", c),
+      (false, 0) => Ok(()),
+      (false, c) if c < 0 => write!(f, " - {}", -c),
+      (false, c) => write!(f, " + {}", c),
+    }
+  }
+}
+
+/// How a `Constraint`'s expression relates to zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+  Equal,
+  NotEqual,
+  LessThan,
+  GreaterOrEqual,
+}
+
+impl Relation {
+  pub fn holds(&self, value: isize) -> bool {
+    match self {
+      Relation::Equal => value == 0,
+      Relation::NotEqual => value != 0,
+      Relation::LessThan => value < 0,
+      Relation::GreaterOrEqual => value >= 0,
+    }
+  }
+
+  pub fn negate(&self) -> Relation {
+    match self {
+      Relation::Equal => Relation::NotEqual,
+      Relation::NotEqual => Relation::Equal,
+      Relation::LessThan => Relation::GreaterOrEqual,
+      Relation::GreaterOrEqual => Relation::LessThan,
+    }
+  }
+}
+
+/// A condition of the form `expr <relation> 0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+  pub expr: LinearExpr,
+  pub relation: Relation,
+}
+
+impl Constraint {
+  pub fn new(expr: LinearExpr, relation: Relation) -> Self {
+    Constraint { expr, relation }
+  }
+
+  /// A constraint that `expr` evaluates to `value`.
+  pub fn equals(expr: &LinearExpr, value: isize) -> Self {
+    Constraint::new(expr - &LinearExpr::constant(value), Relation::Equal)
+  }
+
+  pub fn negate(&self) -> Self {
+    Constraint::new(self.expr.clone(), self.relation.negate())
+  }
+}
+
+impl fmt::Display for Constraint {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let op = match self.relation {
+      Relation::Equal => "==",
+      Relation::NotEqual => "!=",
+      Relation::LessThan => "<",
+      Relation::GreaterOrEqual => ">=",
+    };
+    write!(f, "{} {} 0", self.expr, op)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_arithmetic() {
+    let x = LinearExpr::symbol(0);
+    let y = LinearExpr::symbol(1);
+    let e = &(&x.scale(100) + &y) + &LinearExpr::constant(3);
+    assert_eq!("100*s0 + s1 + 3", e.to_string());
+    assert_eq!("s1 + 3", (&e - &x.scale(100)).to_string());
+    assert_eq!(LinearExpr::constant(0), &x - &x);
+    assert_eq!(None, x.checked_mul(&y));
+    assert_eq!(Some(x.scale(-2)), x.checked_mul(&LinearExpr::constant(-2)));
+    assert_eq!(Some(203), e.substitute(&[Some(2), Some(0)]).as_constant());
+    assert_eq!("s1 + 203", e.substitute(&[Some(2)]).to_string());
+  }
+
+  #[test]
+  fn test_constraint() {
+    let c = Constraint::equals(&LinearExpr::symbol(0), 8);
+    assert_eq!("s0 - 8 == 0", c.to_string());
+    assert_eq!("s0 - 8 != 0", c.negate().to_string());
+    assert!(Relation::LessThan.holds(-1));
+    assert!(!Relation::LessThan.holds(0));
+  }
+}
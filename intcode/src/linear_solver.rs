@@ -0,0 +1,148 @@
+use super::linear_expr::{Constraint, Relation};
+use std::ops::RangeInclusive;
+
+/// Finds integer values for a set of symbols, each limited to a domain, that satisfy a list of
+/// linear constraints.
+///
+/// Equalities with a single unknown symbol are solved directly; any symbol that can't be derived
+/// that way is enumerated across its domain. This makes problems like `100*noun + verb == target`
+/// cheap, but the worst case is still a search over every combination of domains.
+pub struct LinearSolver {
+  domains: Vec<RangeInclusive<isize>>,
+}
+
+impl LinearSolver {
+  /// Creates a solver where symbol `i` may take any value in `domains[i]`.
+  pub fn new(domains: Vec<RangeInclusive<isize>>) -> Self {
+    LinearSolver { domains }
+  }
+
+  /// Returns a value for each symbol satisfying every constraint, or `None` if there isn't one.
+  /// When several solutions exist, the one that is smallest in symbol order is returned.
+  pub fn solve(&self, constraints: &[Constraint]) -> Option<Vec<isize>> {
+    let mut values = vec![None; self.domains.len()];
+    match self.search(constraints, &mut values) {
+      true => Some(values.into_iter().map(|v| v.unwrap()).collect()),
+      false => None,
+    }
+  }
+
+  fn search(&self, constraints: &[Constraint], values: &mut [Option<isize>]) -> bool {
+    let mut derived = Vec::new();
+    if !self.propagate(constraints, values, &mut derived) {
+      LinearSolver::undo(values, &derived);
+      return false;
+    }
+
+    let symbol = match values.iter().position(|v| v.is_none()) {
+      Some(s) => s,
+      None => return true,
+    };
+    for value in self.domains[symbol].clone() {
+      values[symbol] = Some(value);
+      if self.search(constraints, values) {
+        return true;
+      }
+    }
+    values[symbol] = None;
+    LinearSolver::undo(values, &derived);
+    false
+  }
+
+  /// Checks every constraint against the current assignment, deriving any symbol that is the only
+  /// unknown in an equality. Returns false if a constraint can no longer be satisfied.
+  fn propagate(
+    &self,
+    constraints: &[Constraint],
+    values: &mut [Option<isize>],
+    derived: &mut Vec<usize>,
+  ) -> bool {
+    loop {
+      let mut changed = false;
+      for constraint in constraints {
+        let expr = constraint.expr.substitute(values);
+        if let Some(k) = expr.as_constant() {
+          if !constraint.relation.holds(k) {
+            return false;
+          }
+          continue;
+        }
+        if constraint.relation != Relation::Equal || expr.terms().len() != 1 {
+          continue;
+        }
+        let (symbol, coefficient) = expr.terms().iter().next().unwrap();
+        let rest = -expr.constant_term();
+        if rest % coefficient != 0 {
+          return false;
+        }
+        let value = rest / coefficient;
+        if !self.domains[*symbol].contains(&value) {
+          return false;
+        }
+        values[*symbol] = Some(value);
+        derived.push(*symbol);
+        changed = true;
+      }
+      if !changed {
+        return true;
+      }
+    }
+  }
+
+  fn undo(values: &mut [Option<isize>], derived: &[usize]) {
+    for symbol in derived {
+      values[*symbol] = None;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::linear_expr::LinearExpr;
+
+  fn noun_verb(target: isize) -> Constraint {
+    let expr = &LinearExpr::symbol(0).scale(100) + &LinearExpr::symbol(1);
+    Constraint::equals(&expr, target)
+  }
+
+  #[test]
+  fn test_equality() {
+    let solver = LinearSolver::new(vec![0..=99, 0..=99]);
+    assert_eq!(Some(vec![12, 34]), solver.solve(&[noun_verb(1234)]));
+    assert_eq!(Some(vec![0, 0]), solver.solve(&[noun_verb(0)]));
+    assert_eq!(None, solver.solve(&[noun_verb(10000)]));
+  }
+
+  #[test]
+  fn test_divisibility() {
+    let x = LinearExpr::symbol(0).scale(3);
+    let solver = LinearSolver::new(vec![-10..=10]);
+    assert_eq!(Some(vec![-4]), solver.solve(&[Constraint::equals(&x, -12)]));
+    assert_eq!(None, solver.solve(&[Constraint::equals(&x, 7)]));
+  }
+
+  #[test]
+  fn test_inequalities() {
+    let x = LinearExpr::symbol(0);
+    let solver = LinearSolver::new(vec![0..=20]);
+    // x >= 8 && x != 8
+    let constraints = [
+      Constraint::new(&x - &LinearExpr::constant(8), Relation::GreaterOrEqual),
+      Constraint::equals(&x, 8).negate(),
+    ];
+    assert_eq!(Some(vec![9]), solver.solve(&constraints));
+    assert_eq!(
+      None,
+      solver.solve(&[Constraint::new(x.clone(), Relation::LessThan)])
+    );
+  }
+
+  #[test]
+  fn test_no_symbols() {
+    let solver = LinearSolver::new(vec![]);
+    assert_eq!(Some(vec![]), solver.solve(&[]));
+    let one = LinearExpr::constant(1);
+    assert_eq!(None, solver.solve(&[Constraint::equals(&one, 2)]));
+  }
+}
@@ -1,3 +1,6 @@
+use aoc_util::ToDigits;
+use std::fmt;
+
 #[derive(Debug, Clone)]
 pub enum Operation {
   Add([OperationMode; 3]),
@@ -12,7 +15,100 @@ pub enum Operation {
   Halt,
 }
 
-#[derive(Debug, Clone)]
+impl Operation {
+  /// Decodes an opcode and its parameter modes, or returns `None` if the opcode or any of its
+  /// parameter modes are invalid.
+  pub fn decode(opcode: isize) -> Option<Operation> {
+    if opcode < 0 {
+      return None;
+    }
+    let opcode = opcode.digits();
+    let op = opcode[0] + (opcode.get(1).cloned().unwrap_or(0) * 10);
+    let mut params = [0; 3]; // Increase array len if more params needed
+    for i in 0..params.len() {
+      params[i] = opcode.get(i + 2).cloned().unwrap_or(0);
+    }
+
+    let el0 = OperationMode::decode(params[0])?;
+    let el1 = OperationMode::decode(params[1])?;
+    let el2 = OperationMode::decode(params[2])?;
+    match op {
+      1 => Some(Operation::Add([el0, el1, el2])),
+      2 => Some(Operation::Multiply([el0, el1, el2])),
+      3 => Some(Operation::Input([el0])),
+      4 => Some(Operation::Output([el0])),
+      5 => Some(Operation::JumpIfTrue([el0, el1])),
+      6 => Some(Operation::JumpIfFalse([el0, el1])),
+      7 => Some(Operation::LessThan([el0, el1, el2])),
+      8 => Some(Operation::Equals([el0, el1, el2])),
+      9 => Some(Operation::RelBaseOffset([el0])),
+      99 => Some(Operation::Halt),
+      _ => None,
+    }
+  }
+
+  /// The number of memory cells taken up by the instruction, including the opcode.
+  pub fn length(&self) -> usize {
+    match self {
+      Operation::Add(_)
+      | Operation::Multiply(_)
+      | Operation::LessThan(_)
+      | Operation::Equals(_) => 4,
+      Operation::JumpIfTrue(_) | Operation::JumpIfFalse(_) => 3,
+      Operation::Input(_) | Operation::Output(_) | Operation::RelBaseOffset(_) => 2,
+      Operation::Halt => 1,
+    }
+  }
+
+  pub fn modes(&self) -> &[OperationMode] {
+    match self {
+      Operation::Add(m)
+      | Operation::Multiply(m)
+      | Operation::LessThan(m)
+      | Operation::Equals(m) => m,
+      Operation::JumpIfTrue(m) | Operation::JumpIfFalse(m) => m,
+      Operation::Input(m) | Operation::Output(m) | Operation::RelBaseOffset(m) => m,
+      Operation::Halt => &[],
+    }
+  }
+
+  pub fn mnemonic(&self) -> &'static str {
+    match self {
+      Operation::Add(_) => "add",
+      Operation::Multiply(_) => "mul",
+      Operation::Input(_) => "in",
+      Operation::Output(_) => "out",
+      Operation::JumpIfTrue(_) => "jnz",
+      Operation::JumpIfFalse(_) => "jz",
+      Operation::LessThan(_) => "lt",
+      Operation::Equals(_) => "eq",
+      Operation::RelBaseOffset(_) => "arb",
+      Operation::Halt => "hlt",
+    }
+  }
+
+  /// Formats the instruction starting at `cells[0]`, e.g. `add [9], 3, [rb-2]`. Parameters past
+  /// the end of `cells` are shown as 0, the same as reads past the end of memory.
+  pub fn disassemble(cells: &[isize]) -> String {
+    let opcode = *cells.first().unwrap_or(&0);
+    let operation = match Operation::decode(opcode) {
+      Some(o) => o,
+      None => return format!("invalid opcode {}", opcode),
+    };
+    let params: Vec<String> = operation
+      .modes()
+      .iter()
+      .enumerate()
+      .map(|(i, mode)| mode.format(*cells.get(i + 1).unwrap_or(&0)))
+      .collect();
+    match params.is_empty() {
+      true => operation.mnemonic().to_string(),
+      false => format!("{} {}", operation.mnemonic(), params.join(", ")),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum OperationMode {
   Position,
   Immediate,
@@ -20,12 +116,31 @@ pub enum OperationMode {
 }
 
 impl OperationMode {
-  pub fn from(i: isize) -> OperationMode {
+  pub fn decode(i: isize) -> Option<OperationMode> {
     match i {
-      0 => OperationMode::Position,
-      1 => OperationMode::Immediate,
-      2 => OperationMode::Relative,
-      _ => panic!("No operation mode: {}", i),
+      0 => Some(OperationMode::Position),
+      1 => Some(OperationMode::Immediate),
+      2 => Some(OperationMode::Relative),
+      _ => None,
+    }
+  }
+
+  fn format(&self, param: isize) -> String {
+    match self {
+      OperationMode::Position => format!("[{}]", param),
+      OperationMode::Immediate => param.to_string(),
+      OperationMode::Relative if param < 0 => format!("[rb-{}]", -param),
+      OperationMode::Relative => format!("[rb+{}]", param),
+    }
+  }
+}
+
+impl fmt::Display for OperationMode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      OperationMode::Position => write!(f, "position"),
+      OperationMode::Immediate => write!(f, "immediate"),
+      OperationMode::Relative => write!(f, "relative"),
     }
   }
 }